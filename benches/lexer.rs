@@ -0,0 +1,78 @@
+// Lexing and parsing must stay linear in the size of the source: the time per byte for a
+// 1 MB program may not grow much beyond the time per byte for a 64 KB one. Programs whose
+// body is mostly excluded by a false `{$IFDEF}` are measured as well.
+//
+// Run with `cargo bench --bench lexer`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use pascal_interpreter::{parser::Parser, tokenize, LexerOptions};
+
+const RUNS: u32 = 5;
+const MAX_SLOWDOWN: f64 = 4.0;
+const SIZES: [usize; 3] = [64 * 1024, 256 * 1024, 1024 * 1024];
+
+// a program of roughly `size` bytes, with non-ASCII comments so byte and char offsets differ
+fn program(size: usize) -> String {
+    let mut text = String::from("program Bench;\nvar x, y : integer;\nbegin\n");
+    while text.len() < size {
+        text.push_str("  x := x + 1; { café ☕ }\n");
+        text.push_str("  y := (x * 2) div 3 - $1F (* ünïcödé *);\n");
+    }
+    text.push_str("  x := 0\nend.\n");
+    text
+}
+
+// the same statements, all but the last one inside a conditional that is never compiled
+fn inactive_program(size: usize) -> String {
+    let mut text = String::from("program Bench;\nvar x, y : integer;\nbegin\n{$IFDEF NEVER}\n");
+    while text.len() < size {
+        text.push_str("  x := x + 1; { café ☕ }\n");
+        text.push_str("  y := (x * 2) div 3 - $1F (* ünïcödé *);\n");
+    }
+    text.push_str("{$ENDIF}\n  x := 0\nend.\n");
+    text
+}
+
+fn lex(text: &str) {
+    black_box(tokenize(black_box(text)).expect("benchmark program must lex"));
+}
+
+fn parse(text: &str) {
+    let mut parser = Parser::from(black_box(text), LexerOptions::default());
+    black_box(parser.parse().expect("benchmark program must parse"));
+}
+
+fn best_of(text: &str, stage: fn(&str)) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            stage(text);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn check(name: &str, program: fn(usize) -> String, stage: fn(&str)) {
+    let mut per_byte = Vec::new();
+    for size in SIZES.iter() {
+        let text = program(*size);
+        let elapsed = best_of(&text, stage);
+        let ns_per_byte = elapsed.as_nanos() as f64 / text.len() as f64;
+        println!("{:<16} {:>8} bytes: {:>10.3?} ({:.2} ns/byte)", name, text.len(), elapsed, ns_per_byte);
+        per_byte.push(ns_per_byte);
+    }
+
+    let slowdown = per_byte[per_byte.len() - 1] / per_byte[0];
+    println!("{:<16} slowdown per byte from 64 KB to 1 MB: {:.2}x", name, slowdown);
+    assert!(slowdown < MAX_SLOWDOWN, "{} is not linear: {:.2}x slower per byte", name, slowdown);
+}
+
+fn main() {
+    check("tokenize", program, lex);
+    check("parse", program, parse);
+    check("tokenize ifdef", inactive_program, lex);
+    check("parse ifdef", inactive_program, parse);
+}
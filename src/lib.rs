@@ -0,0 +1,29 @@
+//! Interpreter for a subset of Pascal, usable as a library by tools such as highlighters and linters.
+//!
+//! [`Engine`] is the entry point for running programs. It chains the [`parser`], the
+//! [`semantic_analyzer`] and the [`interpreter`], and reports which stage a run stopped at:
+//!
+//! ```no_run
+//! use pascal_interpreter::{Engine, Outcome, RunOptions};
+//!
+//! let source = "program Main; var x : integer; begin x := 6 * 7 end.";
+//! match Engine::new().run_source(source, &RunOptions::default()) {
+//!     Outcome::Completed(globals) => println!("x = {:?}", globals.get_item("x")),
+//!     Outcome::SyntaxError(err) | Outcome::SemanticError(err) => eprintln!("rejected: {}", err),
+//!     Outcome::RuntimeError(err) => eprintln!("failed: {}", err),
+//! }
+//! ```
+//!
+//! Globals can be given values before a run with [`Engine::set_global`] and read back afterwards
+//! with [`Engine::get_global`].
+//!
+//! Diagnostics of the stages are logged through the `log` facade, see [`logging`].
+//! Runs can be followed statement by statement through an [`observer`], e.g. to trace them, and
+//! stopped at breakpoints and stepped through with the [`debugger`].
+//!
+//! The stages can also be used on their own: [`tokenize`] for highlighting, [`parser::Parser`] for
+//! the [`ast`] and the lossless [`cst`], and [`format`](mod@format) for canonical formatting.
+
+mod pascal_interpreter;
+
+pub use pascal_interpreter::*;
@@ -1,41 +1,383 @@
-use std::io;
+use pascal_interpreter::{
+    ast::{dot, outline},
+    debugger::{console::Console, Debugger, Resume},
+    error::RuntimeError,
+    format::{self, BeginPlacement, FormatOptions, KeywordCase},
+    literal::Literal,
+    logging,
+    native::NativeFunction,
+    observer::Tracer,
+    parser::Parser,
+    repl::{Repl, Reply},
+    semantic_analyzer::SemanticAnalyzer,
+    symbols::Type,
+    Engine,
+    LexerOptions,
+    Outcome,
+    RunOptions,
+};
+
+use std::convert::TryFrom;
+use std::env;
 use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+Usage: pascal <command> [options] <file> [arguments...]
+
+Commands:
+  run <file> [arguments...]   run the program, passing it the arguments for ParamStr and ParamCount
+  debug <file> [arguments...] run the program in the debugger, stopping at its first statement
+  check <file>                parse and analyze the program without running it
+  tokens <file>               list the tokens of the program
+  ast <file>                  print the syntax tree of the program
+  fmt <file>                  print the program formatted
+  repl                        read and run declarations, statements and expressions interactively
+
+A <file> of - reads the program from standard input.
+
+Options:
+  --log <SPEC>        log diagnostics to stderr, e.g. debug or warn,exec=trace;
+                      targets are lexer, parser, sema and exec, nothing is logged without it
+  --nested-comments   allow comments inside comments
+  -d<NAME>            define the conditional symbol NAME, as with FPC
+  --globals           run, debug: print the global variables once the program finished
+  --trace             run: print each statement, assignment and call to stderr, like a step mode
+  --trace-expressions run: trace the value of every expression as well
+  --dot               ast: print the tree in Graphviz DOT
+  --calls             ast: print the procedure call graph in Graphviz DOT
+  --json              ast: print the tree as JSON, if built with the json feature
+  --upper             fmt: write keywords in upper case
+  --preserve-case     fmt: keep keywords as written
+  --indent <N>        fmt: indent by N spaces
+  --width <N>         fmt: wrap lines longer than N characters
+  --indent-begin      fmt: indent begin..end blocks under their header
 
-mod pascal_interpreter;
+Exit codes: 0 success, 1 usage or input error, 2 syntax error, 3 semantic error, 4 runtime error";
 
-fn interpret_text(program_text: &str) -> Result<(), pascal_interpreter::error::RuntimeError> {
-    let mut parser = pascal_interpreter::parser::Parser::from(program_text);
-    let mut program = parser.parse()?;
-    println!("Parse success");
-      
-    let mut syntax_analyzer = pascal_interpreter::semantic_analyzer::SemanticAnalyzer::new();
-    syntax_analyzer.analyze(&mut program)?;
-    println!("Syntax analysis success");
-    
-    let mut interpreter =  pascal_interpreter::interpreter::Interpreter::new();
-    let result = interpreter.interpret(&mut program);
-    println!("Memory is {:?}", interpreter.memory_tester);
-    result.map(|_| ())
+const EXIT_USAGE: i32 = 1;
+const EXIT_SYNTAX: i32 = 2;
+const EXIT_SEMANTIC: i32 = 3;
+const EXIT_RUNTIME: i32 = 4;
+
+// options of all commands, each command only looks at its own
+#[derive(Default)]
+struct Options {
+    log: Option<String>,
+    lexer: LexerOptions,
+    globals: bool,
+    trace: bool,
+    trace_expressions: bool,
+    dot: bool,
+    calls: bool,
+    json: bool,
+    format: FormatOptions,
 }
 
-fn main() {
+struct Command {
+    name: String,
+    options: Options,
+    file: Option<String>,
+    // arguments after the file, passed on to the program
+    arguments: Vec<String>,
+}
+
+fn parse_command_line(args: &[String]) -> Result<Command, String> {
+    let name = args.first().ok_or("missing command")?.clone();
+    let mut options = Options::default();
+    let mut rest = args[1..].iter();
+    let mut file = None;
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--log" => {
+                let spec = rest.next().ok_or("--log needs a level or a list of target=level")?;
+                options.log = Some(spec.clone());
+            },
+            "--nested-comments" => options.lexer.nested_comments = true,
+            "--globals" => options.globals = true,
+            "--trace" => options.trace = true,
+            "--trace-expressions" => {
+                options.trace = true;
+                options.trace_expressions = true;
+            },
+            "--dot" => options.dot = true,
+            "--calls" => options.calls = true,
+            "--json" => options.json = true,
+            "--upper" => options.format.keyword_case = KeywordCase::Upper,
+            "--preserve-case" => options.format.keyword_case = KeywordCase::Preserve,
+            "--indent-begin" => options.format.begin_placement = BeginPlacement::Indented,
+            "--indent" => options.format.indent_width = number_option(arg, rest.next())?,
+            "--width" => options.format.max_line_length = number_option(arg, rest.next())?,
+            _ if arg.starts_with("-d") && arg.len() > 2 => { options.lexer.defines.insert(arg[2..].to_owned()); },
+            _ if arg.starts_with('-') && arg != "-" => return Err(format!("unknown option {}", arg)),
+            _ => {
+                file = Some(arg.clone());
+                break
+            }
+        }
+    }
+    if let Some(spec) = &options.log {
+        logging::init(spec)?;
+    }
+    Ok(Command { name, options, file, arguments: rest.cloned().collect() })
+}
+
+fn number_option(option: &str, value: Option<&String>) -> Result<usize, String> {
+    value.and_then(|value| value.parse().ok()).ok_or_else(|| format!("{} needs a number", option))
+}
+
+fn read_source(file: &str) -> io::Result<String> {
+    if file == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        return Ok(source);
+    }
+    fs::read_to_string(file)
+}
+
+// `ParamStr(0)` is the program file, as Pascal has it
+fn param_natives(file: &str, arguments: &[String]) -> Vec<NativeFunction> {
+    let count = arguments.len() as i64;
+    let mut params = vec![file.to_owned()];
+    params.extend(arguments.iter().cloned());
+    vec![
+        NativeFunction::function("ParamCount", &[], Type::Integer, move |_| Ok(Literal::from_int(count))),
+        NativeFunction::function("ParamStr", &[Type::Integer], Type::String, move |args| {
+            let index = args[0].to_int().unwrap_or(-1);
+            let param = usize::try_from(index).ok().and_then(|index| params.get(index)).cloned().unwrap_or_default();
+            Ok(Literal::from_str(param))
+        }),
+    ]
+}
+
+fn report(stage: &str, err: &RuntimeError) {
+    eprintln!("{} error: {}", stage, err);
+    report_backtrace(err);
+}
+
+fn report_backtrace(err: &RuntimeError) {
+    if let Some(backtrace) = err.backtrace() {
+        eprint!("{}", backtrace);
+    }
+}
+
+fn run(file: &str, source: &str, command: &Command) -> i32 {
+    let mut engine = Engine::new();
+    for native in param_natives(file, &command.arguments) {
+        engine.register_native(native);
+    }
+    if command.options.trace {
+        engine.add_observer(Box::new(Tracer::new(source, io::stderr()).with_expressions(command.options.trace_expressions)));
+    }
+    let options = RunOptions { lexer: command.options.lexer.clone(), ..RunOptions::default() };
+    let outcome = engine.run_source(source, &options);
+    exit_code(outcome, command)
+}
+
+fn debug(file: &str, source: &str, command: &Command) -> i32 {
+    let mut engine = Engine::new();
+    for native in param_natives(file, &command.arguments) {
+        engine.register_native(native);
+    }
+    let options = RunOptions { lexer: command.options.lexer.clone(), ..RunOptions::default() };
+    println!("Pascal debugger, help lists the commands");
+    let mut debugger = Debugger::new(Console::new(source, io::stdin().lock(), io::stdout()));
+    match debugger.run(&mut engine, source, &options, Resume::StepIn) {
+        Outcome::RuntimeError(RuntimeError::WithBacktrace(cause, _)) if *cause == RuntimeError::Stopped => {
+            println!("stopped");
+            0
+        },
+        outcome => {
+            if outcome.is_completed() {
+                println!("finished");
+            }
+            exit_code(outcome, command)
+        }
+    }
+}
+
+fn exit_code(outcome: Outcome, command: &Command) -> i32 {
+    match outcome {
+        Outcome::Completed(globals) => {
+            if command.options.globals {
+                let mut members: Vec<(&str, &Literal)> = globals.members().collect();
+                members.sort_by_key(|(name, _)| *name);
+                for (name, value) in members {
+                    println!("{} = {}", name, value);
+                }
+            }
+            0
+        },
+        Outcome::SyntaxError(err) => {
+            report("syntax", &err);
+            EXIT_SYNTAX
+        },
+        Outcome::SemanticError(err) => {
+            report("semantic", &err);
+            EXIT_SEMANTIC
+        },
+        Outcome::RuntimeError(err) => {
+            report("runtime", &err);
+            EXIT_RUNTIME
+        }
+    }
+}
+
+fn check(file: &str, source: &str, command: &Command) -> i32 {
+    let mut program = match Parser::from(source, command.options.lexer.clone()).parse() {
+        Ok(program) => program,
+        Err(err) => {
+            report("syntax", &err);
+            return EXIT_SYNTAX
+        }
+    };
+    let mut analyzer = SemanticAnalyzer::new();
+    for native in param_natives(file, &command.arguments) {
+        analyzer.define_native(&native);
+    }
+    match analyzer.analyze(&mut program) {
+        Ok(_) => 0,
+        Err(err) => {
+            report("semantic", &err);
+            EXIT_SEMANTIC
+        }
+    }
+}
+
+fn tokens(source: &str, command: &Command) -> i32 {
+    match pascal_interpreter::tokenize_with_options(source, command.options.lexer.clone()) {
+        Ok(tokens) => {
+            for token in tokens.iter().filter(|token| !token.text().is_empty()) {
+                println!("{}:{}\t{:?}\t{}", token.line(), token.col(), token.token_type(), token.text());
+            }
+            0
+        },
+        Err(err) => {
+            report("syntax", &err);
+            EXIT_SYNTAX
+        }
+    }
+}
+
+fn ast(source: &str, command: &Command) -> i32 {
+    let program = match Parser::from(source, command.options.lexer.clone()).parse() {
+        Ok(program) => program,
+        Err(err) => {
+            report("syntax", &err);
+            return EXIT_SYNTAX
+        }
+    };
+    let options = &command.options;
+    if options.json {
+        #[cfg(feature = "json")]
+        match pascal_interpreter::ast::json::to_json(&program) {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                report("syntax", &err);
+                return EXIT_SYNTAX
+            }
+        }
+        #[cfg(not(feature = "json"))]
+        {
+            eprintln!("--json needs a build with the json feature");
+            return EXIT_USAGE
+        }
+    } else if options.calls {
+        print!("{}", dot::call_graph_to_dot(&program));
+    } else if options.dot {
+        print!("{}", dot::ast_to_dot(&program));
+    } else {
+        print!("{}", outline::program_outline(&program));
+    }
+    0
+}
+
+fn fmt(source: &str, command: &Command) -> i32 {
+    match Parser::from(source, command.options.lexer.clone()).parse_with_syntax() {
+        Ok((_, syntax)) => {
+            print!("{}", format::format(&syntax, &command.options.format));
+            0
+        },
+        Err(err) => {
+            report("syntax", &err);
+            EXIT_SYNTAX
+        }
+    }
+}
+
+fn repl(options: LexerOptions) {
+    let mut repl = Repl::new(options);
+    println!("Pascal REPL, :help lists the commands");
     loop {
-        println!("load from file >>>");
-        let mut filename = String::new();
-        io::stdin()
-            .read_line(&mut filename)
-            .expect("Failed to read input");
+        print!("{}", if repl.is_continuing() { "... " } else { ">>> " });
+        io::stdout().flush().expect("Failed to write prompt");
 
-        if filename.is_empty() {
-            continue
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).expect("Failed to read input") == 0 {
+            break
         }
+        match repl.feed(line.trim_end_matches(&['\r', '\n'][..])) {
+            Reply::Continue => {},
+            Reply::Output(output) if output.is_empty() => {},
+            Reply::Output(output) => println!("{}", output),
+            Reply::Failed(err) => {
+                eprintln!("error: {}", err);
+                report_backtrace(&err);
+            },
+            Reply::Quit => break
+        }
+    }
+}
 
-        let content = fs::read_to_string(&filename.trim_end());
-        if content.is_ok() {
-            let result = interpret_text(&content.unwrap());
-            println!("Program result is {:?}", result);
-        } else{
-            println!("Failed to read file {}, reason:{:?}", &filename, &content);
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = match parse_command_line(&args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(EXIT_USAGE)
         }
+    };
+
+    if command.name == "repl" {
+        return repl(command.options.lexer);
+    }
+    if command.name == "help" || command.name == "--help" || command.name == "-h" {
+        println!("{}", USAGE);
+        return
+    }
+
+    if !["run", "debug", "check", "tokens", "ast", "fmt"].contains(&command.name.as_str()) {
+        eprintln!("unknown command {}\n\n{}", command.name, USAGE);
+        process::exit(EXIT_USAGE)
     }
+    let file = match &command.file {
+        Some(file) => file.clone(),
+        None => {
+            eprintln!("{} needs a file, or - for standard input\n\n{}", command.name, USAGE);
+            process::exit(EXIT_USAGE)
+        }
+    };
+    if command.name != "run" && command.name != "debug" && !command.arguments.is_empty() {
+        eprintln!("unexpected arguments after {}\n\n{}", file, USAGE);
+        process::exit(EXIT_USAGE)
+    }
+    let source = match read_source(&file) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("cannot read {}: {}", file, err);
+            process::exit(EXIT_USAGE)
+        }
+    };
+
+    let code = match command.name.as_str() {
+        "run" => run(&file, &source, &command),
+        "debug" => debug(&file, &source, &command),
+        "check" => check(&file, &source, &command),
+        "tokens" => tokens(&source, &command),
+        "ast" => ast(&source, &command),
+        _ => fmt(&source, &command),
+    };
+    process::exit(code)
 }
@@ -0,0 +1,209 @@
+//! Graphviz DOT export of the AST and of the procedure call graph, for viewing with `dot -Tsvg`.
+//!
+//! In the call graph every procedure, and the main block of the program, is a node inside a cluster
+//! for its scope, so nested procedures are drawn inside the procedure that declares them. Calls are
+//! resolved the way the semantic analyzer resolves them; a call to a name that is not declared
+//! anywhere on the way out to the global scope goes to a dashed node of its own.
+
+use crate::pascal_interpreter::{
+    ast::{
+        nodes::*,
+        visit::{self, Visitor},
+    },
+    literal::Literal,
+    symbols,
+    token::Token,
+};
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write;
+
+pub fn ast_to_dot(program: &ProgramNode) -> String {
+    let mut graph = AstGraph { out: String::new(), next_id: 0, parents: Vec::new() };
+    graph.out.push_str("digraph ast {\n  node [shape=box, fontname=\"monospace\"];\n");
+    graph.visit_program(program);
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+pub fn call_graph_to_dot(program: &ProgramNode) -> String {
+    let name = program.name.to_str().unwrap_or_default();
+    let mut graph = CallGraph {
+        out: String::new(),
+        scopes: vec![Scope { path: String::from("global"), procedures: procedure_names(&program.block) }],
+        calls: BTreeMap::new(),
+        unresolved: BTreeSet::new(),
+    };
+    graph.out.push_str("digraph calls {\n  node [shape=box, fontname=\"monospace\"];\n");
+    graph.scope(name, &format!("program {}", name), &program.block, 1);
+    for name in &graph.unresolved {
+        writeln!(graph.out, "  \"?{}\" [label=\"{}\", style=dashed];", escape(name), escape(name)).unwrap();
+    }
+    for ((caller, callee), count) in &graph.calls {
+        let label = if *count > 1 { format!(" [label=\"{}\"]", count) } else { String::new() };
+        writeln!(graph.out, "  \"{}\" -> \"{}\"{};", escape(caller), escape(callee), label).unwrap();
+    }
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+// `"` and `\` are the only characters with a meaning inside a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub(crate) fn token_text(token: &Token) -> String {
+    match token.literal() {
+        _ if !token.text().is_empty() => token.text().to_owned(),
+        Literal::Text(text) => text.clone(),
+        literal => literal.to_string()
+    }
+}
+
+struct AstGraph {
+    out: String,
+    next_id: usize,
+    parents: Vec<usize>,
+}
+
+impl AstGraph {
+    // writes a node under the current parent, then the children `walk` visits under it
+    fn node(&mut self, label: String, walk: impl FnOnce(&mut Self)) {
+        let id = self.next_id;
+        self.next_id += 1;
+        writeln!(self.out, "  n{} [label=\"{}\"];", id, escape(&label)).unwrap();
+        if let Some(parent) = self.parents.last() {
+            writeln!(self.out, "  n{} -> n{};", parent, id).unwrap();
+        }
+        self.parents.push(id);
+        walk(self);
+        self.parents.pop();
+    }
+}
+
+impl Visitor for AstGraph {
+    fn visit_program(&mut self, node: &ProgramNode) {
+        self.node(format!("Program {}", node.name.to_str().unwrap_or_default()), |graph| visit::walk_program(graph, node));
+    }
+
+    fn visit_block(&mut self, node: &BlockNode) {
+        self.node(String::from("Block"), |graph| visit::walk_block(graph, node));
+    }
+
+    fn visit_var_decl(&mut self, node: &VarDeclNode) {
+        self.node(String::from("VarDecl"), |graph| visit::walk_var_decl(graph, node));
+    }
+
+    fn visit_type(&mut self, node: &TypeNode) {
+        self.node(format!("Type {}", token_text(&node.token)), |_| {});
+    }
+
+    fn visit_procedure_decl(&mut self, node: &ProcedureDeclNode) {
+        self.node(format!("ProcedureDecl {}", node.name.to_str().unwrap_or_default()), |graph| visit::walk_procedure_decl(graph, node));
+    }
+
+    fn visit_param(&mut self, node: &ParamNode) {
+        self.node(String::from("Param"), |graph| visit::walk_param(graph, node));
+    }
+
+    fn visit_compound(&mut self, node: &CompoundStatementNode) {
+        self.node(String::from("Compound"), |graph| visit::walk_compound(graph, node));
+    }
+
+    fn visit_assignment(&mut self, node: &AssignmentNode) {
+        self.node(String::from(":="), |graph| visit::walk_assignment(graph, node));
+    }
+
+    fn visit_procedure_call(&mut self, node: &ProcedureCallNode) {
+        self.node(format!("Call {}", token_text(&node.name)), |graph| visit::walk_procedure_call(graph, node));
+    }
+
+    fn visit_no_op(&mut self, _: &NoOpNode) {
+        self.node(String::from("NoOp"), |_| {});
+    }
+
+    fn visit_num(&mut self, node: &NumNode) {
+        self.node(format!("Num {}", token_text(&node.token)), |_| {});
+    }
+
+    fn visit_var(&mut self, node: &VarNode) {
+        self.node(format!("Var {}", token_text(&node.name)), |_| {});
+    }
+
+    fn visit_binary_op(&mut self, node: &BinaryOpNode) {
+        self.node(format!("BinaryOp {}", token_text(&node.op)), |graph| visit::walk_binary_op(graph, node));
+    }
+
+    fn visit_unary_op(&mut self, node: &UnaryOpNode) {
+        self.node(format!("UnaryOp {}", token_text(&node.op)), |graph| visit::walk_unary_op(graph, node));
+    }
+}
+
+// procedures declared directly in a block, by lowercase name
+fn procedure_names(block: &BlockNode) -> HashSet<String> {
+    block.declarations.iter().filter_map(|decl| match decl {
+        Decl::Procedure(procedure) => procedure.name.to_str().map(str::to_lowercase),
+        Decl::Var(_) => None
+    }).collect()
+}
+
+struct Scope {
+    path: String,
+    procedures: HashSet<String>,
+}
+
+struct CallGraph {
+    out: String,
+    // scopes from the global one to the innermost, while walking
+    scopes: Vec<Scope>,
+    // number of calls for every pair of caller and callee
+    calls: BTreeMap<(String, String), usize>,
+    unresolved: BTreeSet<String>,
+}
+
+impl CallGraph {
+    // a cluster for the scope on top of the stack, holding the node of its body and the clusters of its procedures
+    fn scope(&mut self, name: &str, label: &str, block: &BlockNode, depth: usize) {
+        let path = self.scopes.last().map(|scope| scope.path.clone()).unwrap_or_default();
+        let indent = "  ".repeat(depth);
+        writeln!(self.out, "{}subgraph \"cluster_{}\" {{", indent, escape(&path)).unwrap();
+        writeln!(self.out, "{}  label=\"{}\";", indent, escape(name)).unwrap();
+        writeln!(self.out, "{}  \"{}\" [label=\"{}\"];", indent, escape(&path), escape(label)).unwrap();
+        for decl in &block.declarations {
+            if let Decl::Procedure(procedure) = decl {
+                let name = procedure.name.to_str().unwrap_or_default();
+                let params: Vec<String> = procedure.params.iter().map(|param| token_text(&param.var.name)).collect();
+                self.scopes.push(Scope { path: symbols::qualified_name(&path, name), procedures: procedure_names(&procedure.block) });
+                self.scope(name, &format!("{}({})", name, params.join(", ")), &procedure.block, depth + 1);
+                self.scopes.pop();
+            }
+        }
+        self.visit_compound(&block.compound_statement);
+        writeln!(self.out, "{}}}", indent).unwrap();
+    }
+
+    // qualified name of the procedure a call made from the current scope goes to
+    fn resolve(&self, name: &str) -> Option<String> {
+        let name = name.to_lowercase();
+        self.scopes.iter().rev()
+            .find(|scope| scope.procedures.contains(&name))
+            .map(|scope| symbols::qualified_name(&scope.path, &name))
+    }
+}
+
+// only reached for the statements of a body, declarations are walked by `scope`
+impl Visitor for CallGraph {
+    fn visit_procedure_call(&mut self, node: &ProcedureCallNode) {
+        let name = token_text(&node.name);
+        let callee = match &node.proc_symbol {
+            Some(symbols::Symbol::Procedure(_, qualified_name)) => qualified_name.clone(),
+            _ => self.resolve(&name).unwrap_or_else(|| {
+                self.unresolved.insert(name.clone());
+                format!("?{}", name)
+            })
+        };
+        let caller = self.scopes.last().map(|scope| scope.path.clone()).unwrap_or_default();
+        *self.calls.entry((caller, callee)).or_insert(0) += 1;
+        visit::walk_procedure_call(self, node);
+    }
+}
@@ -0,0 +1,458 @@
+//! JSON form of the AST, enabled by the `json` feature.
+//!
+//! The schema is versioned and independent of the node types, so it only changes with
+//! [`VERSION`]. A document holds the version and the program:
+//!
+//! ```text
+//! {"version": 1, "program": {"name": "Main", "block": {"declarations": [...], "statements": [...]}}}
+//! ```
+//!
+//! Declarations, statements and expressions are objects tagged by `kind`:
+//!
+//! - declarations: `{"kind": "var", "name": "x", "type": "integer"}` with a type of `integer`,
+//!   `real` or `string`, and `{"kind": "procedure", "name": "P", "params": [{"name": "n", "type": "real"}], "block": {...}}`
+//! - statements: `compound` with `statements`, `assignment` with `target` and `value`, `call` with
+//!   `name` and `arguments`, and `empty`
+//! - expressions: `number` with a `value` of `{"integer": 1}` or `{"real": 1.5}`, `variable` with
+//!   `name`, `binary` with `op` (`+ - * / div mod %`), `left` and `right`, `unary` with `op`
+//!   (`+ -`) and `operand`, and `call`
+//!
+//! Nodes written from a parsed program carry `"at": {"line": 3, "col": 5}`, which may be left out
+//! when loading. Operators carry `overflow_checks`, true unless the program turned them off with
+//! `{$Q-}`. Spans, trivia and the other switches are not part of the schema, nor are the
+//! procedures the semantic analyzer resolved calls to: a loaded tree has to be analyzed before it
+//! can be interpreted. Loading checks names, types and operators, and fails with
+//! `RuntimeError::InvalidAstJson` on anything the schema does not allow.
+
+use crate::pascal_interpreter::{
+    ast::nodes::*,
+    directive::{Switch, Switches},
+    error::RuntimeError,
+    lexer::tokenize,
+    literal::Literal,
+    token::{Span, Token, TokenType},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the schema written by [`to_json`], the only one [`from_json`] reads
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    version: u32,
+    program: Program,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Program {
+    name: String,
+    block: Block,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Block {
+    declarations: Vec<Declaration>,
+    statements: Vec<Statement>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum Declaration {
+    Var {
+        name: String,
+        #[serde(rename = "type")]
+        var_type: TypeName,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<At>,
+    },
+    Procedure {
+        name: String,
+        params: Vec<Param>,
+        block: Block,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Param {
+    name: String,
+    #[serde(rename = "type")]
+    param_type: TypeName,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    at: Option<At>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum Statement {
+    Compound {
+        statements: Vec<Statement>,
+    },
+    Assignment {
+        target: String,
+        value: Expression,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<At>,
+    },
+    Call {
+        name: String,
+        arguments: Vec<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<At>,
+    },
+    Empty,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum Expression {
+    Number {
+        value: Number,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<At>,
+    },
+    Variable {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<At>,
+    },
+    Binary {
+        op: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+        #[serde(default = "enabled")]
+        overflow_checks: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<At>,
+    },
+    Unary {
+        op: UnaryOperator,
+        operand: Box<Expression>,
+        #[serde(default = "enabled")]
+        overflow_checks: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<At>,
+    },
+    Call {
+        name: String,
+        arguments: Vec<Expression>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<At>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum Number {
+    Integer(i64),
+    Real(f64),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum TypeName {
+    Integer,
+    Real,
+    String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+enum BinaryOperator {
+    #[serde(rename = "+")]
+    Plus,
+    #[serde(rename = "-")]
+    Minus,
+    #[serde(rename = "*")]
+    Multiply,
+    #[serde(rename = "/")]
+    Division,
+    #[serde(rename = "div")]
+    IntegerDivision,
+    #[serde(rename = "mod")]
+    Mod,
+    #[serde(rename = "%")]
+    Modulus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+enum UnaryOperator {
+    #[serde(rename = "+")]
+    Plus,
+    #[serde(rename = "-")]
+    Minus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+struct At {
+    line: u32,
+    col: u32,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Write the program in the schema of [`VERSION`]. Fails for trees the parser does not build,
+/// such as numbers holding strings.
+pub fn to_json(program: &ProgramNode) -> Result<String, RuntimeError> {
+    let document = Document {
+        version: VERSION,
+        program: Program { name: name_text(&program.name), block: write_block(&program.block)? },
+    };
+    serde_json::to_string_pretty(&document).map_err(|err| RuntimeError::InvalidAstJson(err.to_string()))
+}
+
+pub fn from_json(text: &str) -> Result<ProgramNode, RuntimeError> {
+    let document: Document = serde_json::from_str(text).map_err(|err| RuntimeError::InvalidAstJson(err.to_string()))?;
+    if document.version != VERSION {
+        return Err(invalid(format!("version {} is not supported, only {}", document.version, VERSION)))
+    }
+    let program = document.program;
+    let name = identifier(&program.name, None)?;
+    Ok(ProgramNode::from(name.literal().clone(), read_block(program.block)?))
+}
+
+fn invalid(message: String) -> RuntimeError {
+    RuntimeError::InvalidAstJson(message)
+}
+
+// Writing
+
+fn name_text(name: &Literal) -> String {
+    match name {
+        Literal::Text(text) => text.clone(),
+        literal => literal.to_string()
+    }
+}
+
+fn token_name(token: &Token) -> String {
+    name_text(token.literal())
+}
+
+// positions of nodes built without a source are left out
+fn at(token: &Token) -> Option<At> {
+    Some(At { line: token.line(), col: token.col() }).filter(|at| at.line > 0)
+}
+
+fn write_block(block: &BlockNode) -> Result<Block, RuntimeError> {
+    let declarations = block.declarations.iter().map(write_declaration).collect::<Result<_, _>>()?;
+    let statements = block.compound_statement.child_statements.iter().map(write_statement).collect::<Result<_, _>>()?;
+    Ok(Block { declarations, statements })
+}
+
+fn write_declaration(decl: &Decl) -> Result<Declaration, RuntimeError> {
+    match decl {
+        Decl::Var(node) => Ok(Declaration::Var {
+            name: token_name(&node.var.name),
+            var_type: write_type(&node.type_spec)?,
+            at: at(&node.var.name),
+        }),
+        Decl::Procedure(node) => Ok(Declaration::Procedure {
+            name: name_text(&node.name),
+            params: node.params.iter()
+                .map(|param| Ok(Param { name: token_name(&param.var.name), param_type: write_type(&param.param_type)?, at: at(&param.var.name) }))
+                .collect::<Result<_, RuntimeError>>()?,
+            block: write_block(&node.block)?,
+        }),
+    }
+}
+
+fn write_type(type_node: &TypeNode) -> Result<TypeName, RuntimeError> {
+    match type_node.token.token_type() {
+        TokenType::IntegerType => Ok(TypeName::Integer),
+        TokenType::FloatType => Ok(TypeName::Real),
+        TokenType::StringType => Ok(TypeName::String),
+        token_type => Err(invalid(format!("{:?} is not a type", token_type)))
+    }
+}
+
+fn write_statement(stmt: &Stmt) -> Result<Statement, RuntimeError> {
+    match stmt {
+        Stmt::Compound(node) => Ok(Statement::Compound {
+            statements: node.child_statements.iter().map(write_statement).collect::<Result<_, _>>()?,
+        }),
+        Stmt::Assignment(node) => Ok(Statement::Assignment {
+            target: token_name(&node.left.name),
+            value: write_expression(&node.right)?,
+            at: at(&node.left.name),
+        }),
+        Stmt::ProcedureCall(node) => {
+            let (name, arguments, at) = write_call(node)?;
+            Ok(Statement::Call { name, arguments, at })
+        },
+        Stmt::NoOp(_) => Ok(Statement::Empty),
+    }
+}
+
+fn write_call(node: &ProcedureCallNode) -> Result<(String, Vec<Expression>, Option<At>), RuntimeError> {
+    let arguments = node.actual_params.iter().map(write_expression).collect::<Result<_, _>>()?;
+    Ok((token_name(&node.name), arguments, at(&node.name)))
+}
+
+fn write_expression(expr: &Expr) -> Result<Expression, RuntimeError> {
+    match expr {
+        Expr::Num(node) => {
+            let value = match node.token.literal() {
+                Literal::Int(int) => Number::Integer(*int),
+                Literal::Float(float) => Number::Real(*float),
+                literal => return Err(invalid(format!("{} is not a number", literal)))
+            };
+            Ok(Expression::Number { value, at: at(&node.token) })
+        },
+        Expr::Var(node) => Ok(Expression::Variable { name: token_name(&node.name), at: at(&node.name) }),
+        Expr::BinaryOp(node) => {
+            let op = match node.op.token_type() {
+                TokenType::Plus => BinaryOperator::Plus,
+                TokenType::Minus => BinaryOperator::Minus,
+                TokenType::Multiply => BinaryOperator::Multiply,
+                TokenType::Division => BinaryOperator::Division,
+                TokenType::IntegerDivision => BinaryOperator::IntegerDivision,
+                TokenType::Mod => BinaryOperator::Mod,
+                TokenType::Modulus => BinaryOperator::Modulus,
+                token_type => return Err(invalid(format!("{:?} is not a binary operator", token_type)))
+            };
+            Ok(Expression::Binary {
+                op,
+                left: Box::new(write_expression(&node.left_side)?),
+                right: Box::new(write_expression(&node.right_side)?),
+                overflow_checks: node.checks.is_enabled(Switch::OverflowChecks),
+                at: at(&node.op),
+            })
+        },
+        Expr::UnaryOp(node) => {
+            let op = match node.op.token_type() {
+                TokenType::Plus => UnaryOperator::Plus,
+                TokenType::Minus => UnaryOperator::Minus,
+                token_type => return Err(invalid(format!("{:?} is not a unary operator", token_type)))
+            };
+            Ok(Expression::Unary {
+                op,
+                operand: Box::new(write_expression(&node.node)?),
+                overflow_checks: node.checks.is_enabled(Switch::OverflowChecks),
+                at: at(&node.op),
+            })
+        },
+        Expr::Call(node) => {
+            let (name, arguments, at) = write_call(node)?;
+            Ok(Expression::Call { name, arguments, at })
+        },
+    }
+}
+
+// Reading
+
+// a token as the lexer reads it from `text`, placed at `at`
+fn token(token_type: TokenType, literal: Literal, text: &str, at: Option<At>) -> Token {
+    let (line, col) = at.map_or((0, 0), |at| (at.line, at.col));
+    Token::new(token_type, literal, line, col).with_source(Span::default(), text.to_owned(), Vec::new())
+}
+
+// the name has to lex as a single identifier, so it can not be empty, a number or a reserved word
+fn identifier(name: &str, at: Option<At>) -> Result<Token, RuntimeError> {
+    let tokens = tokenize(name).map_err(|_| invalid(format!("{:?} is not an identifier", name)))?;
+    match tokens.as_slice() {
+        [first, _eof] if first.token_type() == TokenType::Identifier && first.text() == name => {
+            Ok(token(TokenType::Identifier, Literal::from_str(name.to_owned()), name, at))
+        },
+        _ => Err(invalid(format!("{:?} is not an identifier", name)))
+    }
+}
+
+fn read_block(block: Block) -> Result<BlockNode, RuntimeError> {
+    let declarations = block.declarations.into_iter().map(read_declaration).collect::<Result<_, _>>()?;
+    let statements = block.statements.into_iter().map(read_statement).collect::<Result<_, _>>()?;
+    Ok(BlockNode::from(declarations, CompoundStatementNode::from(statements)))
+}
+
+fn read_declaration(declaration: Declaration) -> Result<Decl, RuntimeError> {
+    match declaration {
+        Declaration::Var { name, var_type, at } => {
+            Ok(Decl::Var(VarDeclNode::from(VarNode::from(identifier(&name, at)?), read_type(var_type))))
+        },
+        Declaration::Procedure { name, params, block } => {
+            let name = identifier(&name, None)?;
+            let params = params.into_iter()
+                .map(|param| Ok(ParamNode::from(VarNode::from(identifier(&param.name, param.at)?), read_type(param.param_type))))
+                .collect::<Result<_, RuntimeError>>()?;
+            Ok(Decl::Procedure(ProcedureDeclNode::from(name.literal().clone(), params, read_block(block)?)))
+        },
+    }
+}
+
+fn read_type(type_name: TypeName) -> TypeNode {
+    let (token_type, text) = match type_name {
+        TypeName::Integer => (TokenType::IntegerType, "integer"),
+        TypeName::Real => (TokenType::FloatType, "real"),
+        TypeName::String => (TokenType::StringType, "string"),
+    };
+    TypeNode::from(token(token_type, Literal::from_str(text.to_owned()), text, None))
+}
+
+fn read_statement(statement: Statement) -> Result<Stmt, RuntimeError> {
+    match statement {
+        Statement::Compound { statements } => {
+            let statements = statements.into_iter().map(read_statement).collect::<Result<_, _>>()?;
+            Ok(Stmt::Compound(CompoundStatementNode::from(statements)))
+        },
+        Statement::Assignment { target, value, at } => {
+            Ok(Stmt::Assignment(AssignmentNode::from(VarNode::from(identifier(&target, at)?), read_expression(value)?)))
+        },
+        Statement::Call { name, arguments, at } => Ok(Stmt::ProcedureCall(read_call(&name, arguments, at)?)),
+        Statement::Empty => Ok(Stmt::NoOp(NoOpNode {})),
+    }
+}
+
+fn read_call(name: &str, arguments: Vec<Expression>, at: Option<At>) -> Result<ProcedureCallNode, RuntimeError> {
+    let arguments = arguments.into_iter().map(read_expression).collect::<Result<_, _>>()?;
+    Ok(ProcedureCallNode::from(identifier(name, at)?, arguments))
+}
+
+fn checks(overflow_checks: bool) -> Switches {
+    let mut switches = Switches::default();
+    switches.set(Switch::OverflowChecks, overflow_checks);
+    switches
+}
+
+fn read_expression(expression: Expression) -> Result<Expr, RuntimeError> {
+    match expression {
+        Expression::Number { value, at } => {
+            let (token_type, literal) = match value {
+                Number::Integer(int) => (TokenType::IntegerConst, Literal::from_int(int)),
+                Number::Real(float) if float.is_finite() => (TokenType::FloatConst, Literal::from_float(float)),
+                Number::Real(float) => return Err(invalid(format!("{} is not a real number", float)))
+            };
+            let text = literal.to_string();
+            Ok(Expr::Num(NumNode::from(token(token_type, literal, &text, at))))
+        },
+        Expression::Variable { name, at } => Ok(Expr::Var(VarNode::from(identifier(&name, at)?))),
+        Expression::Binary { op, left, right, overflow_checks, at } => {
+            let (token_type, text) = match op {
+                BinaryOperator::Plus => (TokenType::Plus, "+"),
+                BinaryOperator::Minus => (TokenType::Minus, "-"),
+                BinaryOperator::Multiply => (TokenType::Multiply, "*"),
+                BinaryOperator::Division => (TokenType::Division, "/"),
+                BinaryOperator::IntegerDivision => (TokenType::IntegerDivision, "div"),
+                BinaryOperator::Mod => (TokenType::Mod, "mod"),
+                BinaryOperator::Modulus => (TokenType::Modulus, "%"),
+            };
+            let checks = checks(overflow_checks);
+            let op = token(token_type, Literal::from_str(text.to_owned()), text, at).with_switches(checks);
+            Ok(Expr::BinaryOp(BinaryOpNode::from(read_expression(*left)?, op, read_expression(*right)?, checks)))
+        },
+        Expression::Unary { op, operand, overflow_checks, at } => {
+            let (token_type, text) = match op {
+                UnaryOperator::Plus => (TokenType::Plus, "+"),
+                UnaryOperator::Minus => (TokenType::Minus, "-"),
+            };
+            let checks = checks(overflow_checks);
+            let op = token(token_type, Literal::from_str(text.to_owned()), text, at).with_switches(checks);
+            Ok(Expr::UnaryOp(UnaryOpNode::from(op, read_expression(*operand)?, checks)))
+        },
+        Expression::Call { name, arguments, at } => Ok(Expr::Call(read_call(&name, arguments, at)?)),
+    }
+}
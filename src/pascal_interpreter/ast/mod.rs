@@ -1 +1,6 @@
-pub mod nodes;
\ No newline at end of file
+pub mod nodes;
+pub mod visit;
+pub mod dot;
+pub mod outline;
+#[cfg(feature = "json")]
+pub mod json;
@@ -1,20 +1,18 @@
 use crate::pascal_interpreter::{
+    directive::Switches,
     literal::Literal,
     error::RuntimeError,
     symbols,
     token::{
+        Position,
+        Span,
         Token,
-        TokenType 
+        TokenType
     }
 };
 
-use std::fmt;
-use std::fmt::Debug;
-
 pub trait VisitableNode  {
     fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor)  -> Result<Option<Literal>, RuntimeError>;
-    fn box_clone(&self) -> Box<dyn VisitableNode>;
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
 }
 
 pub trait NodeVisitor {
@@ -28,275 +26,218 @@ pub trait NodeVisitor {
     fn visit_program(&mut self, _: &mut ProgramNode) -> Result<Option<Literal>, RuntimeError>;
     fn visit_block(&mut self, _: &mut BlockNode) -> Result<Option<Literal>, RuntimeError>;
     fn visit_var_decl(&mut self, _: &mut VarDeclNode) -> Result<Option<Literal>, RuntimeError>;
-    fn visit_type(&mut self, _: &mut TypeNode) -> Result<Option<Literal>, RuntimeError>;
     fn visit_procedure_decl(&mut self, _: &mut ProcedureDeclNode) -> Result<Option<Literal>, RuntimeError>;
-    fn visit_param(&mut self, _: &mut ParamNode) -> Result<Option<Literal>, RuntimeError>;
     fn visit_procedure_call(&mut self, _: &mut ProcedureCallNode) -> Result<Option<Literal>, RuntimeError>;
 }
 
-/// Node containing a singal integral token
-#[derive(Clone, Debug)]
-pub struct NumNode {
-    pub token : Token
-}
-impl NumNode {
-    pub fn from(token: Token) -> Self {
-        NumNode{ token : token }
-    }
-
+/// Expressions evaluate to a value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Num(NumNode),
+    Var(VarNode),
+    BinaryOp(BinaryOpNode),
+    UnaryOp(UnaryOpNode),
+    /// call of a function, valued by its result
+    Call(ProcedureCallNode),
 }
 
-impl VisitableNode for NumNode {
+impl VisitableNode for Expr {
     fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_num(self)
+        match self {
+            Expr::Num(node) => visitor.visit_num(node),
+            Expr::Var(node) => visitor.visit_var(node),
+            Expr::BinaryOp(node) => visitor.visit_binary_op(node),
+            Expr::UnaryOp(node) => visitor.visit_unary_op(node),
+            Expr::Call(node) => visitor.visit_procedure_call(node),
+        }
     }
+}
 
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
+impl Expr {
+    /// Source range of the expression. Parentheses around it are not part of it.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Num(node) => node.token.span(),
+            Expr::Var(node) => node.name.span(),
+            Expr::BinaryOp(node) => Span { start: node.left_side.span().start, end: node.right_side.span().end },
+            Expr::UnaryOp(node) => Span { start: node.op.span().start, end: node.node.span().end },
+            Expr::Call(node) => node.span,
+        }
     }
 
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
+    /// Line and column the expression starts at
+    pub fn position(&self) -> Position {
+        match self {
+            Expr::Num(node) => node.token.position(),
+            Expr::Var(node) => node.name.position(),
+            Expr::BinaryOp(node) => node.left_side.position(),
+            Expr::UnaryOp(node) => node.op.position(),
+            Expr::Call(node) => node.call_site(),
+        }
     }
 }
 
-/// Binary operation nodes - expr op exprs
-pub struct BinaryOpNode {
-    pub left_side: Box<dyn VisitableNode>,
-    pub op: Token,
-    pub right_side: Box<dyn VisitableNode>,
+/// Statements are executed for their effect
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stmt {
+    Compound(CompoundStatementNode),
+    Assignment(AssignmentNode),
+    ProcedureCall(ProcedureCallNode),
+    NoOp(NoOpNode),
 }
 
-impl BinaryOpNode {
-    pub fn from(left_side: Box<dyn VisitableNode>, op: Token, right_side: Box<dyn VisitableNode>) -> Self
-    {
-        BinaryOpNode{left_side: left_side, op: op, right_side: right_side }
+impl VisitableNode for Stmt {
+    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
+        match self {
+            Stmt::Compound(node) => visitor.visit_compound(node),
+            Stmt::Assignment(node) => visitor.visit_assignment(node),
+            Stmt::ProcedureCall(node) => visitor.visit_procedure_call(node),
+            Stmt::NoOp(node) => visitor.visit_no_op(node),
+        }
     }
-
 }
 
-impl VisitableNode for BinaryOpNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_binary_op(self)
+impl Stmt {
+    /// Source range of the statement, None for an empty one. A compound statement spans its
+    /// statements, without its `begin` and `end`.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Stmt::Compound(node) => {
+                let mut spans = node.child_statements.iter().filter_map(Stmt::span);
+                let first = spans.next()?;
+                let last = spans.next_back().unwrap_or(first);
+                Some(Span { start: first.start, end: last.end })
+            },
+            Stmt::Assignment(node) => Some(Span { start: node.left.name.span().start, end: node.right.span().end }),
+            Stmt::ProcedureCall(node) => Some(node.span),
+            Stmt::NoOp(_) => None
+        }
     }
 
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
+    /// Line and column the statement starts at, None for an empty one
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Stmt::Compound(node) => node.child_statements.iter().find_map(Stmt::position),
+            Stmt::Assignment(node) => Some(node.left.name.position()),
+            Stmt::ProcedureCall(node) => Some(node.call_site()),
+            Stmt::NoOp(_) => None
+        }
     }
+}
 
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
-    }
+/// Declarations of a block, in source order
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decl {
+    Var(VarDeclNode),
+    Procedure(ProcedureDeclNode),
 }
 
-impl Clone for BinaryOpNode {
-    fn clone(&self) -> Self {
-        BinaryOpNode {
-            left_side: self.left_side.box_clone(),
-            op: self.op.clone(),
-            right_side: self.right_side.box_clone(),
+impl VisitableNode for Decl {
+    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
+        match self {
+            Decl::Var(node) => visitor.visit_var_decl(node),
+            Decl::Procedure(node) => visitor.visit_procedure_decl(node),
         }
     }
 }
 
-impl fmt::Debug for BinaryOpNode {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "BinaryOp(lhs = ")?;
-        self.left_side.box_fmt(f)?;
-        write!(f, ", op = {:?}, rhs = ", self.op)?;
-        self.right_side.box_fmt(f)
-    }
+/// Piece of a program entered on its own, as the REPL reads them
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fragment {
+    Declarations(Vec<Decl>),
+    Statements(Vec<Stmt>),
+    /// expression whose value is shown
+    Expression(Expr),
 }
 
-
-/// Operation that takes operation token and single argument to work on
-pub struct UnaryOpNode {
-    pub op : Token,
-    pub node: Box<dyn VisitableNode>
+/// Node containing a singal integral token
+#[derive(Clone, Debug, PartialEq)]
+pub struct NumNode {
+    pub token : Token
 }
-
-impl UnaryOpNode {
-    pub fn from(token: Token, node : Box<dyn VisitableNode>) -> Self {
-        UnaryOpNode{ op: token, node: node }
+impl NumNode {
+    pub fn from(token: Token) -> Self {
+        NumNode{ token }
     }
 
 }
 
-impl VisitableNode for UnaryOpNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_unary_op(self)
-    }
+/// Binary operation nodes - expr op exprs
+#[derive(Clone, Debug, PartialEq)]
+pub struct BinaryOpNode {
+    pub left_side: Box<Expr>,
+    pub op: Token,
+    pub right_side: Box<Expr>,
+    /// switches in effect where the operator appears in the source
+    pub checks: Switches,
+}
 
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
+impl BinaryOpNode {
+    pub fn from(left_side: Expr, op: Token, right_side: Expr, checks: Switches) -> Self
+    {
+        BinaryOpNode{left_side: Box::new(left_side), op, right_side: Box::new(right_side), checks }
     }
 
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
-    }
 }
 
-impl Clone for UnaryOpNode {
-    fn clone(&self) -> Self {
-        UnaryOpNode {
-            op: self.op.clone(),
-            node: self.node.box_clone(),
-        }
-    }
+/// Operation that takes operation token and single argument to work on
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnaryOpNode {
+    pub op : Token,
+    pub node: Box<Expr>,
+    pub checks: Switches
 }
 
-impl fmt::Debug for UnaryOpNode {
-    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "UnaryOp(op={:?}", self.op)?;
-        self.node.box_fmt(f)?;
-        write!(f, ")")
+impl UnaryOpNode {
+    pub fn from(token: Token, node : Expr, checks: Switches) -> Self {
+        UnaryOpNode{ op: token, node: Box::new(node), checks }
     }
-}
-
 
+}
 
 /// Compound statement nodes contain multiple subsequent statements.
 /// Does not return result when visited
+#[derive(Clone, Debug, PartialEq)]
 pub struct CompoundStatementNode {
-    pub child_statements: Vec<Box<dyn VisitableNode>>
+    pub child_statements: Vec<Stmt>
 }
 
 impl CompoundStatementNode {
-    pub fn from(children: Vec<Box<dyn VisitableNode>>) -> Self {
+    pub fn from(children: Vec<Stmt>) -> Self {
         CompoundStatementNode{child_statements: children }
     }
 }
 
-impl VisitableNode for CompoundStatementNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_compound(self)
-    }
-    
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
-    }
-}
-
-impl Clone for CompoundStatementNode {
-    fn clone(&self) -> Self {
-        let mut child_statements : Vec<Box<dyn VisitableNode>> = Vec::new();
-        for statement in &self.child_statements {
-            child_statements.push(statement.box_clone());
-        }
-        CompoundStatementNode {
-            child_statements: child_statements
-        }
-    }
-}
-
-impl fmt::Debug for CompoundStatementNode {
-    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Compound(")?;
-        for statement in &self.child_statements {
-            statement.box_fmt(f)?;
-            write!(f, ";")?;
-        }
-        Ok(())
-    }
-}
-
-
 /// Var node stores identifier of a variable
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct VarNode {
     pub name: Token,
 }
 
 impl VarNode {
     pub fn from(name: Token) -> Self {
-        VarNode{name: name}
-    }
-}
-
-impl VisitableNode for VarNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_var(self)
-    }
-    
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
+        VarNode{name}
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct AssignmentNode {
     pub left: VarNode,
-    pub right: Box<dyn VisitableNode>,
+    pub right: Expr,
 
 }
 
 impl AssignmentNode {
-    pub fn from(var: VarNode, expr: Box<dyn VisitableNode>) -> Self {
+    pub fn from(var: VarNode, expr: Expr) -> Self {
         AssignmentNode { left: var, right: expr}
     }
 }
 
-impl VisitableNode for AssignmentNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_assignment(self)
-    }
-
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
-    }
-}
-
-impl Clone for AssignmentNode {
-    fn clone(&self) -> Self {
-        AssignmentNode {
-            left : self.left.clone(),
-            right: self.right.box_clone()
-        }
-    }
-}
-
-impl fmt::Debug for AssignmentNode {
-    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\nAssignment node(left = ")?;
-        self.left.fmt(f)?;
-        write!(f, ";right = ")?;
-        self.right.box_fmt(f)?;
-        write!(f, ")")
-    }
-}
-
-
 /// Empty statement node
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NoOpNode {}
 
-impl VisitableNode for NoOpNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_no_op(self)
-    }
-
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
-    }
-    
-}
-
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ProgramNode {
     pub name: Literal,
     pub block : BlockNode
@@ -304,140 +245,59 @@ pub struct ProgramNode {
 
 impl ProgramNode {
     pub fn from(name: Literal, block: BlockNode) -> Self {
-        ProgramNode{name: name, block: block}
-    }
-}
-
-impl VisitableNode for ProgramNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_program(self)
-    }
-
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
+        ProgramNode{name, block}
     }
 }
 
-
 /// Program block
+#[derive(Clone, Debug, PartialEq)]
 pub struct BlockNode {
-    pub declarations : Vec<Box<dyn VisitableNode>>,
+    pub declarations : Vec<Decl>,
     pub compound_statement : CompoundStatementNode
 }
 
 impl BlockNode {
-    pub fn from(declarations: Vec<Box<dyn VisitableNode>>, compound_statement: CompoundStatementNode) -> Self {
-        BlockNode{declarations: declarations, compound_statement: compound_statement}
-    }
-}
-
-impl Clone for BlockNode {
-    fn clone(&self) -> Self {
-        let mut declarations : Vec<Box<dyn VisitableNode>> = Vec::new();
-        for decl in &self.declarations {
-            declarations.push(decl.box_clone());
-        }
-        BlockNode {
-            declarations: declarations,
-            compound_statement: self.compound_statement.clone()
-        }
+    pub fn from(declarations: Vec<Decl>, compound_statement: CompoundStatementNode) -> Self {
+        BlockNode{declarations, compound_statement}
     }
 }
 
-impl VisitableNode for BlockNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_block(self)
-    }
-
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
-    }
-}
-
-impl fmt::Debug for BlockNode {
-    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Block(decl = [")?;
-        for decl in &self.declarations {
-            decl.box_fmt(f)?;
-            write!(f, ";")?;
-        }
-        write!(f, "]; compound = ")?;
-        self.compound_statement.fmt(f)?;
-        write!(f, ")")
-    }
-}
-
-
 /// Variable declaration contains the initialization statments for a variable with its type
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct VarDeclNode {
     pub var: VarNode,
-    pub type_spec: TypeNode
+    pub type_spec: TypeNode,
+    /// the type the declaration resolves to, filled in by the semantic analyzer
+    pub var_type: Option<symbols::Type>,
 }
 
 impl VarDeclNode {
     pub fn from(var: VarNode, type_spec: TypeNode) -> Self {
-        VarDeclNode{var: var, type_spec: type_spec}
+        VarDeclNode{var, type_spec, var_type: None}
     }
 }
 
-impl VisitableNode for VarDeclNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_var_decl(self)
-    }
-
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
-    }
-}
-
-
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TypeNode {
     pub token : Token,
 }
 
 impl TypeNode {
     pub fn from(token: Token) -> Self {
-        TypeNode{token: token}
+        TypeNode{token}
     }
 
-    pub fn get_type(&self) -> symbols::Type {
+    pub fn get_type(&self) -> Result<symbols::Type, RuntimeError> {
         match self.token.token_type() {
-            TokenType::IntegerType => symbols::Type::Integer,
-            TokenType::FloatType => symbols::Type::Float,
-            _ => panic!("Unhandled type {:?}", self.token.token_type())
+            TokenType::IntegerType => Ok(symbols::Type::Integer),
+            TokenType::FloatType => Ok(symbols::Type::Float),
+            TokenType::StringType => Ok(symbols::Type::String),
+            _ => Err(RuntimeError::UnknownType(self.token.literal().to_str().unwrap_or_default().to_owned()))
         }
     }
 }
 
-impl VisitableNode for TypeNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_type(self)
-    }
-
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
-    }
-}
-
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ProcedureDeclNode {
     pub name: Literal,
     pub params : Vec<ParamNode>,
@@ -446,25 +306,11 @@ pub struct ProcedureDeclNode {
 
 impl ProcedureDeclNode {
     pub fn from(name: Literal, params: Vec<ParamNode>, block: BlockNode) -> Self {
-        ProcedureDeclNode{name: name, params: params, block: block}
-    }
-}
-
-impl VisitableNode for ProcedureDeclNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_procedure_decl(self)
-    }
-
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
+        ProcedureDeclNode{name, params, block}
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ParamNode {
     pub var: VarNode,
     pub param_type: TypeNode
@@ -472,67 +318,39 @@ pub struct ParamNode {
 
 impl ParamNode {
     pub fn from(var: VarNode, param_type : TypeNode) -> Self {
-        ParamNode{var: var, param_type: param_type}
-    }
-}
-
-impl VisitableNode for ParamNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_param(self)
-    }
-
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
-    }
-
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
+        ParamNode{var, param_type}
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct ProcedureCallNode {
-    pub name: Literal,
-    pub actual_params: Vec<Box<dyn VisitableNode>>,
-    pub proc_symbol : Option<symbols::Symbol>
+    pub name: Token,
+    pub actual_params: Vec<Expr>,
+    /// the procedure the name resolves to, filled in by the semantic analyzer
+    pub proc_symbol : Option<symbols::Symbol>,
+    /// source range from the name to the closing parenthesis
+    pub span: Span,
 }
 
-impl ProcedureCallNode {
-    pub fn from(name: Literal, actual_params : Vec<Box<dyn VisitableNode>>) -> Self {
-        ProcedureCallNode{name: name, actual_params: actual_params, proc_symbol: None}
+// calls are equal when they pass the same arguments to the same procedure, wherever they are
+// in the source, like the tokens of the other nodes
+impl PartialEq for ProcedureCallNode {
+    fn eq(&self, other: &ProcedureCallNode) -> bool {
+        self.name == other.name && self.actual_params == other.actual_params && self.proc_symbol == other.proc_symbol
     }
 }
 
-impl VisitableNode for ProcedureCallNode {
-    fn accept_visitor(&mut self, visitor: &mut dyn NodeVisitor) -> Result<Option<Literal>, RuntimeError> {
-        visitor.visit_procedure_call(self)
-    }
-    
-    fn box_clone(&self) -> Box<dyn VisitableNode> {
-        Box::new((*self).clone())
+impl ProcedureCallNode {
+    pub fn from(name: Token, actual_params : Vec<Expr>) -> Self {
+        ProcedureCallNode{name, actual_params, proc_symbol: None, span: Span::default()}
     }
 
-    fn box_fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.fmt(f)
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
     }
-}
 
-impl Clone for ProcedureCallNode {
-    fn clone(&self) -> Self {
-        let mut actual_params : Vec<Box<dyn VisitableNode>> = Vec::new();
-        for decl in &self.actual_params {
-            actual_params.push(decl.box_clone());
-        }
-        ProcedureCallNode {
-            name : self.name.clone(),
-            actual_params: actual_params,
-            proc_symbol: self.proc_symbol.clone()
-        }
+    pub fn call_site(&self) -> Position {
+        self.name.position()
     }
 }
-
-
-impl fmt::Debug for ProcedureCallNode {
-    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ProcedureCall({:?})", self.name)
-    }
-}
\ No newline at end of file
@@ -0,0 +1,97 @@
+//! Indented text outline of the AST, one node per line with its children below it, e.g.
+//!
+//! ```text
+//! Assignment
+//!   Var x
+//!   BinaryOp +
+//!     Num 1
+//!     Var y
+//! ```
+
+use crate::pascal_interpreter::ast::{
+    dot::token_text,
+    nodes::*,
+    visit::{self, Visitor},
+};
+
+use std::fmt::Write;
+
+pub fn program_outline(program: &ProgramNode) -> String {
+    let mut outline = Outline { out: String::new(), depth: 0 };
+    outline.visit_program(program);
+    outline.out
+}
+
+pub fn expr_outline(expr: &Expr) -> String {
+    let mut outline = Outline { out: String::new(), depth: 0 };
+    outline.visit_expr(expr);
+    outline.out
+}
+
+struct Outline {
+    out: String,
+    depth: usize,
+}
+
+impl Outline {
+    fn node(&mut self, label: String, walk: impl FnOnce(&mut Self)) {
+        writeln!(self.out, "{}{}", "  ".repeat(self.depth), label).unwrap();
+        self.depth += 1;
+        walk(self);
+        self.depth -= 1;
+    }
+}
+
+impl Visitor for Outline {
+    fn visit_program(&mut self, node: &ProgramNode) {
+        self.node(format!("Program {}", node.name.to_str().unwrap_or_default()), |outline| visit::walk_program(outline, node));
+    }
+
+    fn visit_block(&mut self, node: &BlockNode) {
+        self.node(String::from("Block"), |outline| visit::walk_block(outline, node));
+    }
+
+    fn visit_var_decl(&mut self, node: &VarDeclNode) {
+        self.node(format!("VarDecl {} : {}", token_text(&node.var.name), token_text(&node.type_spec.token)), |_| {});
+    }
+
+    fn visit_procedure_decl(&mut self, node: &ProcedureDeclNode) {
+        let params: Vec<String> = node.params.iter()
+            .map(|param| format!("{} : {}", token_text(&param.var.name), token_text(&param.param_type.token)))
+            .collect();
+        let label = format!("ProcedureDecl {}({})", node.name.to_str().unwrap_or_default(), params.join("; "));
+        self.node(label, |outline| outline.visit_block(&node.block));
+    }
+
+    fn visit_compound(&mut self, node: &CompoundStatementNode) {
+        self.node(String::from("Compound"), |outline| visit::walk_compound(outline, node));
+    }
+
+    fn visit_assignment(&mut self, node: &AssignmentNode) {
+        self.node(String::from("Assignment"), |outline| visit::walk_assignment(outline, node));
+    }
+
+    fn visit_procedure_call(&mut self, node: &ProcedureCallNode) {
+        self.node(format!("Call {}", token_text(&node.name)), |outline| visit::walk_procedure_call(outline, node));
+    }
+
+    fn visit_no_op(&mut self, _: &NoOpNode) {
+        self.node(String::from("NoOp"), |_| {});
+    }
+
+    fn visit_num(&mut self, node: &NumNode) {
+        self.node(format!("Num {}", token_text(&node.token)), |_| {});
+    }
+
+    fn visit_var(&mut self, node: &VarNode) {
+        self.node(format!("Var {}", token_text(&node.name)), |_| {});
+    }
+
+    fn visit_binary_op(&mut self, node: &BinaryOpNode) {
+        self.node(format!("BinaryOp {}", token_text(&node.op)), |outline| visit::walk_binary_op(outline, node));
+    }
+
+    fn visit_unary_op(&mut self, node: &UnaryOpNode) {
+        self.node(format!("UnaryOp {}", token_text(&node.op)), |outline| visit::walk_unary_op(outline, node));
+    }
+}
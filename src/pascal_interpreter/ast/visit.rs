@@ -0,0 +1,321 @@
+use crate::pascal_interpreter::ast::nodes::*;
+
+/// Read-only traversal of the AST. Every `visit_*` method defaults to the matching `walk_*`
+/// function, which visits the node's children, so a pass only overrides the nodes it cares about.
+pub trait Visitor {
+    fn visit_program(&mut self, node: &ProgramNode) {
+        walk_program(self, node)
+    }
+
+    fn visit_block(&mut self, node: &BlockNode) {
+        walk_block(self, node)
+    }
+
+    fn visit_decl(&mut self, node: &Decl) {
+        walk_decl(self, node)
+    }
+
+    fn visit_var_decl(&mut self, node: &VarDeclNode) {
+        walk_var_decl(self, node)
+    }
+
+    fn visit_type(&mut self, node: &TypeNode) {
+        walk_type(self, node)
+    }
+
+    fn visit_procedure_decl(&mut self, node: &ProcedureDeclNode) {
+        walk_procedure_decl(self, node)
+    }
+
+    fn visit_param(&mut self, node: &ParamNode) {
+        walk_param(self, node)
+    }
+
+    fn visit_stmt(&mut self, node: &Stmt) {
+        walk_stmt(self, node)
+    }
+
+    fn visit_compound(&mut self, node: &CompoundStatementNode) {
+        walk_compound(self, node)
+    }
+
+    fn visit_assignment(&mut self, node: &AssignmentNode) {
+        walk_assignment(self, node)
+    }
+
+    fn visit_procedure_call(&mut self, node: &ProcedureCallNode) {
+        walk_procedure_call(self, node)
+    }
+
+    fn visit_no_op(&mut self, node: &NoOpNode) {
+        walk_no_op(self, node)
+    }
+
+    fn visit_expr(&mut self, node: &Expr) {
+        walk_expr(self, node)
+    }
+
+    fn visit_num(&mut self, node: &NumNode) {
+        walk_num(self, node)
+    }
+
+    fn visit_var(&mut self, node: &VarNode) {
+        walk_var(self, node)
+    }
+
+    fn visit_binary_op(&mut self, node: &BinaryOpNode) {
+        walk_binary_op(self, node)
+    }
+
+    fn visit_unary_op(&mut self, node: &UnaryOpNode) {
+        walk_unary_op(self, node)
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, node: &ProgramNode) {
+    visitor.visit_block(&node.block);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, node: &BlockNode) {
+    for decl in &node.declarations {
+        visitor.visit_decl(decl);
+    }
+    visitor.visit_compound(&node.compound_statement);
+}
+
+pub fn walk_decl<V: Visitor + ?Sized>(visitor: &mut V, node: &Decl) {
+    match node {
+        Decl::Var(node) => visitor.visit_var_decl(node),
+        Decl::Procedure(node) => visitor.visit_procedure_decl(node),
+    }
+}
+
+pub fn walk_var_decl<V: Visitor + ?Sized>(visitor: &mut V, node: &VarDeclNode) {
+    visitor.visit_var(&node.var);
+    visitor.visit_type(&node.type_spec);
+}
+
+pub fn walk_type<V: Visitor + ?Sized>(_visitor: &mut V, _node: &TypeNode) {}
+
+pub fn walk_procedure_decl<V: Visitor + ?Sized>(visitor: &mut V, node: &ProcedureDeclNode) {
+    for param in &node.params {
+        visitor.visit_param(param);
+    }
+    visitor.visit_block(&node.block);
+}
+
+pub fn walk_param<V: Visitor + ?Sized>(visitor: &mut V, node: &ParamNode) {
+    visitor.visit_var(&node.var);
+    visitor.visit_type(&node.param_type);
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, node: &Stmt) {
+    match node {
+        Stmt::Compound(node) => visitor.visit_compound(node),
+        Stmt::Assignment(node) => visitor.visit_assignment(node),
+        Stmt::ProcedureCall(node) => visitor.visit_procedure_call(node),
+        Stmt::NoOp(node) => visitor.visit_no_op(node),
+    }
+}
+
+pub fn walk_compound<V: Visitor + ?Sized>(visitor: &mut V, node: &CompoundStatementNode) {
+    for statement in &node.child_statements {
+        visitor.visit_stmt(statement);
+    }
+}
+
+pub fn walk_assignment<V: Visitor + ?Sized>(visitor: &mut V, node: &AssignmentNode) {
+    visitor.visit_var(&node.left);
+    visitor.visit_expr(&node.right);
+}
+
+pub fn walk_procedure_call<V: Visitor + ?Sized>(visitor: &mut V, node: &ProcedureCallNode) {
+    for param in &node.actual_params {
+        visitor.visit_expr(param);
+    }
+}
+
+pub fn walk_no_op<V: Visitor + ?Sized>(_visitor: &mut V, _node: &NoOpNode) {}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, node: &Expr) {
+    match node {
+        Expr::Num(node) => visitor.visit_num(node),
+        Expr::Var(node) => visitor.visit_var(node),
+        Expr::BinaryOp(node) => visitor.visit_binary_op(node),
+        Expr::UnaryOp(node) => visitor.visit_unary_op(node),
+        Expr::Call(node) => visitor.visit_procedure_call(node),
+    }
+}
+
+pub fn walk_num<V: Visitor + ?Sized>(_visitor: &mut V, _node: &NumNode) {}
+
+pub fn walk_var<V: Visitor + ?Sized>(_visitor: &mut V, _node: &VarNode) {}
+
+pub fn walk_binary_op<V: Visitor + ?Sized>(visitor: &mut V, node: &BinaryOpNode) {
+    visitor.visit_expr(&node.left_side);
+    visitor.visit_expr(&node.right_side);
+}
+
+pub fn walk_unary_op<V: Visitor + ?Sized>(visitor: &mut V, node: &UnaryOpNode) {
+    visitor.visit_expr(&node.node);
+}
+
+/// Mutable traversal of the AST, for passes that rewrite nodes in place.
+/// Every `visit_*` method defaults to the matching `walk_*_mut` function, which visits the node's children.
+pub trait VisitorMut {
+    fn visit_program(&mut self, node: &mut ProgramNode) {
+        walk_program_mut(self, node)
+    }
+
+    fn visit_block(&mut self, node: &mut BlockNode) {
+        walk_block_mut(self, node)
+    }
+
+    fn visit_decl(&mut self, node: &mut Decl) {
+        walk_decl_mut(self, node)
+    }
+
+    fn visit_var_decl(&mut self, node: &mut VarDeclNode) {
+        walk_var_decl_mut(self, node)
+    }
+
+    fn visit_type(&mut self, node: &mut TypeNode) {
+        walk_type_mut(self, node)
+    }
+
+    fn visit_procedure_decl(&mut self, node: &mut ProcedureDeclNode) {
+        walk_procedure_decl_mut(self, node)
+    }
+
+    fn visit_param(&mut self, node: &mut ParamNode) {
+        walk_param_mut(self, node)
+    }
+
+    fn visit_stmt(&mut self, node: &mut Stmt) {
+        walk_stmt_mut(self, node)
+    }
+
+    fn visit_compound(&mut self, node: &mut CompoundStatementNode) {
+        walk_compound_mut(self, node)
+    }
+
+    fn visit_assignment(&mut self, node: &mut AssignmentNode) {
+        walk_assignment_mut(self, node)
+    }
+
+    fn visit_procedure_call(&mut self, node: &mut ProcedureCallNode) {
+        walk_procedure_call_mut(self, node)
+    }
+
+    fn visit_no_op(&mut self, node: &mut NoOpNode) {
+        walk_no_op_mut(self, node)
+    }
+
+    fn visit_expr(&mut self, node: &mut Expr) {
+        walk_expr_mut(self, node)
+    }
+
+    fn visit_num(&mut self, node: &mut NumNode) {
+        walk_num_mut(self, node)
+    }
+
+    fn visit_var(&mut self, node: &mut VarNode) {
+        walk_var_mut(self, node)
+    }
+
+    fn visit_binary_op(&mut self, node: &mut BinaryOpNode) {
+        walk_binary_op_mut(self, node)
+    }
+
+    fn visit_unary_op(&mut self, node: &mut UnaryOpNode) {
+        walk_unary_op_mut(self, node)
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut ProgramNode) {
+    visitor.visit_block(&mut node.block);
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut BlockNode) {
+    for decl in &mut node.declarations {
+        visitor.visit_decl(decl);
+    }
+    visitor.visit_compound(&mut node.compound_statement);
+}
+
+pub fn walk_decl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Decl) {
+    match node {
+        Decl::Var(node) => visitor.visit_var_decl(node),
+        Decl::Procedure(node) => visitor.visit_procedure_decl(node),
+    }
+}
+
+pub fn walk_var_decl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut VarDeclNode) {
+    visitor.visit_var(&mut node.var);
+    visitor.visit_type(&mut node.type_spec);
+}
+
+pub fn walk_type_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut TypeNode) {}
+
+pub fn walk_procedure_decl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut ProcedureDeclNode) {
+    for param in &mut node.params {
+        visitor.visit_param(param);
+    }
+    visitor.visit_block(&mut node.block);
+}
+
+pub fn walk_param_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut ParamNode) {
+    visitor.visit_var(&mut node.var);
+    visitor.visit_type(&mut node.param_type);
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Stmt) {
+    match node {
+        Stmt::Compound(node) => visitor.visit_compound(node),
+        Stmt::Assignment(node) => visitor.visit_assignment(node),
+        Stmt::ProcedureCall(node) => visitor.visit_procedure_call(node),
+        Stmt::NoOp(node) => visitor.visit_no_op(node),
+    }
+}
+
+pub fn walk_compound_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut CompoundStatementNode) {
+    for statement in &mut node.child_statements {
+        visitor.visit_stmt(statement);
+    }
+}
+
+pub fn walk_assignment_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut AssignmentNode) {
+    visitor.visit_var(&mut node.left);
+    visitor.visit_expr(&mut node.right);
+}
+
+pub fn walk_procedure_call_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut ProcedureCallNode) {
+    for param in &mut node.actual_params {
+        visitor.visit_expr(param);
+    }
+}
+
+pub fn walk_no_op_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut NoOpNode) {}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Expr) {
+    match node {
+        Expr::Num(node) => visitor.visit_num(node),
+        Expr::Var(node) => visitor.visit_var(node),
+        Expr::BinaryOp(node) => visitor.visit_binary_op(node),
+        Expr::UnaryOp(node) => visitor.visit_unary_op(node),
+        Expr::Call(node) => visitor.visit_procedure_call(node),
+    }
+}
+
+pub fn walk_num_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut NumNode) {}
+
+pub fn walk_var_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _node: &mut VarNode) {}
+
+pub fn walk_binary_op_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut BinaryOpNode) {
+    visitor.visit_expr(&mut node.left_side);
+    visitor.visit_expr(&mut node.right_side);
+}
+
+pub fn walk_unary_op_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut UnaryOpNode) {
+    visitor.visit_expr(&mut node.node);
+}
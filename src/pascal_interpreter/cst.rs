@@ -0,0 +1,395 @@
+use crate::pascal_interpreter::{
+    ast::nodes::*,
+    token::{
+        Span,
+        Token,
+        TokenType
+    },
+};
+
+use std::fmt;
+use std::rc::Rc;
+
+/// Kind of an inner node of the concrete syntax tree. Leaves are tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Program,
+    Block,
+    VarSection,
+    VarDecl,
+    TypeSpec,
+    ProcedureDecl,
+    Param,
+    CompoundStatement,
+    Assignment,
+    ProcedureCall,
+    Empty,
+    Variable,
+    Number,
+    UnaryExpr,
+    BinaryExpr,
+    ParenExpr,
+}
+
+/// Immutable node of the tree, knowing its width but not its position, so subtrees can be shared
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<Token>),
+}
+
+impl GreenElement {
+    fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len,
+            GreenElement::Token(token) => token.full_len(),
+        }
+    }
+}
+
+impl GreenNode {
+    fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> GreenNode {
+        let len = children.iter().map(GreenElement::len).sum();
+        GreenNode { kind, len, children }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// Length in bytes of the source covered by the node, trivia included
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+/// A green node seen from the root: knows its parent and byte offset in the source
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    offset: usize,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    token: Rc<Token>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+#[derive(Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    fn new_root(green: GreenNode) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData { green: Rc::new(green), parent: None, offset: 0 }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &GreenNode {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    /// Bytes of the source covered by the node, trivia included
+    pub fn span(&self) -> Span {
+        Span { start: self.0.offset, end: self.0.offset + self.0.green.len }
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut children = Vec::new();
+        for child in &self.0.green.children {
+            children.push(match child {
+                GreenElement::Node(green) => {
+                    let data = NodeData { green: green.clone(), parent: Some(self.clone()), offset };
+                    SyntaxElement::Node(SyntaxNode(Rc::new(data)))
+                },
+                GreenElement::Token(token) => {
+                    SyntaxElement::Token(SyntaxToken { token: token.clone(), parent: self.clone(), offset })
+                }
+            });
+            offset += child.len();
+        }
+        children
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens().into_iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None
+        }).collect()
+    }
+
+    /// Tokens directly below the node
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        self.children_with_tokens().into_iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None
+        }).collect()
+    }
+
+    fn child(&self, kind: SyntaxKind) -> Option<SyntaxNode> {
+        self.children().into_iter().find(|child| child.kind() == kind)
+    }
+
+    fn token(&self, token_type: TokenType) -> Option<SyntaxToken> {
+        self.tokens().into_iter().find(|token| token.token_type() == token_type)
+    }
+}
+
+impl SyntaxToken {
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn token_type(&self) -> TokenType {
+        self.token.token_type()
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+
+    /// Bytes of the source covered by the token, trivia included
+    pub fn span(&self) -> Span {
+        Span { start: self.offset, end: self.offset + self.token.full_len() }
+    }
+}
+
+fn write_green(node: &GreenNode, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for child in &node.children {
+        match child {
+            GreenElement::Node(node) => write_green(node, f)?,
+            GreenElement::Token(token) => {
+                for trivia in token.leading_trivia() {
+                    f.write_str(trivia.text())?;
+                }
+                f.write_str(token.text())?;
+                for trivia in token.trailing_trivia() {
+                    f.write_str(trivia.text())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The source text exactly as it was parsed
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_green(&self.0.green, f)
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{}..{}", self.kind(), self.span().start, self.span().end)
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{}..{} {:?}", self.token_type(), self.span().start, self.span().end, self.token.text())
+    }
+}
+
+enum Event {
+    Start(SyntaxKind),
+    Finish,
+    Token(Token),
+}
+
+/// Records the parser's progress and assembles the green tree once parsing is done
+pub(crate) struct SyntaxTreeBuilder {
+    events: Vec<Event>,
+}
+
+impl SyntaxTreeBuilder {
+    pub fn new() -> Self {
+        SyntaxTreeBuilder { events: Vec::new() }
+    }
+
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.events.push(Event::Start(kind));
+    }
+
+    /// Place to start a node later, once it is known that the node wraps what follows,
+    /// e.g. the left operand of a binary expression
+    pub fn checkpoint(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn start_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        self.events.insert(checkpoint, Event::Start(kind));
+    }
+
+    pub fn finish_node(&mut self) {
+        self.events.push(Event::Finish);
+    }
+
+    pub fn token(&mut self, token: Token) {
+        self.events.push(Event::Token(token));
+    }
+
+    pub fn finish(mut self) -> SyntaxNode {
+        // the lexer attaches all trivia to the following token; hand the rest of each line back to the previous one
+        let token_indices: Vec<usize> = self.events.iter().enumerate()
+            .filter(|(_, event)| matches!(event, Event::Token(_)))
+            .map(|(index, _)| index)
+            .collect();
+        for pair in token_indices.windows(2) {
+            let (head, tail) = self.events.split_at_mut(pair[1]);
+            if let (Event::Token(previous), Event::Token(token)) = (&mut head[pair[0]], &mut tail[0]) {
+                token.split_trivia_with(previous);
+            }
+        }
+
+        let mut stack: Vec<(SyntaxKind, Vec<GreenElement>)> = Vec::new();
+        let mut root = None;
+        for event in self.events {
+            match event {
+                Event::Start(kind) => stack.push((kind, Vec::new())),
+                Event::Token(token) => {
+                    let (_, children) = stack.last_mut().expect("token outside of any node");
+                    children.push(GreenElement::Token(Rc::new(token)));
+                },
+                Event::Finish => {
+                    let (kind, children) = stack.pop().expect("unbalanced syntax tree events");
+                    let node = GreenNode::new(kind, children);
+                    match stack.last_mut() {
+                        Some((_, parent)) => parent.push(GreenElement::Node(Rc::new(node))),
+                        None => root = Some(node)
+                    }
+                }
+            }
+        }
+        SyntaxNode::new_root(root.expect("syntax tree without a root"))
+    }
+}
+
+// Lowering to the AST. The builder only produces trees of the shape the parser
+// gives them, so missing children are bugs in the parser rather than input errors.
+
+/// Build the AST of a program from its concrete syntax tree
+pub fn lower(program: &SyntaxNode) -> ProgramNode {
+    let name = program.token(TokenType::Identifier).expect("program name");
+    let block = program.child(SyntaxKind::Block).expect("program block");
+    ProgramNode::from(name.token().literal().clone(), lower_block(&block))
+}
+
+fn lower_block(block: &SyntaxNode) -> BlockNode {
+    let mut declarations = Vec::new();
+    let mut compound = None;
+    for child in block.children() {
+        match child.kind() {
+            SyntaxKind::VarSection => {
+                for var_decl in child.children() {
+                    let type_spec = lower_type_spec(&var_decl);
+                    for name in var_decl.tokens().iter().filter(|token| token.token_type() == TokenType::Identifier) {
+                        declarations.push(Decl::Var(VarDeclNode::from(VarNode::from(name.token().clone()), type_spec.clone())));
+                    }
+                }
+            },
+            SyntaxKind::ProcedureDecl => declarations.push(Decl::Procedure(lower_procedure_decl(&child))),
+            SyntaxKind::CompoundStatement => compound = Some(lower_compound(&child)),
+            kind => unreachable!("{:?} in block", kind)
+        }
+    }
+    BlockNode::from(declarations, compound.expect("block statements"))
+}
+
+fn lower_type_spec(node: &SyntaxNode) -> TypeNode {
+    let type_spec = node.child(SyntaxKind::TypeSpec).expect("type of declaration");
+    TypeNode::from(type_spec.tokens()[0].token().clone())
+}
+
+fn lower_procedure_decl(node: &SyntaxNode) -> ProcedureDeclNode {
+    let name = node.token(TokenType::Identifier).expect("procedure name");
+    let mut params = Vec::new();
+    for param in node.children().iter().filter(|child| child.kind() == SyntaxKind::Param) {
+        let type_spec = lower_type_spec(param);
+        for name in param.tokens().iter().filter(|token| token.token_type() == TokenType::Identifier) {
+            params.push(ParamNode::from(VarNode::from(name.token().clone()), type_spec.clone()));
+        }
+    }
+    let block = node.child(SyntaxKind::Block).expect("procedure block");
+    ProcedureDeclNode::from(name.token().literal().clone(), params, lower_block(&block))
+}
+
+fn lower_compound(node: &SyntaxNode) -> CompoundStatementNode {
+    CompoundStatementNode::from(node.children().iter().map(lower_statement).collect())
+}
+
+fn lower_statement(node: &SyntaxNode) -> Stmt {
+    match node.kind() {
+        SyntaxKind::CompoundStatement => Stmt::Compound(lower_compound(node)),
+        SyntaxKind::Assignment => {
+            let children = node.children();
+            Stmt::Assignment(AssignmentNode::from(lower_variable(&children[0]), lower_expr(&children[1])))
+        },
+        SyntaxKind::ProcedureCall => Stmt::ProcedureCall(lower_call(node)),
+        SyntaxKind::Empty => Stmt::NoOp(NoOpNode {}),
+        kind => unreachable!("{:?} as statement", kind)
+    }
+}
+
+fn lower_call(node: &SyntaxNode) -> ProcedureCallNode {
+    let name = node.token(TokenType::Identifier).expect("procedure name");
+    let actual_params = node.children().iter().map(lower_expr).collect();
+    let rparen = node.token(TokenType::Rparen).expect("closing parenthesis of call");
+    let span = Span { start: name.token().span().start, end: rparen.token().span().end };
+    ProcedureCallNode::from(name.token().clone(), actual_params).with_span(span)
+}
+
+fn lower_variable(node: &SyntaxNode) -> VarNode {
+    VarNode::from(node.tokens()[0].token().clone())
+}
+
+fn lower_expr(node: &SyntaxNode) -> Expr {
+    match node.kind() {
+        SyntaxKind::Number => Expr::Num(NumNode::from(node.tokens()[0].token().clone())),
+        SyntaxKind::Variable => Expr::Var(lower_variable(node)),
+        SyntaxKind::ProcedureCall => Expr::Call(lower_call(node)),
+        SyntaxKind::ParenExpr => lower_expr(&node.children()[0]),
+        SyntaxKind::UnaryExpr => {
+            let op = node.tokens()[0].token().clone();
+            let checks = op.switches();
+            Expr::UnaryOp(UnaryOpNode::from(op, lower_expr(&node.children()[0]), checks))
+        },
+        SyntaxKind::BinaryExpr => {
+            let children = node.children();
+            let op = node.tokens()[0].token().clone();
+            let checks = op.switches();
+            Expr::BinaryOp(BinaryOpNode::from(lower_expr(&children[0]), op, lower_expr(&children[1]), checks))
+        },
+        kind => unreachable!("{:?} as expression", kind)
+    }
+}
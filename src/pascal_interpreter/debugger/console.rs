@@ -0,0 +1,227 @@
+//! Terminal front end of the debugger, as run by `pascal debug`. At each stop it shows where the run
+//! is and the watches, then reads commands until one resumes the run, see [`HELP`].
+
+use crate::pascal_interpreter::{
+    debugger::{DebugHandler, Pause, Resume, StopReason},
+    literal::Literal,
+};
+
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+pub const HELP: &str = "\
+c, continue                     run until a breakpoint
+s, step                         run to the next statement, into calls
+n, next                         run to the next statement, over calls
+f, finish                       run until the current procedure returned
+b, break <line> [if <cond>]     stop at a line, e.g. break 12 if i > 3
+d, delete <id>                  remove a breakpoint
+watch <expr>                    show the value of an expression at every stop
+unwatch <number>                stop showing a watch
+p, print <expr>                 show the value of an expression in the selected frame
+set <name> := <expr>            give a variable of the selected frame a new value
+bt, backtrace                   list the calls the statement is in
+frame <n>                       select the record n calls down, 0 being the innermost
+locals                          list the variables of the selected frame
+l, list                         show the source around the statement
+info                            list the breakpoints and watches
+h, help                         show this help
+q, quit                         end the run";
+
+pub struct Console<R, W> {
+    lines: Vec<String>,
+    input: R,
+    output: W,
+    // record the commands look at, counted from the innermost
+    frame: usize,
+}
+
+// what a command led to
+enum Reply {
+    Resume(Resume),
+    Output(String),
+}
+
+impl<R: BufRead, W: Write> Console<R, W> {
+    /// Debug the run of `source`, reading commands from `input`
+    pub fn new(source: &str, input: R, output: W) -> Self {
+        Console { lines: source.lines().map(str::to_owned).collect(), input, output, frame: 0 }
+    }
+
+    fn source_line(&self, line: u32, marker: &str) -> String {
+        let text = self.lines.get(line as usize - 1).map_or("", String::as_str);
+        format!("{}{:>4}  {}", marker, line, text)
+    }
+
+    fn stop(&self, pause: &Pause<'_>) -> String {
+        let position = pause.position();
+        let name = pause.frame(0).map_or("", |frame| frame.name());
+        let mut out = match pause.reason() {
+            StopReason::Step => format!("{} at {}", name, position),
+            StopReason::Breakpoint(id) => format!("breakpoint {}, {} at {}", id, name, position),
+            StopReason::ConditionFailed(id, err) => format!("breakpoint {}, {} at {}, condition failed: {}", id, name, position, err),
+        };
+        write!(out, "\n{}", self.source_line(position.line, "")).unwrap();
+        for (number, (expression, value)) in pause.watch_values().into_iter().enumerate() {
+            write!(out, "\n  watch {}: {} = {}", number + 1, expression, describe(value)).unwrap();
+        }
+        out
+    }
+
+    fn command(&mut self, command: &str, pause: &mut Pause<'_>) -> Reply {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(split) => (&command[..split], command[split..].trim()),
+            None => (command, "")
+        };
+        let output = match name {
+            "" => String::new(),
+            "c" | "continue" => return Reply::Resume(Resume::Continue),
+            "s" | "step" => return Reply::Resume(Resume::StepIn),
+            "n" | "next" => return Reply::Resume(Resume::StepOver),
+            "f" | "finish" => return Reply::Resume(Resume::StepOut),
+            "q" | "quit" => return Reply::Resume(Resume::Quit),
+            "b" | "break" => match parse_breakpoint(argument) {
+                Ok((line, condition)) => match pause.breakpoints_mut().add(line, condition) {
+                    Ok(id) => format!("breakpoint {} at line {}", id, line),
+                    Err(err) => format!("error: {}", err)
+                },
+                Err(message) => message
+            },
+            "d" | "delete" => match argument.parse() {
+                Ok(id) if pause.breakpoints_mut().remove(id) => format!("deleted breakpoint {}", id),
+                _ => format!("no breakpoint {}", argument)
+            },
+            "watch" => match pause.watches_mut().add(argument) {
+                Ok(number) => format!("watch {}: {}", number, argument),
+                Err(err) => format!("error: {}", err)
+            },
+            "unwatch" => match argument.parse() {
+                Ok(number) if pause.watches_mut().remove(number) => format!("removed watch {}", number),
+                _ => format!("no watch {}", argument)
+            },
+            "p" | "print" => format!("{} = {}", argument, describe(pause.evaluate(self.frame, argument))),
+            "set" => self.set(argument, pause),
+            "bt" | "backtrace" => pause.backtrace().to_string().trim_end().to_owned(),
+            "frame" => match argument.parse() {
+                Ok(frame) => match pause.frame(frame) {
+                    Some(record) => {
+                        self.frame = frame;
+                        format!("frame {}: {}", frame, record.name())
+                    },
+                    None => format!("no frame {}", frame)
+                },
+                Err(_) => String::from("frame needs a number")
+            },
+            "locals" => self.locals(pause),
+            "l" | "list" => {
+                let line = pause.position().line;
+                (line.saturating_sub(3).max(1)..=line + 3)
+                    .filter(|number| *number as usize <= self.lines.len())
+                    .map(|number| self.source_line(number, if number == line { "> " } else { "  " }))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            },
+            "info" => info(pause),
+            "h" | "help" => HELP.to_owned(),
+            _ => format!("unknown command {}, see help", name)
+        };
+        Reply::Output(output)
+    }
+
+    fn set(&self, argument: &str, pause: &mut Pause<'_>) -> String {
+        let (name, expression) = match argument.split_once(":=") {
+            Some((name, expression)) => (name.trim(), expression.trim()),
+            None => return String::from("set needs <name> := <expr>")
+        };
+        match pause.evaluate(self.frame, expression) {
+            Ok(Some(value)) => match pause.set_variable(self.frame, name, value.clone()) {
+                Ok(()) => format!("{} = {}", name, value),
+                Err(err) => format!("error: {}", err)
+            },
+            Ok(None) => format!("{} is not set", expression),
+            Err(err) => format!("error: {}", err)
+        }
+    }
+
+    fn locals(&self, pause: &Pause<'_>) -> String {
+        let record = match pause.frame(self.frame) {
+            Some(record) => record,
+            None => return format!("no frame {}", self.frame)
+        };
+        let mut members: Vec<(&str, &Literal)> = record.members().collect();
+        members.sort_by_key(|(name, _)| *name);
+        members.iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl<R: BufRead, W: Write> DebugHandler for Console<R, W> {
+    fn paused(&mut self, pause: &mut Pause<'_>) -> Resume {
+        self.frame = 0;
+        let stop = self.stop(pause);
+        // the run cannot go on without commands, so a broken terminal ends it
+        if writeln!(self.output, "{}", stop).is_err() {
+            return Resume::Quit;
+        }
+        loop {
+            if write!(self.output, "(debug) ").and_then(|_| self.output.flush()).is_err() {
+                return Resume::Quit;
+            }
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => return Resume::Quit,
+                Ok(_) => {}
+            }
+            match self.command(line.trim(), pause) {
+                Reply::Resume(resume) => return resume,
+                Reply::Output(output) if output.is_empty() => {},
+                Reply::Output(output) => if writeln!(self.output, "{}", output).is_err() {
+                    return Resume::Quit;
+                }
+            }
+        }
+    }
+}
+
+// `<line>` or `<line> if <condition>`
+fn parse_breakpoint(argument: &str) -> Result<(u32, Option<&str>), String> {
+    let (line, condition) = match argument.split_once(char::is_whitespace) {
+        Some((line, rest)) => match rest.trim_start().strip_prefix("if") {
+            Some(condition) if condition.starts_with(char::is_whitespace) => (line, Some(condition.trim())),
+            _ => return Err(String::from("break needs <line> or <line> if <condition>"))
+        },
+        None => (argument, None)
+    };
+    match line.parse() {
+        Ok(line) if line > 0 => Ok((line, condition)),
+        _ => Err(format!("no line {}", line))
+    }
+}
+
+fn describe(value: Result<Option<Literal>, impl std::fmt::Display>) -> String {
+    match value {
+        Ok(Some(value)) => value.to_string(),
+        Ok(None) => String::from("(unset)"),
+        Err(err) => format!("error: {}", err)
+    }
+}
+
+fn info(pause: &Pause<'_>) -> String {
+    let mut out = String::new();
+    for breakpoint in pause.breakpoints().iter() {
+        write!(out, "breakpoint {} at line {}", breakpoint.id(), breakpoint.line()).unwrap();
+        if let Some(condition) = breakpoint.condition() {
+            write!(out, " if {}", condition).unwrap();
+        }
+        writeln!(out, ", hit {} times", breakpoint.hits()).unwrap();
+    }
+    for (number, watch) in pause.watches().iter().enumerate() {
+        writeln!(out, "watch {}: {}", number + 1, watch).unwrap();
+    }
+    if out.is_empty() {
+        out.push_str("no breakpoints or watches");
+    }
+    out.trim_end().to_owned()
+}
@@ -0,0 +1,162 @@
+// Expressions typed into the debugger: conditions of breakpoints, watches and values to print or set.
+//
+// The language has no comparisons yet, so the debugger reads an expression optionally followed by one
+// of `= <> < <= > >=` and another expression itself, and evaluates the sides with an interpreter of its
+// own over a copy of the record they are evaluated in.
+
+use crate::pascal_interpreter::{
+    ast::{
+        dot::token_text,
+        nodes::*,
+        visit::Visitor,
+    },
+    error::RuntimeError,
+    interpreter::Interpreter,
+    lexer::{tokenize, LexerOptions},
+    literal::Literal,
+    parser::Parser,
+    record::ActivationRecord,
+    token::TokenType,
+};
+
+use std::cmp::Ordering;
+
+const RELATIONS: [TokenType; 6] = [
+    TokenType::Equal, TokenType::NotEqual, TokenType::Less,
+    TokenType::LessEqual, TokenType::Greater, TokenType::GreaterEqual,
+];
+
+#[derive(Debug, Clone)]
+pub(crate) struct DebugExpr {
+    text: String,
+    left: Expr,
+    relation: Option<(TokenType, Expr)>,
+}
+
+impl DebugExpr {
+    pub(crate) fn parse(text: &str) -> Result<Self, RuntimeError> {
+        let text = text.trim();
+        let mut depth = 0;
+        let mut relation = None;
+        for token in tokenize(text)? {
+            match token.token_type() {
+                TokenType::Lparen => depth += 1,
+                TokenType::Rparen => depth -= 1,
+                token_type if depth == 0 && RELATIONS.contains(&token_type) => {
+                    relation = Some((token_type, token.span()));
+                    break
+                },
+                _ => {}
+            }
+        }
+        match relation {
+            Some((op, span)) => Ok(DebugExpr {
+                text: text.to_owned(),
+                left: parse_side(&text[..span.start])?,
+                relation: Some((op, parse_side(&text[span.end..])?)),
+            }),
+            None => Ok(DebugExpr { text: text.to_owned(), left: parse_side(text)?, relation: None })
+        }
+    }
+
+    /// Parse a breakpoint condition, which has to be a comparison
+    pub(crate) fn parse_condition(text: &str) -> Result<Self, RuntimeError> {
+        let condition = DebugExpr::parse(text)?;
+        match condition.relation {
+            Some(_) => Ok(condition),
+            None => Err(RuntimeError::NotACondition(condition.text))
+        }
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Value in `record`, None when it is a variable that is not set. Comparisons give a boolean.
+    pub(crate) fn evaluate(&self, record: &ActivationRecord) -> Result<Option<Literal>, RuntimeError> {
+        let (op, right) = match (&self.relation, &self.left) {
+            (Some(relation), _) => relation,
+            (None, Expr::Var(_)) => return evaluate_in(record, &self.left),
+            (None, left) => return evaluate_operand(record, left).map(Some)
+        };
+        let lhs = evaluate_operand(record, &self.left)?;
+        let rhs = evaluate_operand(record, right)?;
+        let ordering = compare(*op, &lhs, &rhs)?;
+        let holds = match op {
+            TokenType::Equal => ordering == Some(Ordering::Equal),
+            TokenType::NotEqual => ordering != Some(Ordering::Equal),
+            TokenType::Less => ordering == Some(Ordering::Less),
+            TokenType::LessEqual => matches!(ordering, Some(Ordering::Less) | Some(Ordering::Equal)),
+            TokenType::Greater => ordering == Some(Ordering::Greater),
+            _ => matches!(ordering, Some(Ordering::Greater) | Some(Ordering::Equal)),
+        };
+        Ok(Some(Literal::from_bool(holds)))
+    }
+
+    /// Whether the condition holds in `record`
+    pub(crate) fn holds(&self, record: &ActivationRecord) -> Result<bool, RuntimeError> {
+        match self.evaluate(record)? {
+            Some(Literal::Bool(holds)) => Ok(holds),
+            _ => Err(RuntimeError::NotACondition(self.text.clone()))
+        }
+    }
+}
+
+fn parse_side(text: &str) -> Result<Expr, RuntimeError> {
+    match Parser::from(text, LexerOptions::default()).parse_fragment()? {
+        Fragment::Expression(expr) => Ok(expr),
+        _ => Err(RuntimeError::IllformedVarExpr)
+    }
+}
+
+// the variables of the record become the globals of a session the expression is run in, so the
+// program's own run is not affected
+fn evaluate_in(record: &ActivationRecord, expr: &Expr) -> Result<Option<Literal>, RuntimeError> {
+    let mut interpreter = Interpreter::new();
+    for (name, value) in record.members() {
+        interpreter.set_global(name, value.clone());
+    }
+    interpreter.start_session(record.name())?;
+    interpreter.run_fragment(&Fragment::Expression(expr.clone())).map_err(|err| match err {
+        RuntimeError::WithBacktrace(cause, _) => *cause,
+        err => err
+    })
+}
+
+// a variable without a value is only fine on its own, as an operand it is reported by name
+fn evaluate_operand(record: &ActivationRecord, expr: &Expr) -> Result<Literal, RuntimeError> {
+    let mut unset = Unset { record, name: None };
+    unset.visit_expr(expr);
+    if let Some(name) = unset.name {
+        return Err(RuntimeError::UndefinedVariable(name));
+    }
+    evaluate_in(record, expr)?.ok_or(RuntimeError::MissingArgument)
+}
+
+// finds the first variable the record has no value for
+struct Unset<'a> {
+    record: &'a ActivationRecord,
+    name: Option<String>,
+}
+
+impl Visitor for Unset<'_> {
+    fn visit_var(&mut self, node: &VarNode) {
+        let name = token_text(&node.name);
+        if self.name.is_none() && self.record.get_item(&name).is_none() {
+            self.name = Some(name.to_lowercase());
+        }
+    }
+}
+
+// numbers compare by value whatever their type, strings and booleans only with their own kind
+fn compare(op: TokenType, lhs: &Literal, rhs: &Literal) -> Result<Option<Ordering>, RuntimeError> {
+    match (lhs, rhs) {
+        (Literal::Int(x), Literal::Int(y)) => Ok(Some(x.cmp(y))),
+        (Literal::Text(x), Literal::Text(y)) => Ok(Some(x.cmp(y))),
+        (Literal::Bool(x), Literal::Bool(y)) => Ok(Some(x.cmp(y))),
+        _ => match (lhs.to_float(), rhs.to_float()) {
+            (Some(x), Some(y)) => Ok(x.partial_cmp(&y)),
+            _ => Err(RuntimeError::UnsupportedOperands(op, lhs.literal_type(), rhs.literal_type()))
+        }
+    }
+}
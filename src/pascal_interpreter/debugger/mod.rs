@@ -0,0 +1,363 @@
+//! Source-level debugger: line breakpoints with optional conditions, stepping into, over and out of
+//! calls, the variables of every record on the call stack, and watch expressions.
+//!
+//! A [`Debugger`] runs a program through an [`Engine`] and hands every stop to its [`DebugHandler`],
+//! which looks at the run through a [`Pause`] and says how it goes on. [`console::Console`] is the
+//! handler of `pascal debug`; a closure does for programmatic use:
+//!
+//! ```no_run
+//! use pascal_interpreter::{debugger::{Debugger, Pause, Resume}, Engine, RunOptions};
+//!
+//! let source = "program Main; var x : integer;\nbegin\n  x := 1;\n  x := x * 2\nend.";
+//! let mut debugger = Debugger::new(|pause: &mut Pause| {
+//!     println!("{}: x = {:?}", pause.position(), pause.variable(0, "x"));
+//!     Resume::StepOver
+//! });
+//! debugger.breakpoints_mut().add(4, Some("x = 1")).unwrap();
+//! debugger.run(&mut Engine::new(), source, &RunOptions::default(), Resume::Continue);
+//! ```
+//!
+//! Conditions, watches and the values given to variables are Pascal expressions over the variables
+//! of a record, which may be compared with `=`, `<>`, `<`, `<=`, `>` or `>=`. They cannot call
+//! procedures or functions.
+
+pub mod console;
+mod expr;
+
+use crate::pascal_interpreter::{
+    ast::nodes::Stmt,
+    engine::{Engine, Outcome, RunOptions},
+    error::RuntimeError,
+    literal::Literal,
+    observer::Observer,
+    record::{ARCallStack, ActivationRecord, Backtrace},
+    symbols::Type,
+    token::{Position, Span},
+};
+
+use expr::DebugExpr;
+
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+
+/// How the run goes on after a stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Run until a breakpoint
+    Continue,
+    /// Stop at the next statement, inside a call if the current one makes one
+    StepIn,
+    /// Stop at the next statement of the current procedure or a caller
+    StepOver,
+    /// Stop at the next statement of a caller
+    StepOut,
+    /// End the run with `RuntimeError::Stopped`
+    Quit,
+}
+
+/// Why the run stopped
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    /// A step finished
+    Step,
+    /// The breakpoint with the id was reached and its condition holds
+    Breakpoint(usize),
+    /// The breakpoint with the id was reached, but its condition could not be evaluated
+    ConditionFailed(usize, RuntimeError),
+}
+
+/// Stops the run at the statements starting on a line
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    id: usize,
+    line: u32,
+    condition: Option<DebugExpr>,
+    hits: usize,
+}
+
+impl Breakpoint {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn condition(&self) -> Option<&str> {
+        self.condition.as_ref().map(DebugExpr::text)
+    }
+
+    /// Times the run stopped at the breakpoint
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    breakpoints: Vec<Breakpoint>,
+    last_id: usize,
+}
+
+impl Breakpoints {
+    /// Stop at `line`, if given only where the `condition` holds, e.g. `add(12, Some("i >= 10"))`.
+    /// Returns the id of the breakpoint.
+    pub fn add(&mut self, line: u32, condition: Option<&str>) -> Result<usize, RuntimeError> {
+        let condition = condition.map(DebugExpr::parse_condition).transpose()?;
+        self.last_id += 1;
+        self.breakpoints.push(Breakpoint { id: self.last_id, line, condition, hits: 0 });
+        Ok(self.last_id)
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+        self.breakpoints.len() < count
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints.iter()
+    }
+
+    // the first breakpoint of the line whose condition holds in the record
+    fn hit(&mut self, line: u32, record: &ActivationRecord) -> Option<StopReason> {
+        for breakpoint in self.breakpoints.iter_mut().filter(|breakpoint| breakpoint.line == line) {
+            let reason = match breakpoint.condition.as_ref().map(|condition| condition.holds(record)) {
+                None | Some(Ok(true)) => StopReason::Breakpoint(breakpoint.id),
+                Some(Ok(false)) => continue,
+                Some(Err(err)) => StopReason::ConditionFailed(breakpoint.id, err)
+            };
+            breakpoint.hits += 1;
+            return Some(reason);
+        }
+        None
+    }
+}
+
+/// Expressions whose values are of interest at every stop, numbered from 1
+#[derive(Debug, Clone, Default)]
+pub struct Watches {
+    watches: Vec<DebugExpr>,
+}
+
+impl Watches {
+    /// Watch an expression, returning its number
+    pub fn add(&mut self, expression: &str) -> Result<usize, RuntimeError> {
+        self.watches.push(DebugExpr::parse(expression)?);
+        Ok(self.watches.len())
+    }
+
+    /// Stop watching the expression with the number; the ones after it move up
+    pub fn remove(&mut self, number: usize) -> bool {
+        if number == 0 || number > self.watches.len() {
+            return false;
+        }
+        self.watches.remove(number - 1);
+        true
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.watches.iter().map(DebugExpr::text)
+    }
+}
+
+/// Decides what happens at each stop of a debugged run
+pub trait DebugHandler {
+    fn paused(&mut self, pause: &mut Pause<'_>) -> Resume;
+}
+
+impl<F> DebugHandler for F where F: FnMut(&mut Pause<'_>) -> Resume {
+    fn paused(&mut self, pause: &mut Pause<'_>) -> Resume {
+        self(pause)
+    }
+}
+
+/// The run stopped before a statement, with its call stack open for inspection and changes
+pub struct Pause<'a> {
+    statement: &'a Stmt,
+    span: Span,
+    reason: StopReason,
+    callstack: &'a mut ARCallStack,
+    breakpoints: &'a mut Breakpoints,
+    watches: &'a mut Watches,
+}
+
+impl Pause<'_> {
+    pub fn reason(&self) -> &StopReason {
+        &self.reason
+    }
+
+    /// The statement about to run
+    pub fn statement(&self) -> &Stmt {
+        self.statement
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn position(&self) -> Position {
+        self.statement.position().expect("the run only stops at statements with a position")
+    }
+
+    /// Records on the call stack, the program's included
+    pub fn depth(&self) -> usize {
+        self.callstack.records.len()
+    }
+
+    /// Record `depth` frames below the innermost one, which the statement runs in
+    pub fn frame(&self, depth: usize) -> Option<&ActivationRecord> {
+        self.callstack.frame(depth)
+    }
+
+    pub fn backtrace(&self) -> Backtrace {
+        self.callstack.backtrace()
+    }
+
+    pub fn variable(&self, depth: usize, name: &str) -> Option<&Literal> {
+        self.frame(depth)?.get_item(name)
+    }
+
+    /// Give a variable or parameter of the record `depth` frames down a new value. It has to fit
+    /// the declared type, an integer being turned real for a real one, whether the variable is set
+    /// yet or not.
+    pub fn set_variable(&mut self, depth: usize, name: &str, value: Literal) -> Result<(), RuntimeError> {
+        let frame = self.callstack.frame_mut(depth).ok_or(RuntimeError::StackUnderflow)?;
+        let declared = frame.declared_type(name).ok_or_else(|| RuntimeError::UndefinedVariable(name.to_lowercase()))?;
+        let value = match (declared, value) {
+            (Type::Integer, value @ Literal::Int(_)) | (Type::Float, value @ Literal::Float(_)) | (Type::String, value @ Literal::Text(_)) => value,
+            (Type::Float, Literal::Int(int)) => Literal::from_float(int as f64),
+            (declared, value) => return Err(RuntimeError::VariableTypeMismatch(name.to_lowercase(), declared.clone(), value.literal_type()))
+        };
+        frame.set_item(name, value);
+        Ok(())
+    }
+
+    /// Value of an expression over the variables of the record `depth` frames down, None when
+    /// it is a variable that is not set
+    pub fn evaluate(&self, depth: usize, expression: &str) -> Result<Option<Literal>, RuntimeError> {
+        let frame = self.frame(depth).ok_or(RuntimeError::StackUnderflow)?;
+        DebugExpr::parse(expression)?.evaluate(frame)
+    }
+
+    /// The watch expressions with their values in the innermost record
+    pub fn watch_values(&self) -> Vec<(&str, Result<Option<Literal>, RuntimeError>)> {
+        let frame = self.callstack.peek();
+        self.watches.watches.iter()
+            .map(|watch| (watch.text(), frame.ok_or(RuntimeError::StackUnderflow).and_then(|frame| watch.evaluate(frame))))
+            .collect()
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        self.breakpoints
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        self.breakpoints
+    }
+
+    pub fn watches(&self) -> &Watches {
+        self.watches
+    }
+
+    pub fn watches_mut(&mut self) -> &mut Watches {
+        self.watches
+    }
+}
+
+// what the debugger and the observer of its run share
+struct Session<H> {
+    handler: H,
+    breakpoints: Breakpoints,
+    watches: Watches,
+    resume: Resume,
+    // records on the call stack when the run was resumed, for stepping over and out
+    resumed_depth: usize,
+}
+
+pub struct Debugger<H> {
+    session: Rc<RefCell<Session<H>>>,
+}
+
+impl<H: DebugHandler + 'static> Debugger<H> {
+    pub fn new(handler: H) -> Self {
+        let session = Session {
+            handler,
+            breakpoints: Breakpoints::default(),
+            watches: Watches::default(),
+            resume: Resume::Continue,
+            resumed_depth: 0,
+        };
+        Debugger { session: Rc::new(RefCell::new(session)) }
+    }
+
+    pub fn breakpoints_mut(&mut self) -> RefMut<'_, Breakpoints> {
+        RefMut::map(self.session.borrow_mut(), |session| &mut session.breakpoints)
+    }
+
+    pub fn watches_mut(&mut self) -> RefMut<'_, Watches> {
+        RefMut::map(self.session.borrow_mut(), |session| &mut session.watches)
+    }
+
+    pub fn handler_mut(&mut self) -> RefMut<'_, H> {
+        RefMut::map(self.session.borrow_mut(), |session| &mut session.handler)
+    }
+
+    /// Run `source` through `engine`, with its natives, globals and limits. The run starts as if
+    /// resumed with `start`: `Resume::StepIn` stops at the first statement, `Resume::Continue` at
+    /// the first breakpoint.
+    pub fn run(&mut self, engine: &mut Engine, source: &str, options: &RunOptions, start: Resume) -> Outcome {
+        {
+            let mut session = self.session.borrow_mut();
+            session.resume = start;
+            session.resumed_depth = 1;
+        }
+        engine.run_observed(source, options, Some(Box::new(DebugObserver { session: Rc::clone(&self.session) })))
+    }
+}
+
+struct DebugObserver<H> {
+    session: Rc<RefCell<Session<H>>>,
+}
+
+impl<H: DebugHandler> Observer for DebugObserver<H> {
+    fn statement_entered(&mut self, stmt: &Stmt, span: Span, callstack: &mut ARCallStack) -> Result<(), RuntimeError> {
+        let mut session = self.session.borrow_mut();
+        let Session { handler, breakpoints, watches, resume, resumed_depth } = &mut *session;
+        if *resume == Resume::Quit {
+            return Err(RuntimeError::Stopped)
+        }
+        let line = match stmt.position() {
+            Some(position) => position.line,
+            None => return Ok(())
+        };
+        let depth = callstack.records.len();
+        let reason = match callstack.peek().and_then(|record| breakpoints.hit(line, record)) {
+            Some(reason) => reason,
+            None => {
+                let stepped = match resume {
+                    Resume::Continue => false,
+                    Resume::StepIn => true,
+                    Resume::StepOver => depth <= *resumed_depth,
+                    Resume::StepOut | Resume::Quit => depth < *resumed_depth,
+                };
+                if !stepped {
+                    return Ok(())
+                }
+                StopReason::Step
+            }
+        };
+
+        *resume = handler.paused(&mut Pause { statement: stmt, span, reason, callstack, breakpoints, watches });
+        *resumed_depth = depth;
+        match resume {
+            Resume::Quit => Err(RuntimeError::Stopped),
+            _ => Ok(())
+        }
+    }
+}
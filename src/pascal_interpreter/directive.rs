@@ -0,0 +1,155 @@
+//! Compiler directives read from `{$...}` and `(*$...*)` comments: switches that toggle interpreter
+//! checks for the code after them, and conditional compilation with `{$DEFINE}`, `{$UNDEF}`,
+//! `{$IFDEF}`, `{$IFNDEF}`, `{$ELSE}` and `{$ENDIF}`.
+//!
+//! Only the overflow check switch `{$Q}` changes what a program does. The range check, I/O check and
+//! boolean evaluation switches `{$R}`, `{$I}` and `{$B}` are read and tracked like it, but the
+//! language has no subranges, arrays, I/O or boolean operators for them to apply to, so they have
+//! no effect and the lexer warns about them.
+
+/// Interpreter check toggled by a switch directive such as `{$Q+}` or `{$OVERFLOWCHECKS OFF}`.
+/// Only overflow checks are implemented; the language has no subranges, I/O or boolean operators
+/// for the others to apply to, so the lexer accepts them with a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Switch {
+    RangeChecks,
+    OverflowChecks,
+    IoChecks,
+    CompleteBooleanEval,
+}
+
+impl Switch {
+    fn from_letter(letter: char) -> Option<Switch> {
+        match letter.to_ascii_uppercase() {
+            'R' => Some(Switch::RangeChecks),
+            'Q' => Some(Switch::OverflowChecks),
+            'I' => Some(Switch::IoChecks),
+            'B' => Some(Switch::CompleteBooleanEval),
+            _ => None
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Switch> {
+        match name {
+            "RANGECHECKS" => Some(Switch::RangeChecks),
+            "OVERFLOWCHECKS" => Some(Switch::OverflowChecks),
+            "IOCHECKS" => Some(Switch::IoChecks),
+            "BOOLEVAL" => Some(Switch::CompleteBooleanEval),
+            _ => None
+        }
+    }
+
+    /// Whether the interpreter consults the switch
+    pub fn is_implemented(self) -> bool {
+        self == Switch::OverflowChecks
+    }
+
+    fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+/// State of all switches for a region of source code.
+/// Overflow and I/O checks start enabled, range checks and complete boolean evaluation disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Switches {
+    enabled: u8,
+}
+
+impl Default for Switches {
+    fn default() -> Self {
+        Switches { enabled: Switch::OverflowChecks.mask() | Switch::IoChecks.mask() }
+    }
+}
+
+impl Switches {
+    pub fn is_enabled(&self, switch: Switch) -> bool {
+        self.enabled & switch.mask() != 0
+    }
+
+    pub fn set(&mut self, switch: Switch, enabled: bool) {
+        if enabled {
+            self.enabled |= switch.mask();
+        } else {
+            self.enabled &= !switch.mask();
+        }
+    }
+}
+
+/// Compiler directive found in a `{$...}` or `(*$...*)` comment
+#[derive(Debug, Clone, PartialEq)]
+pub enum Directive {
+    Switches(Vec<(Switch, bool)>),
+    Define(String),
+    Undef(String),
+    IfDef(String),
+    IfNDef(String),
+    Else,
+    EndIf,
+    Mode(String),
+    // directives of other compilers are ignored, as Turbo Pascal and FPC do
+    Unknown,
+}
+
+impl Directive {
+    /// Parse the text between `{$` and `}`. Returns `None` for malformed known directives.
+    pub fn parse(text: &str) -> Option<Directive> {
+        let text = text.trim();
+        let (name, argument) = match text.find(char::is_whitespace) {
+            Some(index) => (&text[..index], text[index..].trim()),
+            None => (text, ""),
+        };
+        let name = name.to_uppercase();
+
+        let symbol = || {
+            let valid = !argument.is_empty() && argument.chars().all(|c| c.is_alphanumeric() || c == '_');
+            Some(argument.to_uppercase()).filter(|_| valid)
+        };
+
+        match name.as_str() {
+            "DEFINE" => symbol().map(Directive::Define),
+            "UNDEF" => symbol().map(Directive::Undef),
+            "IFDEF" => symbol().map(Directive::IfDef),
+            "IFNDEF" => symbol().map(Directive::IfNDef),
+            "ELSE" => Some(Directive::Else),
+            "ENDIF" => Some(Directive::EndIf),
+            "MODE" => symbol().map(Directive::Mode),
+            _ => {
+                if let Some(switch) = Switch::from_name(&name) {
+                    return match argument.to_uppercase().as_str() {
+                        "ON" | "+" => Some(Directive::Switches(vec![(switch, true)])),
+                        "OFF" | "-" => Some(Directive::Switches(vec![(switch, false)])),
+                        _ => None
+                    };
+                }
+                let is_switch_list = matches!(text.chars().nth(1), Some('+') | Some('-'));
+                if is_switch_list {
+                    Directive::parse_short_switches(text)
+                } else {
+                    Some(Directive::Unknown)
+                }
+            }
+        }
+    }
+
+    // `R+` or a comma separated list such as `R+,Q-`; switches of other compilers are skipped
+    fn parse_short_switches(text: &str) -> Option<Directive> {
+        let mut switches = Vec::new();
+        for item in text.split(',') {
+            let mut chars = item.trim().chars();
+            let letter = chars.next()?;
+            let enabled = match chars.next() {
+                Some('+') => true,
+                Some('-') => false,
+                _ => return None
+            };
+            if chars.next().is_some() {
+                return None;
+            }
+            if let Some(switch) = Switch::from_letter(letter) {
+                switches.push((switch, enabled));
+            }
+        }
+        Some(Directive::Switches(switches))
+    }
+}
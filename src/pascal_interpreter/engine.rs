@@ -0,0 +1,200 @@
+use crate::pascal_interpreter::{
+    error::RuntimeError,
+    interpreter::Interpreter,
+    lexer::LexerOptions,
+    limits::{CancelHandle, Usage},
+    literal::Literal,
+    native::NativeFunction,
+    observer::Observer,
+    parser::Parser,
+    record::ActivationRecord,
+    semantic_analyzer::SemanticAnalyzer,
+    symbols::Type,
+};
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+/// Settings of a single run
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub lexer: LexerOptions,
+    /// Statements and expression nodes the program may evaluate, unbounded when None
+    pub max_steps: Option<u64>,
+    /// Wall-clock time the program may run for, counted from the start of interpreting
+    pub timeout: Option<Duration>,
+    /// Activation records the call stack may hold at once, the program's own included
+    pub max_records: Option<usize>,
+    /// Bytes of string values the program may hold at once
+    pub max_value_bytes: Option<usize>,
+}
+
+/// How a run ended. Each failing stage has its own variant so callers can report them differently.
+#[derive(Debug)]
+pub enum Outcome {
+    /// The program ran to its final `end.`; holds the record of its global variables
+    Completed(ActivationRecord),
+    /// Lexing or parsing failed
+    SyntaxError(RuntimeError),
+    /// The program parsed, but uses undeclared names, wrong argument counts and the like
+    SemanticError(RuntimeError),
+    /// The program failed while running, with the Pascal call stack attached
+    RuntimeError(RuntimeError),
+}
+
+impl Outcome {
+    pub fn is_completed(&self) -> bool {
+        matches!(self, Outcome::Completed(_))
+    }
+
+    pub fn error(&self) -> Option<&RuntimeError> {
+        match self {
+            Outcome::Completed(_) => None,
+            Outcome::SyntaxError(err) | Outcome::SemanticError(err) | Outcome::RuntimeError(err) => Some(err)
+        }
+    }
+
+    pub fn into_result(self) -> Result<ActivationRecord, RuntimeError> {
+        match self {
+            Outcome::Completed(globals) => Ok(globals),
+            Outcome::SyntaxError(err) | Outcome::SemanticError(err) | Outcome::RuntimeError(err) => Err(err)
+        }
+    }
+}
+
+/// Runs Pascal source through the parser, the semantic analyzer and the interpreter
+#[derive(Default)]
+pub struct Engine {
+    natives: Vec<NativeFunction>,
+    initial_globals: Vec<(String, Literal)>,
+    // globals of the last run, as they were when it finished or failed
+    globals: Option<ActivationRecord>,
+    // handle of the next run, replaced when it starts
+    cancel: CancelHandle,
+    usage: Usage,
+    observers: Vec<Box<dyn Observer>>,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine { natives: Vec::new(), initial_globals: Vec::new(), globals: None, cancel: CancelHandle::new(), usage: Usage::default(), observers: Vec::new() }
+    }
+
+    /// Handle that stops the next run of this engine from another thread, or the one in progress.
+    /// Each run gets a handle of its own, so a cancelled run does not stop the ones after it.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Give a global variable a value before the program starts, e.g. `engine.set_global("limit", 10)`.
+    /// The program has to declare it with a matching type, an integer is accepted for a `real`.
+    pub fn set_global(&mut self, name: &str, value: impl Into<Literal>) {
+        self.initial_globals.retain(|(global, _)| !global.eq_ignore_ascii_case(name));
+        self.initial_globals.push((name.to_owned(), value.into()));
+    }
+
+    /// Value of a global after the last run, e.g. `engine.get_global::<i64>("total") == Some(42)`.
+    /// None when the run never got to execute, the global is unset or holds another type.
+    pub fn get_global<T: TryFrom<Literal>>(&self, name: &str) -> Option<T> {
+        let value = self.globals.as_ref()?.get_item(name)?;
+        T::try_from(value.clone()).ok()
+    }
+
+    /// Steps and peak memory of the last run, zero when it did not get to execute
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    /// Record of all globals after the last run
+    pub fn globals(&self) -> Option<&ActivationRecord> {
+        self.globals.as_ref()
+    }
+
+    /// Tell `observer` about the statements, values and calls of the following runs,
+    /// e.g. a [`Tracer`](crate::observer::Tracer)
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    /// Make a Rust function callable from Pascal expressions, e.g.
+    /// `engine.register_fn("GetTemperature", &[Type::Integer], Type::Float, |args| ...)`
+    pub fn register_fn<F>(&mut self, name: &str, params: &[Type], return_type: Type, body: F)
+        where F: Fn(&[Literal]) -> Result<Literal, RuntimeError> + 'static
+    {
+        self.natives.push(NativeFunction::function(name, params, return_type, body));
+    }
+
+    pub fn register_native(&mut self, native: NativeFunction) {
+        self.natives.push(native);
+    }
+
+    /// Make a Rust function callable from Pascal as a procedure statement
+    pub fn register_procedure<F>(&mut self, name: &str, params: &[Type], body: F)
+        where F: Fn(&[Literal]) -> Result<(), RuntimeError> + 'static
+    {
+        self.natives.push(NativeFunction::procedure(name, params, body));
+    }
+
+    pub fn run_source(&mut self, source: &str, options: &RunOptions) -> Outcome {
+        self.run_observed(source, options, None)
+    }
+
+    // run with an observer of this run only, after the ones of the engine
+    pub(crate) fn run_observed(&mut self, source: &str, options: &RunOptions, observer: Option<Box<dyn Observer>>) -> Outcome {
+        self.globals = None;
+        self.usage = Usage::default();
+        let cancel = std::mem::replace(&mut self.cancel, CancelHandle::new());
+        let mut program = match Parser::from(source, options.lexer.clone()).parse() {
+            Ok(program) => program,
+            Err(err) => return Outcome::SyntaxError(err)
+        };
+        let mut analyzer = SemanticAnalyzer::new();
+        for native in &self.natives {
+            analyzer.define_native(native);
+        }
+        if let Err(err) = analyzer.analyze(&mut program) {
+            return Outcome::SemanticError(err)
+        }
+
+        let mut interpreter = Interpreter::new();
+        for native in &self.natives {
+            interpreter.define_native(native.clone());
+        }
+        for (name, value) in &self.initial_globals {
+            match initial_value(&analyzer, name, value) {
+                Ok(value) => interpreter.set_global(name, value),
+                Err(err) => return Outcome::SemanticError(err)
+            }
+        }
+        let fuel = interpreter.fuel_mut();
+        fuel.set_max_steps(options.max_steps);
+        fuel.set_deadline(options.timeout.map(|timeout| Instant::now() + timeout));
+        fuel.set_cancel_handle(cancel);
+        let memory = interpreter.memory_mut();
+        memory.set_max_records(options.max_records);
+        memory.set_max_value_bytes(options.max_value_bytes);
+        let engine_observers = self.observers.len();
+        for observer in self.observers.drain(..).chain(observer) {
+            interpreter.add_observer(observer);
+        }
+        let result = interpreter.interpret(&program);
+        self.observers = interpreter.take_observers();
+        self.observers.truncate(engine_observers);
+        self.globals = interpreter.globals().cloned();
+        self.usage = interpreter.usage();
+        match result {
+            Ok(_) => Outcome::Completed(self.globals.clone().expect("a finished program leaves its record")),
+            Err(err) => Outcome::RuntimeError(err)
+        }
+    }
+}
+
+// the value checked against the declared type of the global, as an integer turned real where needed
+fn initial_value(analyzer: &SemanticAnalyzer, name: &str, value: &Literal) -> Result<Literal, RuntimeError> {
+    let declared = analyzer.global_type(name).ok_or_else(|| RuntimeError::UndefinedVariable(name.to_lowercase()))?;
+    match (value, declared) {
+        (Literal::Int(_), Type::Integer) | (Literal::Float(_), Type::Float) | (Literal::Text(_), Type::String) => Ok(value.clone()),
+        (Literal::Int(int), Type::Float) => Ok(Literal::from_float(*int as f64)),
+        _ => Err(RuntimeError::GlobalTypeMismatch(name.to_lowercase(), declared.clone(), value.literal_type()))
+    }
+}
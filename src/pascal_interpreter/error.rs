@@ -1,21 +1,140 @@
+use super::limits::Limit;
+use super::literal::LiteralType;
+use super::record::Backtrace;
+use super::symbols::Type;
 use super::token::{
+    Position,
     Token,
     TokenType
 };
 
+use std::fmt;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum RuntimeError {
-    UnexpectedToken(Token, TokenType),
+    UnexpectedToken(Box<Token>, TokenType),
+    /// An operand is missing where the token is, e.g. in `x := ;`
+    ExpectedExpression(Box<Token>),
+    ReservedWordAsIdentifier(Box<Token>),
     MissingArgument,
-    UnexpectedChar(char),
+    UnexpectedChar(char, Position),
+    UnterminatedComment(Position),
+    MalformedDirective(String, Position),
+    NumberOutOfRange(String, Position),
+    UnbalancedConditional(Position),
+    UnterminatedConditional(Position),
     UndefinedVariable(String),
-    UnhandledBinaryOp(Token),
-    UnhandledUnaryOp(Token),
+    UnhandledBinaryOp(Box<Token>),
+    UnhandledUnaryOp(Box<Token>),
     DivisionByZero,
+    IntegerOverflow,
+    /// The operator, named by its token type, does not apply to values of the two types
+    UnsupportedOperands(TokenType, LiteralType, LiteralType),
     IllformedVarExpr,
     UnknownType(String),
     VariableRedefinition(String),
     StackUnderflow,
-    UnsupportedArgumentTypeByOp(Token),
-    MissingProcedure
-}
\ No newline at end of file
+    UnsupportedArgumentTypeByOp(Box<Token>),
+    MissingProcedure,
+    /// A call names something that is not a declared procedure or function
+    UndefinedProcedure(String),
+    ArgumentCountMismatch(String, usize, usize),
+    ArgumentTypeMismatch(String, usize, Type, Type),
+    NotAFunction(String),
+    /// Failure reported by a native function, see `native::NativeFunction`
+    NativeError(String),
+    /// A native function gave a result that does not fit its declared return type, None standing for no value
+    NativeReturnTypeMismatch(String, Option<Type>, Option<LiteralType>),
+    /// A value set from the host does not fit the declared type of the global
+    GlobalTypeMismatch(String, Type, LiteralType),
+    InvalidAstJson(String),
+    /// A debugger condition is not a comparison
+    NotACondition(String),
+    /// A debugger assignment gives a variable a value that does not fit its declared type
+    VariableTypeMismatch(String, Type, LiteralType),
+    /// An observer stopped the run, as a debugger does when told to quit
+    Stopped,
+    /// The run went over one of its limits; carries the call stack at that point
+    ResourceExhausted(Limit, Backtrace),
+    WithBacktrace(Box<RuntimeError>, Backtrace)
+}
+
+impl RuntimeError {
+    /// Pascal call stack at the point of failure, if the error was raised while interpreting
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match self {
+            RuntimeError::WithBacktrace(_, backtrace) | RuntimeError::ResourceExhausted(_, backtrace) => Some(backtrace),
+            _ => None
+        }
+    }
+}
+// the token as written, or what it is when it has no text of its own
+fn found(token: &Token) -> String {
+    match token.token_type() {
+        TokenType::Eof => TokenType::Eof.to_string(),
+        _ if !token.text().is_empty() => format!("'{}'", token.text()),
+        _ => format!("'{}'", token.literal())
+    }
+}
+
+fn returned(value_type: &Option<LiteralType>) -> String {
+    value_type.map_or_else(|| String::from("no value"), |value_type| format!("a {}", value_type))
+}
+
+/// Writes the error for people, positioned as `line:col:` where it is known. The backtrace of a
+/// runtime error is left out, see [`RuntimeError::backtrace`].
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::UnexpectedToken(token, expected) => {
+                write!(f, "{}: expected {}, found {}", token.position(), expected, found(token))
+            },
+            RuntimeError::ExpectedExpression(token) => write!(f, "{}: expected an expression, found {}", token.position(), found(token)),
+            RuntimeError::ReservedWordAsIdentifier(token) => {
+                write!(f, "{}: expected identifier, found reserved word {}", token.position(), found(token))
+            },
+            RuntimeError::MissingArgument => write!(f, "an operand or argument has no value"),
+            RuntimeError::UnexpectedChar(ch, position) => write!(f, "{}: unexpected character '{}'", position, ch),
+            RuntimeError::UnterminatedComment(position) => write!(f, "{}: comment is not closed", position),
+            RuntimeError::MalformedDirective(text, position) => write!(f, "{}: malformed directive {}", position, text),
+            RuntimeError::NumberOutOfRange(text, position) => write!(f, "{}: number {} is out of range", position, text),
+            RuntimeError::UnbalancedConditional(position) => write!(f, "{}: {{$ELSE}} or {{$ENDIF}} without {{$IFDEF}}", position),
+            RuntimeError::UnterminatedConditional(position) => write!(f, "{}: {{$IFDEF}} without {{$ENDIF}}", position),
+            RuntimeError::UndefinedVariable(name) => write!(f, "undefined variable {}", name),
+            RuntimeError::UnhandledBinaryOp(token) | RuntimeError::UnhandledUnaryOp(token) => {
+                write!(f, "{}: unsupported operator {}", token.position(), found(token))
+            },
+            RuntimeError::DivisionByZero => write!(f, "division by zero"),
+            RuntimeError::IntegerOverflow => write!(f, "integer overflow"),
+            RuntimeError::UnsupportedOperands(op, lhs, rhs) => write!(f, "{} does not apply to {} and {}", op, lhs, rhs),
+            RuntimeError::IllformedVarExpr => write!(f, "ill-formed variable or expression"),
+            RuntimeError::UnknownType(name) => write!(f, "unknown type {}", name),
+            RuntimeError::VariableRedefinition(name) => write!(f, "{} is declared twice", name),
+            RuntimeError::StackUnderflow => write!(f, "call stack is empty"),
+            RuntimeError::UnsupportedArgumentTypeByOp(token) => {
+                write!(f, "{}: {} does not apply to its operand", token.position(), found(token))
+            },
+            RuntimeError::MissingProcedure => write!(f, "called procedure has no body"),
+            RuntimeError::UndefinedProcedure(name) => write!(f, "undefined procedure {}", name),
+            RuntimeError::ArgumentCountMismatch(name, expected, actual) => {
+                write!(f, "{} expects {} arguments, found {}", name, expected, actual)
+            },
+            RuntimeError::ArgumentTypeMismatch(name, index, expected, actual) => {
+                write!(f, "argument {} of {}: expected {}, found {}", index, name, expected, actual)
+            },
+            RuntimeError::NotAFunction(name) => write!(f, "{} is a procedure and gives no value", name),
+            RuntimeError::NativeError(message) => write!(f, "{}", message),
+            RuntimeError::NativeReturnTypeMismatch(name, expected, actual) => match expected {
+                Some(expected) => write!(f, "{} returned {} instead of a {}", name, returned(actual), expected),
+                None => write!(f, "{} returned {} instead of no value", name, returned(actual))
+            },
+            RuntimeError::GlobalTypeMismatch(name, declared, given) => write!(f, "{} is declared {}, but was given a {}", name, declared, given),
+            RuntimeError::InvalidAstJson(message) => write!(f, "invalid AST JSON: {}", message),
+            RuntimeError::NotACondition(text) => write!(f, "{} is not a comparison", text),
+            RuntimeError::VariableTypeMismatch(name, declared, given) => write!(f, "{} is declared {}, but was given a {}", name, declared, given),
+            RuntimeError::Stopped => write!(f, "stopped"),
+            RuntimeError::ResourceExhausted(limit, _) => write!(f, "{}", limit),
+            RuntimeError::WithBacktrace(cause, _) => write!(f, "{}", cause)
+        }
+    }
+}
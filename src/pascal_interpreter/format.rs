@@ -0,0 +1,743 @@
+//! Canonical formatting of Pascal source, in the spirit of `pasfmt`.
+//!
+//! The formatter works on the concrete syntax tree, so comments, directives and code in inactive
+//! `{$IFDEF}` regions are kept where they were. Everything else about the layout is decided here:
+//! one statement and one declaration per line, indentation by nesting, single spaces around
+//! operators, and groups such as argument lists that are broken over several lines only when they
+//! do not fit. Blank lines between statements and declarations are kept, collapsed to one.
+//!
+//! Formatting is idempotent, and parsing the output gives an AST equal to the one of the input.
+
+use crate::pascal_interpreter::{
+    ast::nodes::*,
+    cst::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken},
+    directive::{Switch, Switches},
+    error::RuntimeError,
+    lexer::LexerOptions,
+    literal::Literal,
+    parser::Parser,
+    token::{Token, TokenType, Trivia, TriviaKind},
+};
+
+/// How reserved words are spelled in the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordCase {
+    Lower,
+    Upper,
+    /// as written in the source
+    Preserve,
+}
+
+/// Where `BEGIN` and `END` of a compound statement go relative to the code around them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BeginPlacement {
+    /// in line with the procedure heading or the enclosing statements
+    Aligned,
+    /// one level further in, with the statements another level in (GNU style)
+    Indented,
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub keyword_case: KeywordCase,
+    pub indent_width: usize,
+    pub begin_placement: BeginPlacement,
+    /// Width the formatter tries to stay within by wrapping expressions and lists
+    pub max_line_length: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            keyword_case: KeywordCase::Lower,
+            indent_width: 2,
+            begin_placement: BeginPlacement::Aligned,
+            max_line_length: 80,
+        }
+    }
+}
+
+/// Formats a program parsed with [`Parser::parse_with_syntax`]
+pub fn format(syntax: &SyntaxNode, options: &FormatOptions) -> String {
+    let mut builder = DocBuilder { options, newlines: 1, commented: false, leading_written: None };
+    let mut docs = Vec::new();
+    builder.node(syntax, &mut docs);
+    docs.push(Doc::HardLine);
+    Printer::new(options).print(&docs)
+}
+
+/// Formats a program that only exists as an AST, e.g. one built by a tool or loaded from JSON.
+/// Fails when the AST has no source form, e.g. a name that is not an identifier.
+pub fn format_program(program: &ProgramNode, options: &FormatOptions) -> Result<String, RuntimeError> {
+    let mut unparser = Unparser { out: String::new(), switches: Switches::default() };
+    unparser.program(program);
+    let mut parser = Parser::from(&unparser.out, LexerOptions::default());
+    let (_, syntax) = parser.parse_with_syntax()?;
+    Ok(format(&syntax, options))
+}
+
+// Layout description handed to the printer, after Wadler's "prettier printer"
+enum Doc {
+    Text(String),
+    /// a space, or a line break when the enclosing group does not fit
+    Line,
+    /// nothing, or a line break when the enclosing group does not fit
+    SoftLine,
+    /// a line break unless already at the start of a line
+    HardLine,
+    /// an empty line unless there already is one
+    BlankLine,
+    Indent(Vec<Doc>),
+    Group(Vec<Doc>),
+}
+
+fn space() -> Doc {
+    Doc::Text(" ".to_owned())
+}
+
+struct DocBuilder<'o> {
+    options: &'o FormatOptions,
+    // line breaks in the source since the last token or comment
+    newlines: usize,
+    // a comment was written since the last token
+    commented: bool,
+    // start of the token whose leading trivia was already written by `line`
+    leading_written: Option<usize>,
+}
+
+impl DocBuilder<'_> {
+    fn node(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        match node.kind() {
+            SyntaxKind::Program => self.program(node, out),
+            SyntaxKind::Block => self.block(node, false, out),
+            SyntaxKind::VarSection => self.var_section(node, out),
+            SyntaxKind::VarDecl | SyntaxKind::Param => self.names_with_type(node, out),
+            SyntaxKind::ProcedureDecl => self.procedure_decl(node, out),
+            SyntaxKind::CompoundStatement => self.placed_compound(node, out),
+            SyntaxKind::ProcedureCall => self.procedure_call(node, out),
+            SyntaxKind::BinaryExpr => self.binary_expr(node, out),
+            SyntaxKind::Assignment => self.spaced(node, TokenType::Assignment, out),
+            SyntaxKind::TypeSpec | SyntaxKind::Empty | SyntaxKind::Variable | SyntaxKind::Number |
+            SyntaxKind::UnaryExpr | SyntaxKind::ParenExpr => self.elements(node, out),
+        }
+    }
+
+    // children written one after the other, without any space
+    fn elements(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        for child in node.children_with_tokens() {
+            self.element(&child, out);
+        }
+    }
+
+    fn element(&mut self, element: &SyntaxElement, out: &mut Vec<Doc>) {
+        match element {
+            SyntaxElement::Node(node) => self.node(node, out),
+            SyntaxElement::Token(token) => self.token(token, out),
+        }
+    }
+
+    // children with a space on both sides of the token of `token_type`
+    fn spaced(&mut self, node: &SyntaxNode, token_type: TokenType, out: &mut Vec<Doc>) {
+        for child in node.children_with_tokens() {
+            match &child {
+                SyntaxElement::Token(token) if token.token_type() == token_type => {
+                    out.push(space());
+                    self.token(token, out);
+                    out.push(space());
+                },
+                _ => self.element(&child, out)
+            }
+        }
+    }
+
+    // starts a line with `first`, writing the comments in front of it and keeping a blank line
+    // above it here rather than in whatever group the token ends up in
+    fn line(&mut self, first: Option<SyntaxToken>, out: &mut Vec<Doc>) {
+        out.push(Doc::HardLine);
+        let first = match first {
+            Some(first) if self.leading_written != Some(first.token().span().start) => first,
+            _ => return
+        };
+        for trivia in first.token().leading_trivia() {
+            self.trivia(trivia, out);
+        }
+        if self.newlines > 1 {
+            out.push(Doc::BlankLine);
+        }
+        if self.commented && self.newlines > 0 {
+            out.push(Doc::HardLine);
+            self.commented = false;
+        }
+        self.leading_written = Some(first.token().span().start);
+    }
+
+    fn program(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        for child in node.children_with_tokens() {
+            match &child {
+                SyntaxElement::Token(token) if token.token_type() == TokenType::Program => {
+                    self.line(Some(token.clone()), out);
+                    self.token(token, out);
+                },
+                SyntaxElement::Token(token) if token.token_type() == TokenType::Identifier => {
+                    out.push(space());
+                    self.token(token, out);
+                },
+                SyntaxElement::Node(block) => {
+                    self.line(first_token(block), out);
+                    self.block(block, false, out);
+                },
+                _ => self.element(&child, out)
+            }
+        }
+    }
+
+    // procedures nested in a procedure are indented one level
+    fn block(&mut self, node: &SyntaxNode, nested: bool, out: &mut Vec<Doc>) {
+        for child in node.children() {
+            if nested && child.kind() == SyntaxKind::ProcedureDecl {
+                let mut procedure = Vec::new();
+                self.node(&child, &mut procedure);
+                out.push(Doc::Indent(procedure));
+            } else {
+                self.line(first_token(&child), out);
+                self.node(&child, out);
+            }
+        }
+    }
+
+    fn var_section(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        let mut declarations = Vec::new();
+        for child in node.children_with_tokens() {
+            match &child {
+                SyntaxElement::Node(declaration) => {
+                    self.line(first_token(declaration), &mut declarations);
+                    self.node(declaration, &mut declarations);
+                },
+                SyntaxElement::Token(token) if token.token_type() == TokenType::Var => self.token(token, out),
+                _ => self.element(&child, &mut declarations)
+            }
+        }
+        out.push(Doc::Indent(declarations));
+    }
+
+    // `a, b, c: integer`, wrapping the names when they are too long for one line
+    fn names_with_type(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        let mut group = Vec::new();
+        let mut names = Vec::new();
+        for child in node.children_with_tokens() {
+            match &child {
+                SyntaxElement::Token(token) => match token.token_type() {
+                    TokenType::Identifier if group.is_empty() => self.token(token, &mut group),
+                    TokenType::Identifier => {
+                        let mut name = vec![Doc::Line];
+                        self.token(token, &mut name);
+                        names.push(Doc::Group(name));
+                    },
+                    TokenType::Colon => {
+                        group.push(Doc::Indent(std::mem::take(&mut names)));
+                        self.token(token, &mut group);
+                        group.push(space());
+                    },
+                    _ => self.token(token, &mut names)
+                },
+                SyntaxElement::Node(type_spec) => self.node(type_spec, &mut group)
+            }
+        }
+        out.push(Doc::Group(group));
+    }
+
+    fn procedure_decl(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        let mut heading = Vec::new();
+        let mut params: Option<Vec<Doc>> = None;
+        let mut in_heading = true;
+        for child in node.children_with_tokens() {
+            match &child {
+                SyntaxElement::Token(token) => match (token.token_type(), params.as_mut()) {
+                    (TokenType::Procedure, _) => {
+                        self.line(Some(token.clone()), out);
+                        self.token(token, out);
+                    },
+                    (TokenType::Identifier, _) => {
+                        heading.push(space());
+                        self.token(token, &mut heading);
+                    },
+                    (TokenType::Lparen, _) => {
+                        self.token(token, &mut heading);
+                        params = Some(vec![Doc::SoftLine]);
+                    },
+                    (TokenType::Semi, Some(params)) => {
+                        self.token(token, params);
+                        params.push(Doc::Line);
+                    },
+                    (TokenType::Rparen, _) => {
+                        heading.push(Doc::Indent(params.take().unwrap_or_default()));
+                        heading.push(Doc::SoftLine);
+                        self.token(token, &mut heading);
+                    },
+                    (TokenType::Semi, None) if in_heading => {
+                        self.token(token, &mut heading);
+                        out.push(Doc::Group(std::mem::take(&mut heading)));
+                        in_heading = false;
+                    },
+                    _ => self.token(token, out)
+                },
+                SyntaxElement::Node(param) if param.kind() == SyntaxKind::Param => {
+                    if let Some(params) = params.as_mut() {
+                        self.node(param, params);
+                    }
+                },
+                SyntaxElement::Node(block) => {
+                    self.line(first_token(block), out);
+                    self.block(block, true, out);
+                }
+            }
+        }
+    }
+
+    fn placed_compound(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        match self.options.begin_placement {
+            BeginPlacement::Aligned => self.compound(node, out),
+            BeginPlacement::Indented => {
+                let mut indented = vec![Doc::HardLine];
+                self.compound(node, &mut indented);
+                out.push(Doc::Indent(indented));
+            }
+        }
+    }
+
+    fn compound(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        let mut statements = Vec::new();
+        for child in node.children_with_tokens() {
+            match &child {
+                SyntaxElement::Token(token) if token.token_type() == TokenType::Begin => self.token(token, out),
+                SyntaxElement::Token(token) if token.token_type() == TokenType::End => {
+                    out.push(Doc::Indent(std::mem::take(&mut statements)));
+                    self.line(Some(token.clone()), out);
+                    self.token(token, out);
+                },
+                SyntaxElement::Node(statement) => {
+                    self.line(first_token(statement), &mut statements);
+                    self.node(statement, &mut statements);
+                },
+                _ => self.element(&child, &mut statements)
+            }
+        }
+    }
+
+    fn procedure_call(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        let mut args = vec![Doc::SoftLine];
+        for child in node.children_with_tokens() {
+            match &child {
+                SyntaxElement::Token(token) => match token.token_type() {
+                    TokenType::Comma => {
+                        self.token(token, &mut args);
+                        args.push(Doc::Line);
+                    },
+                    TokenType::Rparen => {
+                        let mut group = vec![Doc::Indent(std::mem::take(&mut args)), Doc::SoftLine];
+                        self.token(token, &mut group);
+                        out.push(Doc::Group(group));
+                    },
+                    _ => self.token(token, out)
+                },
+                SyntaxElement::Node(arg) => self.node(arg, &mut args)
+            }
+        }
+    }
+
+    // in a chain of operators of one precedence, `a + b - c`, an operand
+    // moves to the next line only when it does not fit on the current one
+    fn binary_expr(&mut self, node: &SyntaxNode, out: &mut Vec<Doc>) {
+        let mut chain = Vec::new();
+        operator_chain(node, &mut chain);
+        let mut rest = Vec::new();
+        let mut elements = chain.iter();
+        if let Some(first) = elements.next() {
+            self.element(first, out);
+        }
+        for element in elements {
+            match element {
+                SyntaxElement::Token(op) => {
+                    rest.push(space());
+                    self.token(op, &mut rest);
+                },
+                SyntaxElement::Node(operand) => {
+                    let mut group = vec![Doc::Line];
+                    self.node(operand, &mut group);
+                    rest.push(Doc::Group(group));
+                }
+            }
+        }
+        out.push(Doc::Indent(rest));
+    }
+
+    fn token(&mut self, token: &SyntaxToken, out: &mut Vec<Doc>) {
+        let token = token.token();
+        if self.leading_written.take() != Some(token.span().start) {
+            for trivia in token.leading_trivia() {
+                self.trivia(trivia, out);
+            }
+        }
+
+        if self.newlines > 0 && self.commented {
+            out.push(Doc::HardLine);
+        } else if self.commented && !closes(token.token_type()) {
+            out.push(space());
+        }
+        if !token.text().is_empty() {
+            out.push(Doc::Text(self.spelling(token)));
+        }
+
+        self.newlines = 0;
+        self.commented = false;
+        for trivia in token.trailing_trivia() {
+            self.trivia(trivia, out);
+        }
+    }
+
+    fn trivia(&mut self, trivia: &Trivia, out: &mut Vec<Doc>) {
+        let text = trivia.text();
+        match trivia.kind() {
+            TriviaKind::Whitespace => self.newlines += text.matches('\n').count(),
+            // kept verbatim, it may not even be Pascal
+            TriviaKind::InactiveCode => {
+                let code = text.trim_end();
+                out.push(Doc::Text(code.to_owned()));
+                self.newlines = text[code.len()..].matches('\n').count();
+                self.commented = true;
+            },
+            TriviaKind::Comment | TriviaKind::Directive => {
+                if self.newlines > 0 {
+                    out.push(Doc::HardLine);
+                    if self.newlines > 1 {
+                        out.push(Doc::BlankLine);
+                    }
+                } else {
+                    out.push(space());
+                }
+                out.push(Doc::Text(text.to_owned()));
+                if text.starts_with("//") {
+                    out.push(Doc::HardLine);
+                }
+                self.newlines = 0;
+                self.commented = true;
+            }
+        }
+    }
+
+    fn spelling(&self, token: &Token) -> String {
+        if !token.token_type().is_reserved_word() {
+            return token.text().to_owned()
+        }
+        match self.options.keyword_case {
+            KeywordCase::Lower => token.text().to_lowercase(),
+            KeywordCase::Upper => token.text().to_uppercase(),
+            KeywordCase::Preserve => token.text().to_owned(),
+        }
+    }
+}
+
+// tokens that follow a comment without a space
+fn closes(token_type: TokenType) -> bool {
+    matches!(token_type, TokenType::Semi | TokenType::Comma | TokenType::Rparen | TokenType::Dot)
+}
+
+fn first_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.children_with_tokens().into_iter().find_map(|child| match child {
+        SyntaxElement::Token(token) => Some(token),
+        SyntaxElement::Node(node) => first_token(&node)
+    })
+}
+
+fn precedence(op: TokenType) -> u8 {
+    match op {
+        TokenType::Plus | TokenType::Minus => 1,
+        _ => 2
+    }
+}
+
+// operands and operators of a left-associative chain of one precedence, in source order
+fn operator_chain(node: &SyntaxNode, chain: &mut Vec<SyntaxElement>) {
+    let children = node.children_with_tokens();
+    let op = children.iter().find_map(|child| match child {
+        SyntaxElement::Token(token) => Some(token.token_type()),
+        SyntaxElement::Node(_) => None
+    });
+    for (i, child) in children.iter().enumerate() {
+        match child {
+            SyntaxElement::Node(left) if i == 0 && left.kind() == SyntaxKind::BinaryExpr
+                && op.map(precedence) == operator(left).map(precedence) => operator_chain(left, chain),
+            _ => chain.push(child.clone())
+        }
+    }
+}
+
+fn operator(node: &SyntaxNode) -> Option<TokenType> {
+    node.tokens().first().map(SyntaxToken::token_type)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+struct Printer {
+    indent_width: usize,
+    max_line_length: usize,
+    out: String,
+    column: usize,
+    // indentation of the current line while nothing has been written on it
+    pending_indent: Option<usize>,
+}
+
+impl Printer {
+    fn new(options: &FormatOptions) -> Self {
+        Printer {
+            indent_width: options.indent_width,
+            max_line_length: options.max_line_length,
+            out: String::new(),
+            column: 0,
+            pending_indent: Some(0),
+        }
+    }
+
+    fn print(mut self, docs: &[Doc]) -> String {
+        let mut stack: Vec<(usize, Mode, &Doc)> = docs.iter().rev().map(|doc| (0, Mode::Break, doc)).collect();
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(text) => self.text(text),
+                Doc::Line if mode == Mode::Flat => self.text(" "),
+                Doc::SoftLine if mode == Mode::Flat => {},
+                Doc::Line | Doc::SoftLine | Doc::HardLine => self.newline(indent),
+                Doc::BlankLine => {
+                    self.newline(indent);
+                    if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+                        self.out.push('\n');
+                    }
+                },
+                Doc::Indent(docs) => {
+                    stack.extend(docs.iter().rev().map(|doc| (indent + self.indent_width, mode, doc)));
+                },
+                Doc::Group(docs) => {
+                    let mode = if mode == Mode::Flat || self.fits(docs, &stack) { Mode::Flat } else { Mode::Break };
+                    stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                }
+            }
+        }
+        self.out
+    }
+
+    fn text(&mut self, text: &str) {
+        if text == " " && (self.pending_indent.is_some() || self.out.ends_with(' ')) {
+            return
+        }
+        if let Some(indent) = self.pending_indent.take() {
+            self.out.push_str(&" ".repeat(indent));
+        }
+        self.out.push_str(text);
+        match text.rfind('\n') {
+            Some(i) => self.column = text[i + 1..].chars().count(),
+            None => self.column += text.chars().count()
+        }
+    }
+
+    fn newline(&mut self, indent: usize) {
+        if self.pending_indent.is_none() {
+            let content = self.out.trim_end_matches(' ').len();
+            self.out.truncate(content);
+            self.out.push('\n');
+        }
+        self.pending_indent = Some(indent);
+        self.column = indent;
+    }
+
+    // whether `docs` fit flat on the current line, along with what follows up to the next line break
+    fn fits(&self, docs: &[Doc], rest: &[(usize, Mode, &Doc)]) -> bool {
+        let mut width = self.max_line_length as isize - self.column as isize;
+        let mut pending: Vec<(Mode, &Doc)> = docs.iter().rev().map(|doc| (Mode::Flat, doc)).collect();
+        let mut rest = rest.iter().rev();
+        loop {
+            let (mode, doc) = match pending.pop() {
+                Some(next) => next,
+                None => match rest.next() {
+                    Some((_, mode, doc)) => (*mode, *doc),
+                    None => return true
+                }
+            };
+            match doc {
+                Doc::Text(text) => {
+                    let first_line = text.split('\n').next().unwrap_or_default();
+                    width -= first_line.chars().count() as isize;
+                    if first_line.len() < text.len() {
+                        return width >= 0
+                    }
+                },
+                Doc::Line if mode == Mode::Flat => width -= 1,
+                Doc::SoftLine if mode == Mode::Flat => {},
+                // a line ended by a `//` comment may still have its groups flat
+                Doc::Line | Doc::SoftLine | Doc::HardLine | Doc::BlankLine => return true,
+                Doc::Indent(docs) | Doc::Group(docs) => pending.extend(docs.iter().rev().map(|doc| (mode, doc))),
+            }
+            if width < 0 {
+                return false
+            }
+        }
+    }
+}
+
+const SWITCH_LETTERS: [(Switch, char); 4] = [
+    (Switch::RangeChecks, 'R'),
+    (Switch::OverflowChecks, 'Q'),
+    (Switch::IoChecks, 'I'),
+    (Switch::CompleteBooleanEval, 'B'),
+];
+
+// Writes an AST back as plain source for the formatter to lay out. Parentheses are added where
+// precedence needs them, and switch directives where the checks of an operator change.
+struct Unparser {
+    out: String,
+    switches: Switches,
+}
+
+impl Unparser {
+    fn program(&mut self, program: &ProgramNode) {
+        self.out.push_str("program ");
+        self.out.push_str(program.name.to_str().unwrap_or_default());
+        self.out.push_str(";\n");
+        self.block(&program.block);
+        self.out.push_str(".\n");
+    }
+
+    // the grammar has a single `var` section, ahead of the procedures
+    fn block(&mut self, block: &BlockNode) {
+        let var_decls: Vec<_> = block.declarations.iter().filter_map(|declaration| match declaration {
+            Decl::Var(var_decl) => Some(var_decl),
+            Decl::Procedure(_) => None
+        }).collect();
+        if !var_decls.is_empty() {
+            self.out.push_str("var\n");
+        }
+        for var_decl in var_decls {
+            self.token(&var_decl.var.name);
+            self.out.push_str(" : ");
+            self.token(&var_decl.type_spec.token);
+            self.out.push_str(";\n");
+        }
+        for declaration in &block.declarations {
+            if let Decl::Procedure(procedure) = declaration {
+                self.out.push_str("procedure ");
+                self.out.push_str(procedure.name.to_str().unwrap_or_default());
+                self.out.push('(');
+                for (i, param) in procedure.params.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str("; ");
+                    }
+                    self.token(&param.var.name);
+                    self.out.push_str(" : ");
+                    self.token(&param.param_type.token);
+                }
+                self.out.push_str(");\n");
+                self.block(&procedure.block);
+                self.out.push_str(";\n");
+            }
+        }
+        self.compound(&block.compound_statement);
+    }
+
+    fn compound(&mut self, compound: &CompoundStatementNode) {
+        self.out.push_str("begin\n");
+        for (i, statement) in compound.child_statements.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(";\n");
+            }
+            self.statement(statement);
+        }
+        self.out.push_str("\nend");
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Compound(compound) => self.compound(compound),
+            Stmt::Assignment(assignment) => {
+                self.token(&assignment.left.name);
+                self.out.push_str(" := ");
+                self.expr(&assignment.right);
+            },
+            Stmt::ProcedureCall(call) => self.call(call),
+            Stmt::NoOp(_) => {}
+        }
+    }
+
+    fn call(&mut self, call: &ProcedureCallNode) {
+        self.token(&call.name);
+        self.out.push('(');
+        for (i, arg) in call.actual_params.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(arg);
+        }
+        self.out.push(')');
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Num(num) => self.token(&num.token),
+            Expr::Call(call) => self.call(call),
+            Expr::Var(var) => self.token(&var.name),
+            Expr::UnaryOp(unary) => {
+                self.checks(unary.checks);
+                self.token(&unary.op);
+                self.operand(&unary.node, matches!(*unary.node, Expr::BinaryOp(_)));
+            },
+            Expr::BinaryOp(binary) => {
+                let own = precedence(binary.op.token_type());
+                let binds_looser = |expr: &Expr, max| match expr {
+                    Expr::BinaryOp(child) => precedence(child.op.token_type()) < max,
+                    _ => false
+                };
+                self.operand(&binary.left_side, binds_looser(&binary.left_side, own));
+                self.out.push(' ');
+                self.checks(binary.checks);
+                self.token(&binary.op);
+                self.out.push(' ');
+                self.operand(&binary.right_side, binds_looser(&binary.right_side, own + 1));
+            }
+        }
+    }
+
+    fn operand(&mut self, expr: &Expr, parenthesized: bool) {
+        if parenthesized {
+            self.out.push('(');
+        }
+        self.expr(expr);
+        if parenthesized {
+            self.out.push(')');
+        }
+    }
+
+    fn checks(&mut self, checks: Switches) {
+        for (switch, letter) in SWITCH_LETTERS {
+            let enabled = checks.is_enabled(switch);
+            if enabled != self.switches.is_enabled(switch) {
+                self.out.push_str(&format!("{{${}{}}} ", letter, if enabled { '+' } else { '-' }));
+            }
+        }
+        self.switches = checks;
+    }
+
+    fn token(&mut self, token: &Token) {
+        if !token.text().is_empty() {
+            self.out.push_str(token.text());
+            return
+        }
+        match (token.token_type(), token.literal()) {
+            (TokenType::IntegerType, _) => self.out.push_str("integer"),
+            (TokenType::FloatType, _) => self.out.push_str("real"),
+            (TokenType::StringType, _) => self.out.push_str("string"),
+            (_, Literal::Float(value)) => self.out.push_str(&format!("{:?}", value)),
+            (_, literal) => self.out.push_str(&literal.to_string().replace('\'', ""))
+        }
+    }
+}
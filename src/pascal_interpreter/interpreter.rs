@@ -1,143 +1,418 @@
 use crate::pascal_interpreter::{
-    ast::nodes::*,
-    token::TokenType,
+    ast::{
+        nodes::*,
+        visit::{self, Visitor}
+    },
+    directive::Switch,
+    token::{Span, TokenType},
     error::RuntimeError,
+    limits::{Fuel, Limit, Memory, Usage},
     literal::Literal,
+    logging,
+    native::NativeFunction,
+    observer::Observer,
     record,
     record::CallStack,
     symbols
 };
 
+use std::collections::HashMap;
+use std::rc::Rc;
+
 pub struct Interpreter {
     callstack: record::ARCallStack,
-    pub memory_tester: Vec<record::ActivationRecord>,
+    // procedure declarations of the program by qualified name, as resolved by the semantic analyzer
+    procedures: HashMap<String, Rc<ProcedureDeclNode>>,
+    // native functions by lowercase name
+    natives: HashMap<String, NativeFunction>,
+    // values the host gave globals before the run
+    initial_globals: Vec<(String, Literal)>,
+    // record of the program's globals once its run finished
+    finished_globals: Option<record::ActivationRecord>,
+    fuel: Fuel,
+    memory: Memory,
+    observers: Vec<Box<dyn Observer>>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         Interpreter { 
             callstack: record::ARCallStack::new(),
-            memory_tester: Vec::new(),
+            procedures: HashMap::new(),
+            natives: HashMap::new(),
+            initial_globals: Vec::new(),
+            finished_globals: None,
+            fuel: Fuel::new(),
+            memory: Memory::new(),
+            observers: Vec::new(),
         }
     }
 
-    pub fn interpret(&mut self, program: &mut ProgramNode) -> Result<Option<Literal>, RuntimeError> {
+    /// Make a native function callable; calls reach it once the semantic analyzer resolved them to it
+    pub fn define_native(&mut self, native: NativeFunction) {
+        self.natives.insert(native.name().to_lowercase(), native);
+    }
+
+    /// Value a global variable starts out with in the following runs, instead of being unset
+    pub fn set_global(&mut self, name: &str, value: Literal) {
+        self.initial_globals.retain(|(global, _)| !global.eq_ignore_ascii_case(name));
+        self.initial_globals.push((name.to_owned(), value));
+    }
+
+    /// Record of the program's globals: the live one while running or after a failure, otherwise the one of the last finished run
+    pub fn globals(&self) -> Option<&record::ActivationRecord> {
+        self.callstack.records.first().or(self.finished_globals.as_ref())
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<&Literal> {
+        self.globals().and_then(|globals| globals.get_item(name))
+    }
+
+    /// Record `depth` frames below the innermost one of the running program
+    pub fn frame(&self, depth: usize) -> Option<&record::ActivationRecord> {
+        self.callstack.frame(depth)
+    }
+
+    pub fn frame_mut(&mut self, depth: usize) -> Option<&mut record::ActivationRecord> {
+        self.callstack.frame_mut(depth)
+    }
+
+    /// Step budget, deadline and cancel handle of the following runs, and the steps taken by the last one
+    pub fn fuel(&self) -> &Fuel {
+        &self.fuel
+    }
+
+    pub fn fuel_mut(&mut self) -> &mut Fuel {
+        &mut self.fuel
+    }
+
+    /// Caps on the records and values of the following runs
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Tell `observer` about the statements, values and calls of the following runs
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    /// Remove the observers, e.g. to get at what they collected
+    pub fn take_observers(&mut self) -> Vec<Box<dyn Observer>> {
+        std::mem::take(&mut self.observers)
+    }
+
+    /// Steps and peak memory of the last run, or of the current one so far
+    pub fn usage(&self) -> Usage {
+        Usage::from(&self.fuel, &self.memory)
+    }
+
+    /// Run the program. Errors are returned with the Pascal call stack at the point of failure attached.
+    pub fn interpret(&mut self, program: &ProgramNode) -> Result<Option<Literal>, RuntimeError> {
+        self.callstack = record::ARCallStack::new();
+        self.finished_globals = None;
+        self.fuel.refill();
+        self.memory.clear();
+        self.procedures.clear();
+        ProcedureCollector { scope_path: String::from("global"), procedures: &mut self.procedures }.visit_program(program);
+        // a failing visit returns before popping its record, so the stack still describes the failure
+        self.visit_program(program).map_err(|err| self.with_backtrace(err))?;
+        log::debug!(target: logging::EXEC, "finished after {:?}", self.usage());
+        Ok(None)
+    }
+
+    /// Start a session of fragments run one after the other against the same global record, as in the REPL
+    pub fn start_session(&mut self, name: &str) -> Result<(), RuntimeError> {
         self.callstack = record::ARCallStack::new();
-        self.visit_program(program)
+        self.finished_globals = None;
+        self.fuel.refill();
+        self.memory.clear();
+        self.procedures.clear();
+        let mut ar = record::ActivationRecord::from(Literal::from_str(name.to_owned()), record::ARType::Program, 1);
+        for (name, value) in &self.initial_globals {
+            ar.set_item(name, value.clone());
+        }
+        self.enter(ar)
+    }
+
+    /// Run a fragment of the session, giving the value of an expression. After a failure the
+    /// records of the calls it was in are dropped, so the session goes on at the global level.
+    pub fn run_fragment(&mut self, fragment: &Fragment) -> Result<Option<Literal>, RuntimeError> {
+        let result = match fragment {
+            Fragment::Declarations(declarations) => {
+                self.declare(declarations);
+                let mut collector = ProcedureCollector { scope_path: String::from("global"), procedures: &mut self.procedures };
+                declarations.iter().for_each(|decl| collector.visit_decl(decl));
+                Ok(None)
+            },
+            Fragment::Statements(statements) => statements.iter()
+                .try_for_each(|stmt| self.execute(stmt))
+                .map(|_| None),
+            Fragment::Expression(expr) => self.evaluate(expr)
+        };
+        result.map_err(|err| {
+            let err = self.with_backtrace(err);
+            while self.callstack.records.len() > 1 {
+                self.pop();
+            }
+            err
+        })
+    }
+
+    fn with_backtrace(&self, err: RuntimeError) -> RuntimeError {
+        match err {
+            RuntimeError::ResourceExhausted(..) => err,
+            err => RuntimeError::WithBacktrace(Box::new(err), self.callstack.backtrace())
+        }
+    }
+
+    // every statement and expression node evaluated takes a step
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        self.fuel.step().map_err(|limit| self.exhausted(limit))
+    }
+
+    // push a record, if it fits in the memory limits
+    fn enter(&mut self, record: record::ActivationRecord) -> Result<(), RuntimeError> {
+        self.memory.push_record(record.value_size()).map_err(|limit| self.exhausted(limit))?;
+        self.push(record);
+        Ok(())
+    }
+
+    fn exhausted(&self, limit: Limit) -> RuntimeError {
+        RuntimeError::ResourceExhausted(limit, self.callstack.backtrace())
+    }
+
+    // run a statement, telling the observers first
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        if !self.observers.is_empty() && !matches!(stmt, Stmt::Compound(_) | Stmt::NoOp(_)) {
+            let span = stmt.span().unwrap_or_default();
+            // observers may change variables, which the memory limits have to account for
+            let value_bytes = self.value_bytes();
+            for observer in &mut self.observers {
+                observer.statement_entered(stmt, span, &mut self.callstack)?;
+            }
+            let changed_bytes = self.value_bytes();
+            self.memory.grow(value_bytes, changed_bytes).map_err(|limit| self.exhausted(limit))?;
+        }
+        match stmt {
+            Stmt::Compound(node) => self.visit_compound(node).map(|_| ()),
+            Stmt::Assignment(node) => self.visit_assignment(node).map(|_| ()),
+            Stmt::ProcedureCall(node) => self.visit_procedure_call(node).map(|_| ()),
+            Stmt::NoOp(_) => self.step(),
+        }
+    }
+
+    fn value_bytes(&self) -> usize {
+        self.callstack.records.iter().map(record::ActivationRecord::value_size).sum()
+    }
+
+    // evaluate an expression, telling the observers its value
+    fn evaluate(&mut self, expr: &Expr) -> Result<Option<Literal>, RuntimeError> {
+        let value = match expr {
+            Expr::Num(node) => {
+                self.step()?;
+                Some(node.token.literal().clone())
+            },
+            Expr::Var(node) => self.visit_var(node)?,
+            Expr::BinaryOp(node) => self.visit_binary_op(node)?,
+            Expr::UnaryOp(node) => self.visit_unary_op(node)?,
+            Expr::Call(node) => self.visit_procedure_call(node)?,
+        };
+        if !self.observers.is_empty() {
+            let span = expr.span();
+            for observer in &mut self.observers {
+                observer.expression_evaluated(expr, span, value.as_ref());
+            }
+        }
+        Ok(value)
+    }
+
+    fn notify_entered(&mut self, call: &ProcedureCallNode) {
+        if let Some(record) = self.callstack.peek() {
+            for observer in &mut self.observers {
+                observer.procedure_entered(call, record);
+            }
+        }
+    }
+
+    fn notify_exited(&mut self, call: &ProcedureCallNode, result: Option<&Literal>) {
+        if let Some(record) = self.callstack.peek() {
+            for observer in &mut self.observers {
+                observer.procedure_exited(call, record, result);
+            }
+        }
     }
 }
 
-impl NodeVisitor for Interpreter {
-    fn visit_num(&mut self, visitable: &mut NumNode) -> Result<Option<Literal>, RuntimeError> {
-        Ok(Some(visitable.token.literal().clone()))
+// Finds the procedure declarations of a program, named the way the semantic analyzer qualifies them
+struct ProcedureCollector<'a> {
+    scope_path: String,
+    procedures: &'a mut HashMap<String, Rc<ProcedureDeclNode>>,
+}
+
+impl Visitor for ProcedureCollector<'_> {
+    fn visit_procedure_decl(&mut self, node: &ProcedureDeclNode) {
+        let qualified_name = symbols::qualified_name(&self.scope_path, node.name.to_str().unwrap_or(""));
+        let enclosing_path = std::mem::replace(&mut self.scope_path, qualified_name.clone());
+        visit::walk_procedure_decl(self, node);
+        self.scope_path = enclosing_path;
+        self.procedures.insert(qualified_name, Rc::new(node.clone()));
     }
+}
 
-    fn visit_binary_op(&mut self, visitable: &mut BinaryOpNode) -> Result<Option<Literal>, RuntimeError> {
-        
-        let lhs = visitable.left_side.as_mut().accept_visitor(self)?;
-        let rhs = visitable.right_side.as_mut().accept_visitor(self)?;
+// integer arithmetic under `{$Q-}`, wrapping around instead of failing with IntegerOverflow
+fn wrapping_integer_op(op: TokenType, lhs: &Literal, rhs: &Literal) -> Option<Literal> {
+    let (x, y) = (lhs.to_int()?, rhs.to_int()?);
+    let result = match op {
+        TokenType::Plus => x.wrapping_add(y),
+        TokenType::Minus => x.wrapping_sub(y),
+        TokenType::Multiply => x.wrapping_mul(y),
+        TokenType::IntegerDivision if y != 0 => x.wrapping_div(y),
+        TokenType::Modulus | TokenType::Mod if y != 0 => x.wrapping_rem(y),
+        _ => return None
+    };
+    Some(Literal::from_int(result))
+}
+
+// the nodes are only read, so a call runs the body of its procedure where the procedure map holds it
+impl Interpreter {
+    fn visit_binary_op(&mut self, visitable: &BinaryOpNode) -> Result<Option<Literal>, RuntimeError> {
+        self.step()?;
+        let lhs = self.evaluate(&visitable.left_side)?;
+        let rhs = self.evaluate(&visitable.right_side)?;
         
         let lhs = lhs.ok_or(RuntimeError::MissingArgument)?;
         let rhs = rhs.ok_or(RuntimeError::MissingArgument)?;
 
-        let result = match visitable.op.token_type() {
-            TokenType::Plus     => Ok(Some(lhs + rhs)),
-            TokenType::Minus    => Ok(Some(lhs - rhs)),
-            TokenType::Multiply => Ok(Some(lhs * rhs)),
-            TokenType::IntegerDivision | TokenType::Division => {
-                rhs.to_int().filter(|&val| val != 0).ok_or(RuntimeError::DivisionByZero)?;
-                Ok(Some(lhs / rhs))
+        if !visitable.checks.is_enabled(Switch::OverflowChecks) {
+            if let Some(result) = wrapping_integer_op(visitable.op.token_type(), &lhs, &rhs) {
+                return Ok(Some(result));
             }
-            TokenType::Modulus => Ok(Some(lhs % rhs)),
-            _ => Err(RuntimeError::UnhandledBinaryOp(visitable.op.clone()))
+        }
+
+        let result = match visitable.op.token_type() {
+            TokenType::Plus     => (lhs + rhs)?,
+            TokenType::Minus    => (lhs - rhs)?,
+            TokenType::Multiply => (lhs * rhs)?,
+            TokenType::Division => (lhs / rhs)?,
+            TokenType::IntegerDivision => lhs.int_div(rhs)?,
+            TokenType::Modulus | TokenType::Mod => (lhs % rhs)?,
+            _ => return Err(RuntimeError::UnhandledBinaryOp(Box::new(visitable.op.clone())))
         };
-        result
+        // a concatenation is not held by any record yet, but still has to fit
+        self.memory.fits(result.heap_size()).map_err(|limit| self.exhausted(limit))?;
+        Ok(Some(result))
     }
 
-    fn visit_unary_op(&mut self, visitable: &mut UnaryOpNode) -> Result<Option<Literal>, RuntimeError> {
-        let arg = visitable.node.as_mut().accept_visitor(self)?
+    fn visit_unary_op(&mut self, visitable: &UnaryOpNode) -> Result<Option<Literal>, RuntimeError> {
+        self.step()?;
+        let arg = self.evaluate(&visitable.node)?
             .ok_or(RuntimeError::MissingArgument)?;
 
+        if arg.to_float().is_none() {
+            return Err(RuntimeError::UnsupportedArgumentTypeByOp(Box::new(visitable.op.clone())));
+        }
+
+        let zero = Literal::from_int(0);
+        if !visitable.checks.is_enabled(Switch::OverflowChecks) {
+            if let Some(result) = wrapping_integer_op(visitable.op.token_type(), &zero, &arg) {
+                return Ok(Some(result));
+            }
+        }
+
         match visitable.op.token_type() {
-            TokenType::Plus     => return Ok(Some(Literal::from_int(0)+arg)),
-            TokenType::Minus    => return Ok(Some(Literal::from_int(0)-arg)),
-            _ => Err(RuntimeError::UnhandledUnaryOp(visitable.op.clone())),
+            TokenType::Plus     => Ok(Some((zero + arg)?)),
+            TokenType::Minus    => Ok(Some((zero - arg)?)),
+            _ => Err(RuntimeError::UnhandledUnaryOp(Box::new(visitable.op.clone()))),
         }
     }
 
-    fn visit_compound(&mut self, visitable: &mut CompoundStatementNode) -> Result<Option<Literal>, RuntimeError> {
-        for statement in visitable.child_statements.iter_mut() {
-            statement.accept_visitor(self)?;
+    fn visit_compound(&mut self, visitable: &CompoundStatementNode) -> Result<Option<Literal>, RuntimeError> {
+        self.step()?;
+        for statement in visitable.child_statements.iter() {
+            self.execute(statement)?;
         }
         Ok(None)
     }
 
-    fn visit_var(&mut self, visitable: &mut VarNode) -> Result<Option<Literal>, RuntimeError> {
+    fn visit_var(&mut self, visitable: &VarNode) -> Result<Option<Literal>, RuntimeError> {
+        self.step()?;
         let var_name = visitable.name.literal().to_str().ok_or(RuntimeError::IllformedVarExpr)?;
         
-        let ar = self.peek_mut().ok_or(RuntimeError::StackUnderflow)?;
+        let ar = self.peek().ok_or(RuntimeError::StackUnderflow)?;
         Ok(ar.get_item(var_name).map(|v| v.to_owned()))
     }    
 
-     fn visit_assignment(&mut self, visitable: &mut AssignmentNode) -> Result<Option<Literal>, RuntimeError> {
+     fn visit_assignment(&mut self, visitable: &AssignmentNode) -> Result<Option<Literal>, RuntimeError> {
+        self.step()?;
         let var_name = visitable.left.name.literal().to_str().ok_or(RuntimeError::IllformedVarExpr)?;
-        let expr_result = visitable.right.as_mut().accept_visitor(self)?
+        let expr_result = self.evaluate(&visitable.right)?
             .ok_or(RuntimeError::IllformedVarExpr)?;
 
+        let old_size = self.peek().ok_or(RuntimeError::StackUnderflow)?
+            .get_item(var_name).map_or(0, Literal::heap_size);
+        self.memory.grow(old_size, expr_result.heap_size()).map_err(|limit| self.exhausted(limit))?;
+        if !self.observers.is_empty() {
+            let span = Span { start: visitable.left.name.span().start, end: visitable.right.span().end };
+            for observer in &mut self.observers {
+                observer.variable_assigned(var_name, span, &expr_result);
+            }
+        }
         let ar = self.peek_mut().ok_or(RuntimeError::StackUnderflow)?;
         ar.set_item(var_name, expr_result);
         Ok(None)
     }
 
-    fn visit_no_op(&mut self, _: &mut NoOpNode) -> Result<Option<Literal>, RuntimeError> {
-       Ok(None)
-    }
-
-    fn visit_program(&mut self, visitable: &mut ProgramNode) -> Result<Option<Literal>, RuntimeError> {
-        let ar = record::ActivationRecord::from(visitable.name.clone(), record::ARType::Program, 1);
-        self.push(ar);
-        self.visit_block(&mut visitable.block)?;
-        self.pop();
-        Ok(None)
-    }
-
-     fn visit_block(&mut self, visitable: &mut BlockNode) -> Result<Option<Literal>, RuntimeError> {
-        for decl in &mut visitable.declarations {
-            decl.accept_visitor(self)?;
+    fn visit_program(&mut self, visitable: &ProgramNode) -> Result<Option<Literal>, RuntimeError> {
+        let mut ar = record::ActivationRecord::from(visitable.name.clone(), record::ARType::Program, 1);
+        for (name, value) in &self.initial_globals {
+            ar.set_item(name, value.clone());
         }
-        self.visit_compound(&mut visitable.compound_statement)
-    }
-
-    fn visit_var_decl(&mut self, _: &mut VarDeclNode) -> Result<Option<Literal>, RuntimeError> {
+        self.enter(ar)?;
+        self.visit_block(&visitable.block)?;
+        self.finished_globals = self.pop();
         Ok(None)
     }
 
-    fn visit_type(&mut self, _: &mut TypeNode) -> Result<Option<Literal>, RuntimeError> {
-        Ok(None)
+     fn visit_block(&mut self, visitable: &BlockNode) -> Result<Option<Literal>, RuntimeError> {
+        self.declare(&visitable.declarations);
+        self.visit_compound(&visitable.compound_statement)
     }
 
-    fn visit_procedure_decl(&mut self, _: &mut ProcedureDeclNode) -> Result<Option<Literal>, RuntimeError> {
-        Ok(None)
-    }
+    fn visit_procedure_call(&mut self, visitable: &ProcedureCallNode) -> Result<Option<Literal>, RuntimeError> {
+        self.step()?;
+        let mut ar = record::ActivationRecord::from(visitable.name.literal().clone(), record::ARType::Procedure, 2);
+        ar.set_call_site(visitable.call_site());
 
-    fn visit_param(&mut self, _: &mut ParamNode) -> Result<Option<Literal>, RuntimeError> {
-        Ok(None)
-    }
+        if let Some(symbols::Symbol::Native(..)) = &visitable.proc_symbol {
+            return self.call_native(visitable, ar)
+        }
 
-    fn visit_procedure_call(&mut self, visitable: &mut ProcedureCallNode) -> Result<Option<Literal>, RuntimeError> {
-        let mut ar = record::ActivationRecord::from(visitable.name.clone(), record::ARType::Procedure, 2);
-        
-        match &mut visitable.proc_symbol {
-            Some(symbols::Symbol::Procedure(formal_params, block_node)) => {
-                for (formal, actual) in formal_params.iter_mut().zip(visitable.actual_params.iter_mut()) {
-                    let eval_param = actual.accept_visitor(self)?;
+        match &visitable.proc_symbol {
+            Some(symbols::Symbol::Procedure(formal_params, qualified_name)) => {
+                let procedure = self.procedures.get(qualified_name).cloned().ok_or(RuntimeError::MissingProcedure)?;
+                for (formal, actual) in formal_params.iter().zip(&visitable.actual_params) {
+                    let eval_param = self.evaluate(actual)?;
                     let eval_param = eval_param.ok_or(RuntimeError::MissingArgument)?;
-                    ar.set_item(&formal.0, eval_param);
+                    ar.set_argument(&formal.0, eval_param);
+                    ar.declare(&formal.0, formal.1.clone());
                 }
 
-                self.push(ar);
-                println!("Procedure block node is {:?}", &block_node);
-                self.visit_block(block_node)?;
+                log::debug!(target: logging::EXEC, "calling {} with {:?}", qualified_name, ar.members().collect::<Vec<_>>());
+                log::trace!(target: logging::EXEC, "body of {} is {:?}", qualified_name, procedure.block);
+                self.enter(ar)?;
+                self.notify_entered(visitable);
+                self.visit_block(&procedure.block)?;
+                self.notify_exited(visitable, None);
                 Ok(())
                 
             },
@@ -149,6 +424,39 @@ impl NodeVisitor for Interpreter {
     }
 }
 
+impl Interpreter {
+    // note the declared types of the variables in the innermost record, for the debugger to check values against
+    fn declare(&mut self, declarations: &[Decl]) {
+        if let Some(ar) = self.peek_mut() {
+            for decl in declarations {
+                if let Decl::Var(VarDeclNode { var, var_type: Some(var_type), .. }) = decl {
+                    ar.declare(var.name.literal().to_str().unwrap_or_default(), var_type.clone());
+                }
+            }
+        }
+    }
+
+    // natives get a record of their own too, so they show up in backtraces
+    fn call_native(&mut self, call: &ProcedureCallNode, mut ar: record::ActivationRecord) -> Result<Option<Literal>, RuntimeError> {
+        let name = call.name.literal().to_str().unwrap_or_default().to_lowercase();
+        let native = self.natives.get(&name).cloned().ok_or(RuntimeError::MissingProcedure)?;
+        let mut args = Vec::new();
+        for (i, actual) in call.actual_params.iter().enumerate() {
+            let value = self.evaluate(actual)?.ok_or(RuntimeError::MissingArgument)?;
+            ar.set_argument(&format!("#{}", i + 1), value.clone());
+            args.push(value);
+        }
+
+        log::debug!(target: logging::EXEC, "calling native {} with {:?}", native.name(), args);
+        self.enter(ar)?;
+        self.notify_entered(call);
+        let result = native.call(args)?;
+        self.notify_exited(call, result.as_ref());
+        self.pop();
+        Ok(result)
+    }
+}
+
 impl record::CallStack for Interpreter {
     type Item = record::ActivationRecord;
 
@@ -159,8 +467,9 @@ impl record::CallStack for Interpreter {
     fn pop(&mut self) -> Option<record::ActivationRecord> {
         
         let elem = self.callstack.pop();
-        if elem.is_some() {
-            self.memory_tester.push(elem.as_ref().unwrap().clone());
+        if let Some(record) = &elem {
+            log::debug!(target: logging::EXEC, "leaving {} with {:?}", record.name(), record.members().collect::<Vec<_>>());
+            self.memory.pop_record(record.value_size());
         }
         elem
     }
@@ -1,90 +1,316 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
 use crate::pascal_interpreter::{
+    directive::{Directive, Switches},
     literal::Literal,
+    logging,
     token::*,
     error::RuntimeError,
 };
 
+/// Dialect switches of the lexer
+#[derive(Debug, Clone, Default)]
+pub struct LexerOptions {
+    /// Allow `{ { } }` and `(* (* *) *)` to nest, as in FPC mode
+    pub nested_comments: bool,
+    /// Conditional symbols defined before the first line, like FPC's `-d` option
+    pub defines: HashSet<String>,
+}
+
+// An open `{$IFDEF}`/`{$IFNDEF}` region
+struct Conditional {
+    start: Position,
+    parent_active: bool,
+    active: bool,
+    else_seen: bool,
+}
+
 pub struct Lexer<'a> {
     text: &'a str,
+    // byte offset of `current_char` in `text`
     pos: usize,
     current_char: Option<char>,
     line: u32,
     col: u32,
-    reserved_keywords: HashMap<String, TokenType>,
-    reserved_symbols: HashMap<char, TokenType>
+    options: LexerOptions,
+    defines: HashSet<String>,
+    conditionals: Vec<Conditional>,
+    switches: Switches,
+    // trivia read since the last token
+    trivia: Vec<Trivia>,
+    // byte offset where the inactive code read since the last directive starts
+    inactive_start: Option<usize>,
+    // type of the last token read, telling whether an operand or an operator comes next
+    last_token: Option<TokenType>
 }
 
 impl<'a> Lexer<'a> {
-    pub fn from(text: &'a str) -> Lexer<'a> {
-        if text.is_empty() {
-            panic!("Missing input");
-        }
-
+    pub fn from(text: &'a str, options: LexerOptions) -> Lexer<'a> {
         Lexer {
-            text: &text,
+            text,
             pos: 0,
             current_char: text.chars().next(),
             line: 1u32,
             col: 1u32,
-            reserved_keywords: Lexer::init_reserved_keywords(),
-            reserved_symbols: Lexer::init_reserved_symbols()
+            defines: options.defines.iter().map(|name| name.to_uppercase()).collect(),
+            options,
+            conditionals: Vec::new(),
+            switches: Switches::default(),
+            trivia: Vec::new(),
+            inactive_start: None,
+            last_token: None
         }
     }
 
-    // Advance the `pos` pointer and set the `current_char`
+    // Advance the `pos` pointer past the current character and set the `current_char`
     fn advance(&mut self) {
-        if self.current_char.unwrap() == '\n' {
-            self.line += 1u32;
-            self.col = 0u32;
-        }
+        if let Some(ch) = self.current_char {
+            if ch == '\n' {
+                self.line += 1u32;
+                self.col = 1u32;
+            } else {
+                self.col += 1;
+            }
 
-        self.pos += 1;
-        
-        if self.pos >= self.text.len() {
-            self.current_char = None;
-        } else {
-            self.current_char = self.text.chars().nth(self.pos);
-            self.col+= 1;
+            self.pos += ch.len_utf8();
+            self.current_char = self.text[self.pos..].chars().next();
         }
     }
 
+    fn peek_char(&self) -> Option<char> {
+        self.peek_nth(1)
+    }
+
+    fn peek_nth(&self, offset: usize) -> Option<char> {
+        self.text[self.pos..].chars().nth(offset)
+    }
+
+    // stops after a line break, so every line ends a separate piece of trivia
     fn skip_whitespace(&mut self) {
-        while self.current_char.filter(|c| c.is_whitespace()).is_some() {
+        while let Some(ch) = self.current_char.filter(|c| c.is_whitespace()) {
             self.advance();
+            if ch == '\n' {
+                break;
+            }
         }
     }
 
-    fn skip_comment(&mut self) {    
-        while self.current_char.filter(|&c| c != '}').is_some() {
+    // whether the input continues with `delimiter` at the current position
+    fn at(&self, delimiter: &str) -> bool {
+        self.text[self.pos..].starts_with(delimiter)
+    }
+
+    fn advance_by(&mut self, count: usize) {
+        for _ in 0..count {
             self.advance();
         }
-        self.advance();
-}
+    }
+
+    /// Skip a `{ }` or `(* *)` comment starting at the current character
+    fn skip_comment(&mut self, open: &str, close: &str) -> Result<(), RuntimeError> {
+        let start = Position { line: self.line, col: self.col };
+        self.advance_by(open.len());
+
+        let mut depth = 1;
+        while self.current_char.is_some() {
+            if self.at(close) {
+                self.advance_by(close.len());
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else if self.options.nested_comments && self.at(open) {
+                self.advance_by(open.len());
+                depth += 1;
+            } else {
+                self.advance();
+            }
+        }
+        Err(RuntimeError::UnterminatedComment(start))
+    }
 
-    /// Return an integer consumed from input
-    fn get_number(&mut self) -> Token {
+    /// Read a `{$ }` or `(*$ *)` directive starting at the current character and apply it
+    fn read_directive(&mut self, open: &str, close: &str) -> Result<(), RuntimeError> {
+        let start = Position { line: self.line, col: self.col };
+        self.advance_by(open.len() + 1);
+
+        let mut text = String::new();
+        while !self.at(close) {
+            match self.current_char {
+                Some(ch) => text.push(ch),
+                None => return Err(RuntimeError::UnterminatedComment(start))
+            }
+            self.advance();
+        }
+        self.advance_by(close.len());
+
+        let directive = Directive::parse(&text).ok_or_else(|| RuntimeError::MalformedDirective(text.clone(), start))?;
+        self.apply_directive(directive, start)
+    }
+
+    fn apply_directive(&mut self, directive: Directive, start: Position) -> Result<(), RuntimeError> {
+        match directive {
+            Directive::IfDef(name) => self.open_conditional(self.defines.contains(&name), start),
+            Directive::IfNDef(name) => self.open_conditional(!self.defines.contains(&name), start),
+            Directive::Else => {
+                let conditional = self.conditionals.last_mut()
+                    .filter(|conditional| !conditional.else_seen)
+                    .ok_or(RuntimeError::UnbalancedConditional(start))?;
+                conditional.active = conditional.parent_active && !conditional.active;
+                conditional.else_seen = true;
+            },
+            Directive::EndIf => {
+                self.conditionals.pop().ok_or(RuntimeError::UnbalancedConditional(start))?;
+            },
+            // everything else only counts in code that is compiled
+            _ if !self.is_active() => {},
+            Directive::Define(name) => {
+                self.defines.insert(name);
+            },
+            Directive::Undef(name) => {
+                self.defines.remove(&name);
+            },
+            Directive::Switches(switches) => {
+                for (switch, enabled) in switches {
+                    if !switch.is_implemented() {
+                        log::warn!(target: logging::LEXER, "{}: {:?} switch has no effect", start, switch);
+                    }
+                    self.switches.set(switch, enabled);
+                }
+            },
+            Directive::Mode(mode) => {
+                self.options.nested_comments = mode == "FPC" || mode == "OBJFPC";
+            },
+            Directive::Unknown => {}
+        }
+        Ok(())
+    }
+
+    fn open_conditional(&mut self, condition: bool, start: Position) {
+        let parent_active = self.is_active();
+        self.conditionals.push(Conditional { start, parent_active, active: parent_active && condition, else_seen: false });
+    }
+
+    // whether the last token ends an operand, so an operator may follow it
+    fn after_operand(&self) -> bool {
+        matches!(self.last_token, Some(TokenType::IntegerConst) | Some(TokenType::FloatConst) | Some(TokenType::Identifier) | Some(TokenType::Rparen))
+    }
+
+    // false inside the excluded branch of a conditional
+    fn is_active(&self) -> bool {
+        self.conditionals.last().is_none_or(|conditional| conditional.active)
+    }
+
+    /// The whole source text being lexed
+    pub fn source(&self) -> &'a str {
+        self.text
+    }
+
+    /// Skip a `//` comment up to the end of the line
+    fn skip_line_comment(&mut self) {
+        while self.current_char.filter(|&c| c != '\n').is_some() {
+            self.advance();
+        }
+    }
+
+    // digits of the given radix, dropping `_` separators placed between them
+    fn get_digits(&mut self, radix: u32) -> String {
         let mut result = String::new();
-        while self.current_char.filter(|c| c.is_digit(10)).is_some() {
-            result.push(self.current_char.unwrap());
+        while let Some(ch) = self.current_char {
+            if ch.is_digit(radix) {
+                result.push(ch);
+            } else if ch != '_' || !self.peek_char().is_some_and(|next| next.is_digit(radix)) {
+                break;
+            }
             self.advance();
         }
+        result
+    }
 
-        if self.current_char.filter(|&c| c == '.').is_some() {
-            result.push(self.current_char.unwrap());
+    /// Return a number consumed from input: a decimal integer or real such as `1_000` or `1.5E-3`,
+    /// or an integer written in hex `$FF`, binary `%1010` or octal `&17`
+    fn get_number(&mut self) -> Result<Token, RuntimeError> {
+        let (line, col, start) = (self.line, self.col, self.pos);
+        let radix = match self.current_char {
+            Some('$') => 16,
+            Some('%') => 2,
+            Some('&') => 8,
+            _ => 10
+        };
+        if radix != 10 {
+            self.advance();
+        }
+
+        let mut result = self.get_digits(radix);
+        let mut is_real = false;
+
+        // `1..5` is a range, not the real number `1.` followed by `.5`
+        if radix == 10 && self.current_char == Some('.') && self.peek_char() != Some('.') {
+            result.push('.');
             self.advance();
+            result.push_str(&self.get_digits(10));
+            is_real = true;
+        }
 
-            while self.current_char.filter(|c| c.is_digit(10)).is_some() {
-                result.push(self.current_char.unwrap());
+        let exponent_follows = match (self.peek_char(), self.peek_nth(2)) {
+            (Some('+'), Some(digit)) | (Some('-'), Some(digit)) | (Some(digit), _) => digit.is_ascii_digit(),
+            _ => false
+        };
+        if radix == 10 && matches!(self.current_char, Some('e') | Some('E')) && exponent_follows {
+            result.push('e');
+            self.advance();
+            if let Some(sign) = self.current_char.filter(|&c| c == '+' || c == '-') {
+                result.push(sign);
                 self.advance();
             }
-            let value = Literal::from_float(result.parse::<f64>().unwrap());
-            return Token::new(TokenType::FloatConst, value, self.line, self.col);
+            result.push_str(&self.get_digits(10));
+            is_real = true;
+        }
+
+        let position = Position { line, col };
+        if is_real {
+            let value = result.parse::<f64>().ok().filter(|value| value.is_finite())
+                .ok_or_else(|| RuntimeError::NumberOutOfRange(self.text_from(start), position))?;
+            return Ok(self.token(TokenType::FloatConst, Literal::from_float(value), start, line, col));
+        }
+
+        let value = i64::from_str_radix(&result, radix)
+            .map_err(|_| RuntimeError::NumberOutOfRange(self.text_from(start), position))?;
+        Ok(self.token(TokenType::IntegerConst, Literal::from_int(value), start, line, col))
+    }
+
+    // source text from the byte offset `start` up to the current position
+    fn text_from(&self, start: usize) -> String {
+        self.text[start..self.pos].to_owned()
+    }
+
+    // record the source from `start` up to the current position as trivia
+    // everything but directives between a false `{$IFDEF}` and its `{$ELSE}` or `{$ENDIF}`
+    // becomes one piece of inactive code, pushed once the region ends
+    fn push_trivia(&mut self, kind: TriviaKind, start: usize) {
+        if kind != TriviaKind::Directive && !self.is_active() {
+            self.inactive_start.get_or_insert(start);
+            return;
+        }
+        self.end_inactive_code(start);
+        self.trivia.push(Trivia::new(kind, self.text_from(start), Span { start, end: self.pos }));
+    }
+
+    // push the open region of inactive code, which ends at the byte offset `end`
+    fn end_inactive_code(&mut self, end: usize) {
+        if let Some(start) = self.inactive_start.take() {
+            self.trivia.push(Trivia::new(TriviaKind::InactiveCode, self.text[start..end].to_owned(), Span { start, end }));
         }
+    }
 
-        let value = Literal::from_int(result.parse::<i64>().unwrap());
-        Token::new(TokenType::IntegerConst, value, self.line, self.col)
+    // a token read from `start` up to the current position, taking the trivia in front of it
+    fn token(&mut self, token_type: TokenType, literal: Literal, start: usize, line: u32, col: u32) -> Token {
+        let span = Span { start, end: self.pos };
+        let leading_trivia = std::mem::take(&mut self.trivia);
+        self.last_token = Some(token_type);
+        Token::new(token_type, literal, line, col)
+            .with_source(span, self.text_from(start), leading_trivia)
+            .with_switches(self.switches)
     }
 
     fn get_identifier(&mut self) -> String {
@@ -98,73 +324,219 @@ impl<'a> Lexer<'a> {
 
     // Lexical analyzer (tokenizer)
     pub fn get_next_token(&mut self) -> Result<Token, RuntimeError> {
-        while self.current_char.is_some() {
-            let ch = self.current_char.unwrap();
+        let token = self.read_token()?;
+        log::trace!(target: logging::LEXER, "{}:{} {:?} {:?}", token.line(), token.col(), token.token_type(), token.text());
+        Ok(token)
+    }
+
+    fn read_token(&mut self) -> Result<Token, RuntimeError> {
+        while let Some(ch) = self.current_char {
+            let (line, col, start) = (self.line, self.col, self.pos);
             if ch.is_whitespace() {
                 self.skip_whitespace();
+                self.push_trivia(TriviaKind::Whitespace, start);
                 continue;
             }
 
             if ch == '{' {
-                self.skip_comment();
+                if self.peek_char() == Some('$') {
+                    self.read_directive("{", "}")?;
+                    self.push_trivia(TriviaKind::Directive, start);
+                } else {
+                    self.skip_comment("{", "}")?;
+                    self.push_trivia(TriviaKind::Comment, start);
+                }
                 continue;
             }
 
-            if ch.is_digit(10) {
-                return Ok(self.get_number());
+            // maximal munch: prefer `:=` over `:` followed by `=`
+            if let Some(next) = self.peek_char() {
+                let operator: String = [ch, next].iter().collect();
+                match reserved_operator(&operator) {
+                    Some(TokenType::LparenStar) => {
+                        if self.at("(*$") {
+                            self.read_directive("(*", "*)")?;
+                            self.push_trivia(TriviaKind::Directive, start);
+                        } else {
+                            self.skip_comment("(*", "*)")?;
+                            self.push_trivia(TriviaKind::Comment, start);
+                        }
+                        continue;
+                    },
+                    Some(TokenType::DoubleSlash) => {
+                        self.skip_line_comment();
+                        self.push_trivia(TriviaKind::Comment, start);
+                        continue;
+                    },
+                    Some(result) if self.is_active() => {
+                        self.advance_by(2);
+                        return Ok(self.token(result, Literal::from_str(operator), start, line, col));
+                    },
+                    _ => {}
+                }
             }
 
-            let entry = self.reserved_symbols.get(&ch); 
-            if entry.is_some() {
-                let result = entry.unwrap().to_owned();
+            // code excluded by conditional compilation is skipped up to the next directive
+            if !self.is_active() {
                 self.advance();
-                return Ok(Token::new(result, Literal::from_str(ch.to_string()), self.line, self.col));
+                self.push_trivia(TriviaKind::InactiveCode, start);
+                continue;
+            }
+
+            // `%` followed by a binary digit starts a binary number where an operand is expected,
+            // e.g. `x := %101`, and is the modulus after one, as in `x%10`
+            let radix = match ch {
+                '$' => 16,
+                '%' if !self.after_operand() => 2,
+                '&' => 8,
+                _ => 10
+            };
+            let first_digit = if radix == 10 { Some(ch) } else { self.peek_char() };
+            if first_digit.is_some_and(|digit| digit.is_digit(radix)) {
+                return self.get_number();
+            }
+
+            if let Some(result) = reserved_symbol(ch) {
+                self.advance();
+                return Ok(self.token(result, Literal::from_str(ch.to_string()), start, line, col));
             } 
             
             if ch.is_alphabetic() {
                 let identifier = self.get_identifier();
-                let entry = self.reserved_keywords.get(&identifier); 
-                let token_type = entry.map(|x| x.to_owned()).unwrap_or(TokenType::Identifier);
-                return Ok(Token::new(token_type, Literal::from_str(identifier), self.line, self.col));   
+                let token_type = reserved_keyword(&identifier).unwrap_or(TokenType::Identifier);
+                return Ok(self.token(token_type, Literal::from_str(identifier), start, line, col));
             }
-            return Err(RuntimeError::UnexpectedChar(ch));
-        }
-        Ok(Token::new(TokenType::EOF, Literal::from_str(String::new()), self.line, self.col))
-    }
-
-    pub fn get_current_char(&self) -> Option<char> {
-        self.current_char
-    }
-
-    fn init_reserved_keywords() -> HashMap<String, TokenType> {
-        let mut reserved_keywords :  HashMap<String, TokenType> = HashMap::new();
-        reserved_keywords.insert(String::from("BEGIN"), TokenType::Begin);
-        reserved_keywords.insert(String::from("END"), TokenType::End);
-        reserved_keywords.insert(String::from("PROGRAM"), TokenType::Program);
-        reserved_keywords.insert(String::from("VAR"), TokenType::Var);
-        reserved_keywords.insert(String::from("DIV"), TokenType::IntegerDivision);
-        reserved_keywords.insert(String::from("INTEGER"), TokenType::IntegerType);
-        reserved_keywords.insert(String::from("REAL"), TokenType::FloatType);
-        reserved_keywords.insert(String::from("PROCEDURE"), TokenType::Procedure);
-        
-        reserved_keywords
-    }  
-
-    fn init_reserved_symbols() -> HashMap<char, TokenType> {
-        let mut reserved_symbols :  HashMap<char, TokenType> = HashMap::new();
-        reserved_symbols.insert('+', TokenType::Plus);
-        reserved_symbols.insert('-', TokenType::Minus);
-        reserved_symbols.insert('*', TokenType::Multiply);
-        reserved_symbols.insert('/', TokenType::Division);
-        reserved_symbols.insert('%', TokenType::Modulus);
-        reserved_symbols.insert('=', TokenType::Assignment);
-        reserved_symbols.insert('(', TokenType::Lparen);
-        reserved_symbols.insert(')', TokenType::Rparen);
-        reserved_symbols.insert(';', TokenType::Semi);
-        reserved_symbols.insert('.', TokenType::Dot);
-        reserved_symbols.insert(':', TokenType::Colon);
-        reserved_symbols.insert(',', TokenType::Comma);
-        
-        reserved_symbols
-    }
-}
\ No newline at end of file
+            return Err(RuntimeError::UnexpectedChar(ch, Position { line, col }));
+        }
+        self.end_inactive_code(self.pos);
+        if let Some(conditional) = self.conditionals.last() {
+            return Err(RuntimeError::UnterminatedConditional(conditional.start));
+        }
+        Ok(self.token(TokenType::Eof, Literal::from_str(String::new()), self.pos, self.line, self.col))
+    }
+}
+
+// Turbo Pascal and ISO 7185 reserved words, in upper case and sorted for binary search
+const RESERVED_KEYWORDS: [(&str, TokenType); 53] = [
+    ("AND", TokenType::And),
+    ("ARRAY", TokenType::Array),
+    ("ASM", TokenType::Asm),
+    ("BEGIN", TokenType::Begin),
+    ("CASE", TokenType::Case),
+    ("CONST", TokenType::Const),
+    ("CONSTRUCTOR", TokenType::Constructor),
+    ("DESTRUCTOR", TokenType::Destructor),
+    ("DIV", TokenType::IntegerDivision),
+    ("DO", TokenType::Do),
+    ("DOWNTO", TokenType::Downto),
+    ("ELSE", TokenType::Else),
+    ("END", TokenType::End),
+    ("EXPORTS", TokenType::Exports),
+    ("FILE", TokenType::File),
+    ("FOR", TokenType::For),
+    ("FUNCTION", TokenType::Function),
+    ("GOTO", TokenType::Goto),
+    ("IF", TokenType::If),
+    ("IMPLEMENTATION", TokenType::Implementation),
+    ("IN", TokenType::In),
+    ("INHERITED", TokenType::Inherited),
+    ("INLINE", TokenType::Inline),
+    ("INTEGER", TokenType::IntegerType),
+    ("INTERFACE", TokenType::Interface),
+    ("LABEL", TokenType::Label),
+    ("LIBRARY", TokenType::Library),
+    ("MOD", TokenType::Mod),
+    ("NIL", TokenType::Nil),
+    ("NOT", TokenType::Not),
+    ("OBJECT", TokenType::Object),
+    ("OF", TokenType::Of),
+    ("OR", TokenType::Or),
+    ("PACKED", TokenType::Packed),
+    ("PROCEDURE", TokenType::Procedure),
+    ("PROGRAM", TokenType::Program),
+    ("REAL", TokenType::FloatType),
+    ("RECORD", TokenType::Record),
+    ("REPEAT", TokenType::Repeat),
+    ("SET", TokenType::Set),
+    ("SHL", TokenType::Shl),
+    ("SHR", TokenType::Shr),
+    ("STRING", TokenType::StringType),
+    ("THEN", TokenType::Then),
+    ("TO", TokenType::To),
+    ("TYPE", TokenType::Type),
+    ("UNIT", TokenType::Unit),
+    ("UNTIL", TokenType::Until),
+    ("USES", TokenType::Uses),
+    ("VAR", TokenType::Var),
+    ("WHILE", TokenType::While),
+    ("WITH", TokenType::With),
+    ("XOR", TokenType::Xor),
+];
+
+// the reserved word `identifier` spells in any case, without allocating an upper case copy
+fn reserved_keyword(identifier: &str) -> Option<TokenType> {
+    let upper = identifier.bytes().map(|byte| byte.to_ascii_uppercase());
+    RESERVED_KEYWORDS.binary_search_by(|(keyword, _)| keyword.bytes().cmp(upper.clone()))
+        .ok()
+        .map(|index| RESERVED_KEYWORDS[index].1)
+}
+
+fn reserved_symbol(ch: char) -> Option<TokenType> {
+    let token_type = match ch {
+        '+' => TokenType::Plus,
+        '-' => TokenType::Minus,
+        '*' => TokenType::Multiply,
+        '/' => TokenType::Division,
+        '%' => TokenType::Modulus,
+        '=' => TokenType::Equal,
+        '<' => TokenType::Less,
+        '>' => TokenType::Greater,
+        '(' => TokenType::Lparen,
+        ')' => TokenType::Rparen,
+        ';' => TokenType::Semi,
+        '.' => TokenType::Dot,
+        ':' => TokenType::Colon,
+        ',' => TokenType::Comma,
+        _ => return None
+    };
+    Some(token_type)
+}
+
+fn reserved_operator(operator: &str) -> Option<TokenType> {
+    let token_type = match operator {
+        ":=" => TokenType::Assignment,
+        "<>" => TokenType::NotEqual,
+        "<=" => TokenType::LessEqual,
+        ">=" => TokenType::GreaterEqual,
+        ".." => TokenType::Range,
+        "(*" => TokenType::LparenStar,
+        "*)" => TokenType::StarRparen,
+        "//" => TokenType::DoubleSlash,
+        _ => return None
+    };
+    Some(token_type)
+}
+
+/// Split source text into tokens, ending with an `Eof` token.
+/// Whitespace, comments, directives and code excluded by conditional compilation are kept as trivia:
+/// a token's trailing trivia runs up to the end of its line and the rest leads the next token,
+/// so the tokens' trivia and text together give back the source.
+pub fn tokenize(text: &str) -> Result<Vec<Token>, RuntimeError> {
+    tokenize_with_options(text, LexerOptions::default())
+}
+
+pub fn tokenize_with_options(text: &str, options: LexerOptions) -> Result<Vec<Token>, RuntimeError> {
+    let mut lexer = Lexer::from(text, options);
+    let mut tokens: Vec<Token> = Vec::new();
+    loop {
+        let mut token = lexer.get_next_token()?;
+        if let Some(previous) = tokens.last_mut() {
+            token.split_trivia_with(previous);
+        }
+        let is_eof = token.token_type() == TokenType::Eof;
+        tokens.push(token);
+        if is_eof {
+            return Ok(tokens);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Bounds on how much work a run may do and how much memory it may hold, for programs that cannot
+//! be trusted to finish.
+//!
+//! Every statement and every expression node the interpreter evaluates is one step. A run stops with
+//! `RuntimeError::ResourceExhausted` once it has taken more steps than allowed, passed its deadline
+//! or been cancelled through a [`CancelHandle`], and likewise once it holds more activation records
+//! or bytes of string values than [`Memory`] allows.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+// the clock is only read every so many steps, reading it on each one would dominate small statements
+const DEADLINE_CHECK_INTERVAL: u64 = 256;
+
+/// The bound a run went over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// More steps than the given budget
+    Steps(u64),
+    /// The wall-clock deadline passed
+    Deadline,
+    /// The run was cancelled through its `CancelHandle`
+    Cancelled,
+    /// More activation records on the call stack than allowed
+    ActivationRecords(usize),
+    /// More bytes of string values held at once than allowed
+    ValueBytes(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Steps(max) => write!(f, "step budget of {} exhausted", max),
+            Limit::Deadline => write!(f, "deadline exceeded"),
+            Limit::Cancelled => write!(f, "cancelled"),
+            Limit::ActivationRecords(max) => write!(f, "more than {} activation records", max),
+            Limit::ValueBytes(max) => write!(f, "more than {} bytes of values", max),
+        }
+    }
+}
+
+/// Stops a run from another thread. Cancelling is permanent: every run the handle is attached to
+/// stops at its next step, which is why an `Engine` attaches a new one to each run.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        CancelHandle { cancelled: Arc::new(AtomicBool::new(false)) }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Steps taken by a run, checked against its budget, deadline and cancel handle
+#[derive(Debug, Clone, Default)]
+pub struct Fuel {
+    max_steps: Option<u64>,
+    deadline: Option<Instant>,
+    cancel: CancelHandle,
+    steps: u64,
+}
+
+impl Fuel {
+    pub fn new() -> Self {
+        Fuel::default()
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: Option<u64>) {
+        self.max_steps = max_steps;
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub fn set_cancel_handle(&mut self, cancel: CancelHandle) {
+        self.cancel = cancel;
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Start counting a new run from zero
+    pub fn refill(&mut self) {
+        self.steps = 0;
+    }
+
+    /// Take one step, failing with the limit it would go over
+    pub fn step(&mut self) -> Result<(), Limit> {
+        self.steps += 1;
+        if self.cancel.is_cancelled() {
+            return Err(Limit::Cancelled);
+        }
+        match (self.max_steps, self.deadline) {
+            (Some(max), _) if self.steps > max => Err(Limit::Steps(max)),
+            (_, Some(deadline)) if self.steps % DEADLINE_CHECK_INTERVAL == 1 && Instant::now() >= deadline => Err(Limit::Deadline),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Activation records and bytes of values a run holds, checked against its caps
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    max_records: Option<usize>,
+    max_value_bytes: Option<usize>,
+    records: usize,
+    value_bytes: usize,
+    peak_records: usize,
+    peak_value_bytes: usize,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Memory::default()
+    }
+
+    pub fn set_max_records(&mut self, max_records: Option<usize>) {
+        self.max_records = max_records;
+    }
+
+    pub fn set_max_value_bytes(&mut self, max_value_bytes: Option<usize>) {
+        self.max_value_bytes = max_value_bytes;
+    }
+
+    /// Forget what the previous run held, keeping the caps
+    pub fn clear(&mut self) {
+        *self = Memory { max_records: self.max_records, max_value_bytes: self.max_value_bytes, ..Memory::default() };
+    }
+
+    /// Account for a record holding `value_bytes` being pushed
+    pub fn push_record(&mut self, value_bytes: usize) -> Result<(), Limit> {
+        match self.max_records {
+            Some(max) if self.records >= max => return Err(Limit::ActivationRecords(max)),
+            _ => {}
+        }
+        self.grow(0, value_bytes)?;
+        self.records += 1;
+        self.peak_records = self.peak_records.max(self.records);
+        Ok(())
+    }
+
+    pub fn pop_record(&mut self, value_bytes: usize) {
+        self.records = self.records.saturating_sub(1);
+        self.value_bytes = self.value_bytes.saturating_sub(value_bytes);
+    }
+
+    /// Account for a value of `old_bytes` being replaced by one of `new_bytes`
+    pub fn grow(&mut self, old_bytes: usize, new_bytes: usize) -> Result<(), Limit> {
+        let value_bytes = self.value_bytes.saturating_sub(old_bytes).saturating_add(new_bytes);
+        match self.max_value_bytes {
+            Some(max) if value_bytes > max => Err(Limit::ValueBytes(max)),
+            _ => {
+                self.value_bytes = value_bytes;
+                self.peak_value_bytes = self.peak_value_bytes.max(value_bytes);
+                Ok(())
+            }
+        }
+    }
+
+    /// Check that a temporary value of `bytes` fits next to what is held
+    pub fn fits(&self, bytes: usize) -> Result<(), Limit> {
+        match self.max_value_bytes {
+            Some(max) if self.value_bytes.saturating_add(bytes) > max => Err(Limit::ValueBytes(max)),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Peak resource usage of a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub steps: u64,
+    pub peak_records: usize,
+    pub peak_value_bytes: usize,
+}
+
+impl Usage {
+    pub fn from(fuel: &Fuel, memory: &Memory) -> Self {
+        Usage { steps: fuel.steps, peak_records: memory.peak_records, peak_value_bytes: memory.peak_value_bytes }
+    }
+}
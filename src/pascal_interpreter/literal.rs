@@ -1,3 +1,8 @@
+use super::error::RuntimeError;
+use super::token::TokenType;
+
+use std::convert::TryFrom;
+use std::fmt;
 use std::ops::{Add, Sub, Mul, Div, Rem};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -8,8 +13,30 @@ pub enum Literal {
     Bool(bool)
 }
 
+/// Type tag of a literal, used to report unsupported operand combinations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralType {
+    Text,
+    Int,
+    Float,
+    Bool
+}
+
+/// Writes the Pascal name of the type
+impl fmt::Display for LiteralType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LiteralType::Text => write!(f, "string"),
+            LiteralType::Int => write!(f, "integer"),
+            LiteralType::Float => write!(f, "real"),
+            LiteralType::Bool => write!(f, "boolean"),
+        }
+    }
+}
+
 impl Literal
 {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(text : String) -> Literal {
         Literal::Text(text)
     }
@@ -48,77 +75,179 @@ impl Literal
         }
     }
 
+    pub fn literal_type(&self) -> LiteralType {
+        match &self {
+            Literal::Text(_) => LiteralType::Text,
+            Literal::Int(_) => LiteralType::Int,
+            Literal::Float(_) => LiteralType::Float,
+            Literal::Bool(_) => LiteralType::Bool,
+        }
+    }
+
+    /// Bytes the value holds beyond its own size, what memory limits count
+    pub fn heap_size(&self) -> usize {
+        match &self {
+            Literal::Text(s) => s.len(),
+            _ => 0,
+        }
+    }
+
+    /// Integer division, `div`, truncating towards zero
+    pub fn int_div(self, rhs: Literal) -> Result<Literal, RuntimeError> {
+        match (self.to_int(), rhs.to_int()) {
+            (Some(_), Some(0))  => Err(RuntimeError::DivisionByZero),
+            (Some(x), Some(y))  => x.checked_div(y).map(Literal::from_int).ok_or(RuntimeError::IntegerOverflow),
+            _ => Err(self.unsupported_operands(TokenType::IntegerDivision, &rhs)),
+        }
+    }
+
+    fn unsupported_operands(&self, op: TokenType, rhs: &Literal) -> RuntimeError {
+        RuntimeError::UnsupportedOperands(op, self.literal_type(), rhs.literal_type())
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Text(s) => write!(f, "'{}'", s),
+            Literal::Int(i) => write!(f, "{}", i),
+            Literal::Float(x) => write!(f, "{}", x),
+            Literal::Bool(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+        }
+    }
+}
+
+impl From<i64> for Literal {
+    fn from(num: i64) -> Self {
+        Literal::Int(num)
+    }
+}
+
+impl From<f64> for Literal {
+    fn from(num: f64) -> Self {
+        Literal::Float(num)
+    }
+}
+
+impl From<bool> for Literal {
+    fn from(boolean: bool) -> Self {
+        Literal::Bool(boolean)
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(text: &str) -> Self {
+        Literal::Text(text.to_owned())
+    }
+}
+
+// The conversions back hand the literal over unchanged when it holds another type
+impl TryFrom<Literal> for i64 {
+    type Error = Literal;
+
+    fn try_from(value: Literal) -> Result<Self, Self::Error> {
+        value.to_int().ok_or(value)
+    }
+}
+
+impl TryFrom<Literal> for f64 {
+    type Error = Literal;
+
+    fn try_from(value: Literal) -> Result<Self, Self::Error> {
+        value.to_float().ok_or(value)
+    }
+}
+
+impl TryFrom<Literal> for bool {
+    type Error = Literal;
+
+    fn try_from(value: Literal) -> Result<Self, Self::Error> {
+        match value {
+            Literal::Bool(boolean) => Ok(boolean),
+            _ => Err(value)
+        }
+    }
+}
+
+impl TryFrom<Literal> for String {
+    type Error = Literal;
+
+    fn try_from(value: Literal) -> Result<Self, Self::Error> {
+        match value {
+            Literal::Text(text) => Ok(text),
+            _ => Err(value)
+        }
+    }
 }
 
 impl Add for Literal {
-    type Output = Literal;
+    type Output = Result<Literal, RuntimeError>;
 
-    fn add(self, rhs: Literal) -> Literal {
+    fn add(self, rhs: Literal) -> Self::Output {
         match (&self, &rhs) {
             (Literal::Text(lhs), Literal::Text(rhs)) => {
                 let mut result = lhs.to_string();
                 result.push_str(rhs);
-                return Literal::Text(result);
+                Ok(Literal::Text(result))
             },
-            (Literal::Int(x),   Literal::Int(y))    => return Literal::from_int(x + y),
-            (Literal::Float(x), Literal::Float(y))  => return Literal::from_float(x + y), 
-            (Literal::Int(x),   Literal::Float(y))  => return Literal::from_float((*x as f64) + y),
-            (Literal::Float(x), Literal::Int(y))    => return Literal::from_float(x + (*y as f64)),
-            _ => panic!("not implemented for {:?} and {:?}", &self, &rhs),
+            (Literal::Int(x),   Literal::Int(y))    => x.checked_add(*y).map(Literal::from_int).ok_or(RuntimeError::IntegerOverflow),
+            (Literal::Float(x), Literal::Float(y))  => Ok(Literal::from_float(x + y)),
+            (Literal::Int(x),   Literal::Float(y))  => Ok(Literal::from_float((*x as f64) + y)),
+            (Literal::Float(x), Literal::Int(y))    => Ok(Literal::from_float(x + (*y as f64))),
+            _ => Err(self.unsupported_operands(TokenType::Plus, &rhs)),
         }
     }
 }
 
 impl Sub for Literal {
-    type Output = Literal;
+    type Output = Result<Literal, RuntimeError>;
 
-    fn sub(self, rhs: Literal) -> Literal {
+    fn sub(self, rhs: Literal) -> Self::Output {
         match (&self, &rhs) {
-            (Literal::Int(x),   Literal::Int(y))    => return Literal::from_int(x - y),
-            (Literal::Float(x), Literal::Float(y))  => return Literal::from_float(x + y), 
-            (Literal::Int(x),   Literal::Float(y))  => return Literal::from_float((*x as f64) + y),
-            (Literal::Float(x), Literal::Int(y))    => return Literal::from_float(x + (*y as f64)),
-            _ => panic!("not implemented for {:?} and {:?}", &self, &rhs),
+            (Literal::Int(x),   Literal::Int(y))    => x.checked_sub(*y).map(Literal::from_int).ok_or(RuntimeError::IntegerOverflow),
+            (Literal::Float(x), Literal::Float(y))  => Ok(Literal::from_float(x - y)),
+            (Literal::Int(x),   Literal::Float(y))  => Ok(Literal::from_float((*x as f64) - y)),
+            (Literal::Float(x), Literal::Int(y))    => Ok(Literal::from_float(x - (*y as f64))),
+            _ => Err(self.unsupported_operands(TokenType::Minus, &rhs)),
         }
     }
 }
 
 
 impl Mul for Literal {
-    type Output = Literal;
+    type Output = Result<Literal, RuntimeError>;
 
-    fn mul(self, rhs: Literal) -> Literal {
+    fn mul(self, rhs: Literal) -> Self::Output {
         match (&self, &rhs) {
-            (Literal::Int(x),   Literal::Int(y))    => return Literal::from_int(x * y),
-            (Literal::Float(x), Literal::Float(y))  => return Literal::from_float(x * y), 
-            (Literal::Int(x),   Literal::Float(y))  => return Literal::from_float((*x as f64) * y),
-            (Literal::Float(x), Literal::Int(y))    => return Literal::from_float(x * (*y as f64)),
-            _ => panic!("not implemented for {:?} and {:?}", &self, &rhs),
+            (Literal::Int(x),   Literal::Int(y))    => x.checked_mul(*y).map(Literal::from_int).ok_or(RuntimeError::IntegerOverflow),
+            (Literal::Float(x), Literal::Float(y))  => Ok(Literal::from_float(x * y)),
+            (Literal::Int(x),   Literal::Float(y))  => Ok(Literal::from_float((*x as f64) * y)),
+            (Literal::Float(x), Literal::Int(y))    => Ok(Literal::from_float(x * (*y as f64))),
+            _ => Err(self.unsupported_operands(TokenType::Multiply, &rhs)),
         }
     }
 }
 
 impl Div for Literal {
-    type Output = Literal;
+    type Output = Result<Literal, RuntimeError>;
 
-    fn div(self, rhs: Literal) -> Literal {
-        match (&self, &rhs) {
-            (Literal::Int(x),   Literal::Int(y))    => return Literal::from_float((*x as f64) / (*y as f64)),
-            (Literal::Float(x), Literal::Float(y))  => return Literal::from_float(x / y), 
-            (Literal::Int(x),   Literal::Float(y))  => return Literal::from_float((*x as f64) / y),
-            (Literal::Float(x), Literal::Int(y))    => return Literal::from_float(x / (*y as f64)),
-            _ => panic!("not implemented for {:?} and {:?}", &self, &rhs),
+    fn div(self, rhs: Literal) -> Self::Output {
+        match (self.to_float(), rhs.to_float()) {
+            (Some(_), Some(0.0))  => Err(RuntimeError::DivisionByZero),
+            (Some(x), Some(y))    => Ok(Literal::from_float(x / y)),
+            _ => Err(self.unsupported_operands(TokenType::Division, &rhs)),
         }
     }
 }
 
 impl Rem for Literal {
-    type Output = Literal;
+    type Output = Result<Literal, RuntimeError>;
 
-    fn rem(self, rhs: Literal) -> Literal {
-        match (&self, &rhs) {
-            (Literal::Int(x),   Literal::Int(y))    => return Literal::from_int(x % y),
-            _ => panic!("not implemented for {:?} and {:?}", &self, &rhs),
+    fn rem(self, rhs: Literal) -> Self::Output {
+        match (self.to_int(), rhs.to_int()) {
+            (Some(_), Some(0))  => Err(RuntimeError::DivisionByZero),
+            (Some(x), Some(y))  => x.checked_rem(y).map(Literal::from_int).ok_or(RuntimeError::IntegerOverflow),
+            _ => Err(self.unsupported_operands(TokenType::Mod, &rhs)),
         }
     }
-}
\ No newline at end of file
+}
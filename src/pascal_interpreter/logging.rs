@@ -0,0 +1,92 @@
+//! Diagnostics of the stages, logged through the `log` facade so they never mix with program output.
+//!
+//! Every message has one of the targets below. Nothing is shown unless the host installs a logger,
+//! for instance the one of [`init`], which writes to stderr:
+//!
+//! ```no_run
+//! pascal_interpreter::logging::init("warn,sema=debug,exec=trace").unwrap();
+//! ```
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use std::str::FromStr;
+
+/// Tokens read by the lexer
+pub const LEXER: &str = "lexer";
+/// Programs and fragments parsed
+pub const PARSER: &str = "parser";
+/// Scopes and symbols of the semantic analyzer
+pub const SEMA: &str = "sema";
+/// Calls and records of the interpreter
+pub const EXEC: &str = "exec";
+
+const TARGETS: [&str; 4] = [LEXER, PARSER, SEMA, EXEC];
+
+/// Level of messages to show, in general and for single targets
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    default: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    /// Read a comma separated list of a level and `target=level` pairs, e.g. `info` or `warn,exec=trace`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut filter = Filter { default: LevelFilter::Off, targets: Vec::new() };
+        for directive in spec.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    let target = target.trim();
+                    if !TARGETS.contains(&target) {
+                        return Err(format!("unknown log target {}, expected one of {}", target, TARGETS.join(", ")));
+                    }
+                    filter.targets.push((target.to_owned(), parse_level(level)?));
+                },
+                None => filter.default = parse_level(directive)?
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets.iter().rev()
+            .find(|(name, _)| name == target)
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level.trim()).map_err(|_| format!("unknown log level {}", level.trim()))
+}
+
+struct StderrLogger {
+    filter: Filter,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install a logger writing the messages `spec` selects to stderr. Fails when a logger is installed already.
+pub fn init(spec: &str) -> Result<(), String> {
+    let filter = Filter::parse(spec)?;
+    let max_level = filter.max_level();
+    log::set_logger(Box::leak(Box::new(StderrLogger { filter })))
+        .map_err(|_| String::from("a logger is installed already"))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
@@ -3,8 +3,29 @@ pub mod parser;
 pub mod semantic_analyzer;
 pub mod error;
 pub mod literal;
-mod token;
-mod ast;
-mod symbols;
+pub mod token;
+pub mod cst;
+pub mod directive;
+pub mod ast;
+pub mod symbols;
+pub mod format;
+pub mod engine;
+pub mod record;
+pub mod native;
+pub mod limits;
+pub mod observer;
+pub mod logging;
+pub mod repl;
+pub mod debugger;
 mod lexer;
-mod record;
\ No newline at end of file
+
+pub use engine::{
+    Engine,
+    Outcome,
+    RunOptions
+};
+pub use lexer::{
+    tokenize,
+    tokenize_with_options,
+    LexerOptions
+};
@@ -0,0 +1,101 @@
+use crate::pascal_interpreter::{
+    error::RuntimeError,
+    literal::{Literal, LiteralType},
+    symbols::{Symbol, Type},
+};
+
+use std::fmt;
+use std::rc::Rc;
+
+type Body = Rc<dyn Fn(&[Literal]) -> Result<Option<Literal>, RuntimeError>>;
+
+/// Procedure or function implemented in Rust and callable from Pascal like a declared one.
+/// Arguments arrive converted to the parameter types, so a `real` parameter always gets a `Literal::Float`.
+#[derive(Clone)]
+pub struct NativeFunction {
+    name: String,
+    params: Vec<Type>,
+    return_type: Option<Type>,
+    body: Body,
+}
+
+impl NativeFunction {
+    pub fn function<F>(name: &str, params: &[Type], return_type: Type, body: F) -> Self
+        where F: Fn(&[Literal]) -> Result<Literal, RuntimeError> + 'static
+    {
+        NativeFunction {
+            name: name.to_owned(),
+            params: params.to_vec(),
+            return_type: Some(return_type),
+            body: Rc::new(move |args| body(args).map(Some)),
+        }
+    }
+
+    pub fn procedure<F>(name: &str, params: &[Type], body: F) -> Self
+        where F: Fn(&[Literal]) -> Result<(), RuntimeError> + 'static
+    {
+        NativeFunction {
+            name: name.to_owned(),
+            params: params.to_vec(),
+            return_type: None,
+            body: Rc::new(move |args| body(args).map(|_| None)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn params(&self) -> &[Type] {
+        &self.params
+    }
+
+    pub fn return_type(&self) -> Option<&Type> {
+        self.return_type.as_ref()
+    }
+
+    /// Symbol the semantic analyzer defines for the native in the global scope
+    pub fn symbol(&self) -> Symbol {
+        Symbol::Native(self.params.clone(), self.return_type.clone())
+    }
+
+    /// Run the body, after converting integer arguments for `real` parameters, and the result of a `real` function.
+    /// A result that does not fit the return type, or a missing or unexpected one, is an error.
+    pub fn call(&self, args: Vec<Literal>) -> Result<Option<Literal>, RuntimeError> {
+        let args: Vec<Literal> = args.into_iter().zip(&self.params).map(|(arg, param)| coerce(arg, param)).collect();
+        let result = (self.body)(&args)?;
+        match (result, &self.return_type) {
+            (Some(value), Some(return_type)) => {
+                let value = coerce(value, return_type);
+                if fits(&value, return_type) {
+                    Ok(Some(value))
+                } else {
+                    Err(self.return_type_mismatch(Some(value.literal_type())))
+                }
+            },
+            (None, None) => Ok(None),
+            (result, _) => Err(self.return_type_mismatch(result.map(|value| value.literal_type())))
+        }
+    }
+
+    fn return_type_mismatch(&self, found: Option<LiteralType>) -> RuntimeError {
+        RuntimeError::NativeReturnTypeMismatch(self.name.clone(), self.return_type.clone(), found)
+    }
+}
+
+fn fits(value: &Literal, to: &Type) -> bool {
+    matches!((value, to), (Literal::Int(_), Type::Integer) | (Literal::Float(_), Type::Float) | (Literal::Text(_), Type::String))
+}
+
+fn coerce(value: Literal, to: &Type) -> Literal {
+    match (&value, to) {
+        (Literal::Int(int), Type::Float) => Literal::from_float(*int as f64),
+        _ => value
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction(name = {}, params = {:?}, return_type = {:?})", self.name, self.params, self.return_type)
+    }
+}
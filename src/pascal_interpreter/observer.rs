@@ -0,0 +1,123 @@
+//! Hooks into a run of the [`Interpreter`](crate::interpreter::Interpreter), for tracers, profilers
+//! and debuggers.
+//!
+//! Observers are added with `Interpreter::add_observer` or `Engine::add_observer` and told about
+//! each event in the order it happens. [`Tracer`] prints them like the step mode of Turbo Pascal:
+//!
+//! ```text
+//! TRACE 3:3     x := Double(4)
+//! TRACE 3:8       -> Double(n = 4)
+//! TRACE 7:3       y := n * 2
+//! TRACE             y = 8
+//! TRACE           <- Double
+//! ```
+
+use crate::pascal_interpreter::{
+    ast::nodes::*,
+    error::RuntimeError,
+    literal::Literal,
+    record::{ARCallStack, ActivationRecord},
+    token::{Position, Span},
+};
+
+use std::io::Write;
+
+/// Callbacks of a run, all doing nothing unless overridden
+pub trait Observer {
+    /// Before an assignment or a procedure call statement runs. Compound and empty statements are
+    /// not reported themselves, only the statements in them. The observer may change variables of
+    /// any record, as a debugger does, and stop the run by returning an error.
+    fn statement_entered(&mut self, _stmt: &Stmt, _span: Span, _callstack: &mut ARCallStack) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// After an expression node got its value, so operands are reported before their operation.
+    /// The value is None for a variable that was never assigned.
+    fn expression_evaluated(&mut self, _expr: &Expr, _span: Span, _value: Option<&Literal>) {}
+
+    /// After a variable of the innermost record was assigned; `span` is the assignment statement
+    fn variable_assigned(&mut self, _name: &str, _span: Span, _value: &Literal) {}
+
+    /// After the record of a procedure or native call was pushed, with the arguments bound
+    fn procedure_entered(&mut self, _call: &ProcedureCallNode, _record: &ActivationRecord) {}
+
+    /// Before the record of a call is popped, with the value a native function returned.
+    /// Calls that fail are not reported as exited.
+    fn procedure_exited(&mut self, _call: &ProcedureCallNode, _record: &ActivationRecord, _result: Option<&Literal>) {}
+}
+
+/// Prints a `TRACE` line for each statement, assignment and call of a run, indented by call depth
+pub struct Tracer<W: Write> {
+    source: String,
+    out: W,
+    expressions: bool,
+    depth: usize,
+}
+
+impl<W: Write> Tracer<W> {
+    /// Trace the run of `source`, the text the program was parsed from, to `out`
+    pub fn new(source: &str, out: W) -> Self {
+        Tracer { source: source.to_owned(), out, expressions: false, depth: 0 }
+    }
+
+    /// Also print the value of every expression node
+    pub fn with_expressions(mut self, expressions: bool) -> Self {
+        self.expressions = expressions;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    // source text of a node on one line
+    fn text(&self, span: Span) -> String {
+        self.source.get(span.start..span.end)
+            .map(|text| text.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .unwrap_or_default()
+    }
+
+    // a trace must not fail the run it follows, so write errors are dropped
+    fn line(&mut self, position: Option<Position>, extra_indent: usize, text: &str) {
+        let position = position.map(|position| position.to_string()).unwrap_or_default();
+        let indent = "  ".repeat(self.depth + extra_indent);
+        let _ = writeln!(self.out, "TRACE {:<7} {}{}", position, indent, text);
+    }
+}
+
+impl<W: Write> Observer for Tracer<W> {
+    fn statement_entered(&mut self, stmt: &Stmt, span: Span, _: &mut ARCallStack) -> Result<(), RuntimeError> {
+        let text = self.text(span);
+        self.line(stmt.position(), 0, &text);
+        Ok(())
+    }
+
+    fn expression_evaluated(&mut self, expr: &Expr, span: Span, value: Option<&Literal>) {
+        if self.expressions {
+            let value = value.map_or_else(|| String::from("(unset)"), Literal::to_string);
+            let text = format!("{} = {}", self.text(span), value);
+            self.line(Some(expr.position()), 1, &text);
+        }
+    }
+
+    fn variable_assigned(&mut self, name: &str, _: Span, value: &Literal) {
+        self.line(None, 1, &format!("{} = {}", name, value));
+    }
+
+    fn procedure_entered(&mut self, call: &ProcedureCallNode, record: &ActivationRecord) {
+        let arguments: Vec<String> = record.arguments().iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect();
+        self.depth += 1;
+        self.line(Some(call.call_site()), 0, &format!("-> {}({})", record.name(), arguments.join(", ")));
+    }
+
+    fn procedure_exited(&mut self, _: &ProcedureCallNode, record: &ActivationRecord, result: Option<&Literal>) {
+        let text = match result {
+            Some(result) => format!("<- {} = {}", record.name(), result),
+            None => format!("<- {}", record.name())
+        };
+        self.line(None, 0, &text);
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
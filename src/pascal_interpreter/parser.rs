@@ -1,22 +1,36 @@
 use crate::pascal_interpreter::{
     ast::nodes::*,
-    lexer::Lexer,
+    cst::{
+        SyntaxKind,
+        SyntaxNode,
+        SyntaxTreeBuilder
+    },
+    lexer::{
+        Lexer,
+        LexerOptions
+    },
     token::*,
     error::RuntimeError,
+    literal::Literal,
+    logging,
 };
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     current_token: Token,
+    // the token after `current_token`, read ahead only where the grammar needs it
+    next_token: Option<Token>,
+    syntax: SyntaxTreeBuilder,
 }
 
 impl<'a> Parser<'a> {
-    pub fn from(text: &'a str) -> Self {
-        let mut lexer = Lexer::from(text);
-        let token = lexer.get_next_token().unwrap();
+    pub fn from(text: &'a str, options: LexerOptions) -> Self {
+        // the first token is read by `parse`, so lexical errors there are reported instead of panicking
         Parser {
-            lexer: lexer,
-            current_token: token
+            lexer: Lexer::from(text, options),
+            current_token: Token::new(TokenType::Eof, Literal::from_str(String::new()), 1, 1),
+            next_token: None,
+            syntax: SyntaxTreeBuilder::new()
         }
     }
 
@@ -26,76 +40,132 @@ impl<'a> Parser<'a> {
     // otherwise raise an exception.
     fn eat(&mut self, token_type: TokenType) -> Result<(), RuntimeError> {
         if self.current_token.token_type() == token_type {
-            self.current_token = self.lexer.get_next_token()?;
+            let next_token = self.next_token()?;
+            self.syntax.token(std::mem::replace(&mut self.current_token, next_token));
             return Ok(())
         }
-        Err(RuntimeError::UnexpectedToken(self.current_token.clone(), token_type))
+        if token_type == TokenType::Identifier && self.current_token.token_type().is_reserved_word() {
+            return Err(RuntimeError::ReservedWordAsIdentifier(Box::new(self.current_token.clone())));
+        }
+        Err(RuntimeError::UnexpectedToken(Box::new(self.current_token.clone()), token_type))
+    }
+
+    // the token after the current one, from the lookahead if it was peeked at
+    fn next_token(&mut self) -> Result<Token, RuntimeError> {
+        match self.next_token.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.get_next_token()
+        }
+    }
+
+    // type of the token after the current one, without consuming either
+    fn peek_token_type(&mut self) -> Result<TokenType, RuntimeError> {
+        let next = self.next_token()?;
+        let token_type = next.token_type();
+        self.next_token = Some(next);
+        Ok(token_type)
+    }
+
+    // read the first token of a new parse
+    fn start(&mut self) -> Result<(), RuntimeError> {
+        self.next_token = None;
+        self.current_token = self.lexer.get_next_token()?;
+        self.syntax = SyntaxTreeBuilder::new();
+        Ok(())
+    }
+
+    // a reserved word followed by something only an identifier can be followed by,
+    // e.g. `type := 1` or `var end : integer`
+    fn reserved_word_used_as_identifier(&mut self) -> Result<bool, RuntimeError> {
+        if !self.current_token.token_type().is_reserved_word() {
+            return Ok(false);
+        }
+        Ok(matches!(self.peek_token_type()?, TokenType::Colon | TokenType::Comma | TokenType::Lparen | TokenType::Assignment))
     }
 
-    fn factor(&mut self) -> Result<Box<dyn VisitableNode>, RuntimeError> {
+    fn factor(&mut self) -> Result<Expr, RuntimeError> {
         match &self.current_token.token_type() {
             TokenType::IntegerConst | TokenType::FloatConst => {
                 let result = NumNode::from(self.current_token.to_owned());
+                self.syntax.start_node(SyntaxKind::Number);
                 self.eat(self.current_token.token_type())?;
-                return Ok(Box::from(result));
+                self.syntax.finish_node();
+                Ok(Expr::Num(result))
             },
             TokenType::Lparen => {
+                self.syntax.start_node(SyntaxKind::ParenExpr);
                 self.eat(TokenType::Lparen)?;
                 let result = self.expr()?;
                 self.eat(TokenType::Rparen)?;
-                return Ok(result);
+                self.syntax.finish_node();
+                Ok(result)
             },
             TokenType::Plus | TokenType::Minus => {
                 let token = self.current_token.to_owned();
+                let checks = token.switches();
+                self.syntax.start_node(SyntaxKind::UnaryExpr);
                 self.eat(self.current_token.token_type())?;
                 let arg = self.factor()?;
-                return Ok(Box::from(UnaryOpNode::from(token, arg)));
+                self.syntax.finish_node();
+                Ok(Expr::UnaryOp(UnaryOpNode::from(token, arg, checks)))
             }
+            TokenType::Identifier if self.peek_token_type()? == TokenType::Lparen => {
+                Ok(Expr::Call(self.proccall_statement()?))
+            },
             TokenType::Identifier => {
                 let variable = self.variable()?;
-                return Ok(Box::from(variable))
+                Ok(Expr::Var(variable))
             }
-            _ => Err(RuntimeError::UnexpectedToken(self.current_token.clone(), TokenType::EOF))
+            _ => Err(RuntimeError::ExpectedExpression(Box::new(self.current_token.clone())))
         }
         
     }
 
-    fn term(&mut self) -> Result<Box<dyn VisitableNode>, RuntimeError> {
-        let ops = vec![TokenType::Multiply, TokenType::Division, TokenType::IntegerDivision];
+    fn term(&mut self) -> Result<Expr, RuntimeError> {
+        let ops = [TokenType::Multiply, TokenType::Division, TokenType::IntegerDivision, TokenType::Modulus, TokenType::Mod];
 
+        let checkpoint = self.syntax.checkpoint();
         let mut node = self.factor()?;
         while ops.contains(&self.current_token.token_type()) {
             let token = self.current_token.to_owned();
+            let checks = token.switches();
+            self.syntax.start_node_at(checkpoint, SyntaxKind::BinaryExpr);
             self.eat(token.token_type())?;
             let rhs = self.factor()?;
-            let new_node = Box::from(BinaryOpNode::from(node, token, rhs));
-            node = new_node
+            self.syntax.finish_node();
+            node = Expr::BinaryOp(BinaryOpNode::from(node, token, rhs, checks));
         }
         Ok(node)
     }
 
-    fn expr(&mut self) -> Result<Box<dyn VisitableNode>, RuntimeError> {
-        let ops = vec![TokenType::Plus, TokenType::Minus];
+    fn expr(&mut self) -> Result<Expr, RuntimeError> {
+        let ops = [TokenType::Plus, TokenType::Minus];
 
+        let checkpoint = self.syntax.checkpoint();
         let mut node = self.term()?;
         while ops.contains(&self.current_token.token_type()) {
             let token = self.current_token.to_owned();
+            let checks = token.switches();
+            self.syntax.start_node_at(checkpoint, SyntaxKind::BinaryExpr);
             self.eat(token.token_type())?;
             let rhs = self.term()?;
-            node = Box::from(BinaryOpNode::from(node, token, rhs));
+            self.syntax.finish_node();
+            node = Expr::BinaryOp(BinaryOpNode::from(node, token, rhs, checks));
         }
         Ok(node)   
     }
 
     fn compound_statement(&mut self, begin: TokenType, end: TokenType) -> Result<CompoundStatementNode, RuntimeError> {
+        self.syntax.start_node(SyntaxKind::CompoundStatement);
         self.eat(begin)?;
         let statements = self.statement_list()?;
         self.eat(end)?;
+        self.syntax.finish_node();
        
         Ok(CompoundStatementNode::from(statements))
     }
 
-    fn statement_list(&mut self) -> Result<Vec<Box<dyn VisitableNode>>, RuntimeError> {
+    fn statement_list(&mut self) -> Result<Vec<Stmt>, RuntimeError> {
         let node = self.statement()?;
         let mut statements = Vec::new();
         statements.push(node);
@@ -107,20 +177,20 @@ impl<'a> Parser<'a> {
         Ok(statements)
     }
 
-    fn statement(&mut self) -> Result<Box<dyn VisitableNode>, RuntimeError> {
-        let result : Box<dyn VisitableNode> = match self.current_token.token_type() {
+    fn statement(&mut self) -> Result<Stmt, RuntimeError> {
+        let result = match self.current_token.token_type() {
             TokenType::Begin => {
                 let compound = self.compound_statement(TokenType::Begin, TokenType::End)?;
-                Box::from(compound)
+                Stmt::Compound(compound)
             },
-            TokenType::Identifier => {
-                let current_char = self.lexer.get_current_char();
-                match current_char {
-                    Some('(') => Box::from(self.proccall_statement()?),
-                    _ => Box::from(self.assignment_statement()?)
-                }
+            TokenType::Identifier if self.peek_token_type()? == TokenType::Lparen => {
+                Stmt::ProcedureCall(self.proccall_statement()?)
+            },
+            TokenType::Identifier => Stmt::Assignment(self.assignment_statement()?),
+            _ if self.reserved_word_used_as_identifier()? => {
+                return Err(RuntimeError::ReservedWordAsIdentifier(Box::new(self.current_token.clone())))
             },
-            _ => Box::from(self.empty())
+            _ => Stmt::NoOp(self.empty())
         };
         Ok(result)
        
@@ -128,24 +198,30 @@ impl<'a> Parser<'a> {
 
     fn variable(&mut self) -> Result<VarNode, RuntimeError> {
         let name = self.current_token.to_owned();
+        self.syntax.start_node(SyntaxKind::Variable);
         self.eat(TokenType::Identifier)?;
+        self.syntax.finish_node();
         Ok(VarNode::from(name))
     }
 
     fn assignment_statement(&mut self) -> Result<AssignmentNode, RuntimeError> {
+        self.syntax.start_node(SyntaxKind::Assignment);
         let left = self.variable()?;
-        self.eat(TokenType::Colon)?;
         self.eat(TokenType::Assignment)?;
         
         let right = self.expr()?;
+        self.syntax.finish_node();
         Ok(AssignmentNode::from(left, right))
     }
 
-    fn empty(&self) -> NoOpNode {
+    fn empty(&mut self) -> NoOpNode {
+        self.syntax.start_node(SyntaxKind::Empty);
+        self.syntax.finish_node();
         NoOpNode{}
     }
 
     fn program(&mut self) -> Result<ProgramNode, RuntimeError> {
+        self.syntax.start_node(SyntaxKind::Program);
         self.eat(TokenType::Program)?;
         
         let prog_name = self.current_token.literal().clone();
@@ -153,42 +229,73 @@ impl<'a> Parser<'a> {
         self.eat(TokenType::Semi)?;
         
         let block_node = self.block()?;
-        self.eat(TokenType::Dot)?;
+        if self.current_token.token_type() != TokenType::Dot {
+            return Err(RuntimeError::UnexpectedToken(Box::new(self.current_token.clone()), TokenType::Dot))
+        }
+        // the lexer is not asked for the token after the final `.`, what follows need not be Pascal
+        let dot = self.current_token.clone();
+        self.syntax.token(dot.clone());
+        self.end_of_source(&dot);
+        self.syntax.finish_node();
 
         Ok(ProgramNode::from(prog_name, block_node))
     }
+
+    // end the syntax tree with an `Eof` token; anything after the final `end.` is ignored
+    // as in Turbo Pascal, and kept as inactive code so the tree still covers the whole source
+    fn end_of_source(&mut self, dot: &Token) {
+        let source = self.lexer.source();
+        let span = Span { start: dot.span().end, end: source.len() };
+        let rest = &source[span.start..];
+        let (line, col) = match rest.rfind('\n') {
+            Some(last_break) => (dot.line() + rest.matches('\n').count() as u32, rest[last_break + 1..].chars().count() as u32 + 1),
+            None => (dot.line(), dot.col() + 1 + rest.chars().count() as u32)
+        };
+        let leading_trivia = match rest {
+            "" => Vec::new(),
+            rest => vec![Trivia::new(TriviaKind::InactiveCode, rest.to_owned(), span)]
+        };
+        let eof = Token::new(TokenType::Eof, Literal::from_str(String::new()), line, col)
+            .with_source(Span { start: source.len(), end: source.len() }, String::new(), leading_trivia);
+        self.syntax.token(eof);
+    }
     
     fn block(&mut self) -> Result<BlockNode, RuntimeError> {
+        self.syntax.start_node(SyntaxKind::Block);
         let declarations = self.declarations()?;
         let compound = self.compound_statement(TokenType::Begin, TokenType::End)?;
+        self.syntax.finish_node();
 
         Ok(BlockNode::from(declarations, compound))
     }
 
-    fn declarations(&mut self) -> Result<Vec<Box<dyn VisitableNode>>, RuntimeError> {
-        let mut declarations : Vec<Box<dyn VisitableNode>> = Vec::new();
+    fn declarations(&mut self) -> Result<Vec<Decl>, RuntimeError> {
+        let mut declarations = Vec::new();
         
         if self.current_token.token_type() == TokenType::Var {
+            self.syntax.start_node(SyntaxKind::VarSection);
             self.eat(TokenType::Var)?;
             
-            while self.current_token.token_type() == TokenType::Identifier {
+            while self.current_token.token_type() == TokenType::Identifier || self.reserved_word_used_as_identifier()? {
                 let var_declarations = self.variable_declarations()?;
                 declarations.extend(var_declarations);
                 self.eat(TokenType::Semi)?;
             }
+            self.syntax.finish_node();
         }
                 
        while self.current_token.token_type() == TokenType::Procedure {
             let proc_decl = self.procedure_declaration()?;
-            declarations.push(Box::from(proc_decl));
+            declarations.push(Decl::Procedure(proc_decl));
        }
 
         Ok(declarations)
     }
 
-    fn variable_declarations(&mut self) -> Result<Vec<Box<dyn VisitableNode>>, RuntimeError> {
+    fn variable_declarations(&mut self) -> Result<Vec<Decl>, RuntimeError> {
         let mut variables : Vec<VarNode> = Vec::new();
         variables.push(VarNode::from(self.current_token.to_owned()));
+        self.syntax.start_node(SyntaxKind::VarDecl);
         
         self.eat(TokenType::Identifier)?; 
         
@@ -200,19 +307,20 @@ impl<'a> Parser<'a> {
         self.eat(TokenType::Colon)?;
 
         let type_spec = self.type_spec()?; 
-        let mut variable_declarations : Vec<Box<dyn VisitableNode>> = Vec::new();
-        for var in variables.into_iter() {
-            let decl : Box<dyn VisitableNode> = Box::from(VarDeclNode::from(var, type_spec.clone()));
-            variable_declarations.push(decl);
-        }
+        self.syntax.finish_node();
+        let variable_declarations = variables.into_iter()
+            .map(|var| Decl::Var(VarDeclNode::from(var, type_spec.clone())))
+            .collect();
         Ok(variable_declarations)
     }
 
     fn type_spec(&mut self) -> Result<TypeNode, RuntimeError> {
         match self.current_token.token_type() {
-            TokenType::IntegerType | TokenType::FloatType => { 
+            TokenType::IntegerType | TokenType::FloatType | TokenType::StringType => { 
                     let token = self.current_token.to_owned();
+                    self.syntax.start_node(SyntaxKind::TypeSpec);
                     self.eat(token.token_type())?;
+                    self.syntax.finish_node();
                     Ok(TypeNode::from(token))
             },
             _ => Err(RuntimeError::UnknownType(self.current_token.literal().to_str().unwrap_or("").to_string()))
@@ -222,6 +330,7 @@ impl<'a> Parser<'a> {
     fn formal_parameters(&mut self) -> Result<Vec<ParamNode>, RuntimeError> {
         let mut param_tokens = Vec::new();
         param_tokens.push(self.current_token.clone());
+        self.syntax.start_node(SyntaxKind::Param);
         
         self.eat(TokenType::Identifier)?;
 
@@ -233,6 +342,7 @@ impl<'a> Parser<'a> {
 
         self.eat(TokenType::Colon)?;
         let type_node = self.type_spec()?;
+        self.syntax.finish_node();
 
         let mut param_nodes = Vec::new();
         for i in param_tokens {
@@ -256,6 +366,7 @@ impl<'a> Parser<'a> {
     }
 
     fn procedure_declaration(&mut self) -> Result<ProcedureDeclNode, RuntimeError> {
+        self.syntax.start_node(SyntaxKind::ProcedureDecl);
         self.eat(TokenType::Procedure)?;
         let proc_name = self.current_token.literal().clone();
         self.eat(TokenType::Identifier)?;
@@ -270,16 +381,18 @@ impl<'a> Parser<'a> {
         let block_node = self.block()?;
         let proc_decl = ProcedureDeclNode::from(proc_name, params, block_node);
         self.eat(TokenType::Semi)?;
-        return Ok(proc_decl);
+        self.syntax.finish_node();
+        Ok(proc_decl)
     }
 
     fn proccall_statement(&mut self) -> Result<ProcedureCallNode, RuntimeError> {
-         //"""proccall_statement : ID LPAREN (expr (COMMA expr)*)? RPAREN"""
-        let proc_name = self.current_token.literal().clone();
+         //"""proccall_statement : ID LPAREN (expr (COMMA expr)*)? RPAREN""", also a function call in a factor
+        let proc_name = self.current_token.clone();
+        self.syntax.start_node(SyntaxKind::ProcedureCall);
         self.eat(TokenType::Identifier)?;
         self.eat(TokenType::Lparen)?;
        
-        let mut actual_params : Vec<Box<dyn VisitableNode>>= Vec::new();
+        let mut actual_params = Vec::new();
         if self.current_token.token_type() != TokenType::Rparen {
             let node = self.expr()?;
             actual_params.push(node);
@@ -290,12 +403,58 @@ impl<'a> Parser<'a> {
             let node = self.expr()?;
             actual_params.push(node);
         }  
+        let span = Span { start: proc_name.span().start, end: self.current_token.span().end };
         self.eat(TokenType::Rparen)?;
+        self.syntax.finish_node();
 
-        Ok(ProcedureCallNode::from(proc_name, actual_params))
+        Ok(ProcedureCallNode::from(proc_name, actual_params).with_span(span))
     }
        
+    /// Parse declarations, statements or a single expression entered without a surrounding program
+    pub fn parse_fragment(&mut self) -> Result<Fragment, RuntimeError> {
+        self.start()?;
+        let fragment = match self.current_token.token_type() {
+            TokenType::Var | TokenType::Procedure => Fragment::Declarations(self.declarations()?),
+            TokenType::Begin => Fragment::Statements(self.statement_list()?),
+            TokenType::Identifier if self.peek_token_type()? == TokenType::Assignment => {
+                Fragment::Statements(self.statement_list()?)
+            },
+            _ => self.expression_fragment()?
+        };
+        if self.current_token.token_type() != TokenType::Eof {
+            return Err(RuntimeError::UnexpectedToken(Box::new(self.current_token.clone()), TokenType::Eof))
+        }
+        log::trace!(target: logging::PARSER, "parsed fragment {:?}", fragment);
+        Ok(fragment)
+    }
+
+    // an expression, optionally ended by `;`, or a call starting a list of statements
+    fn expression_fragment(&mut self) -> Result<Fragment, RuntimeError> {
+        let expr = self.expr()?;
+        if self.current_token.token_type() != TokenType::Semi {
+            return Ok(Fragment::Expression(expr))
+        }
+        self.eat(TokenType::Semi)?;
+        match expr {
+            Expr::Call(call) if self.current_token.token_type() != TokenType::Eof => {
+                let mut statements = vec![Stmt::ProcedureCall(call)];
+                statements.extend(self.statement_list()?);
+                Ok(Fragment::Statements(statements))
+            },
+            expr => Ok(Fragment::Expression(expr))
+        }
+    }
+
     pub fn parse(&mut self) -> Result<ProgramNode, RuntimeError> {
-		self.program()
-	}
+        self.parse_with_syntax().map(|(program, _)| program)
+    }
+
+    /// Parse the program into its AST together with the lossless syntax tree it was read from
+    pub fn parse_with_syntax(&mut self) -> Result<(ProgramNode, SyntaxNode), RuntimeError> {
+        self.start()?;
+        let program = self.program()?;
+        log::debug!(target: logging::PARSER, "parsed program {}", program.name.to_str().unwrap_or_default());
+        let syntax = std::mem::replace(&mut self.syntax, SyntaxTreeBuilder::new()).finish();
+        Ok((program, syntax))
+    }
 }
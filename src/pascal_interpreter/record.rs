@@ -1,8 +1,10 @@
 use super::literal::Literal;
+use super::symbols::Type;
+use super::token::Position;
 use std::collections::HashMap;
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ARType{
     Program,
     Procedure,
@@ -13,19 +15,74 @@ pub struct ActivationRecord {
     name : Literal,
     record_type: ARType,
     nesting_level : u32,
-    members : HashMap<String, Literal>
+    members : HashMap<String, Literal>,
+    // declared types of the variables and parameters, set or not
+    declared: HashMap<String, Type>,
+    call_site: Option<Position>,
+    arguments: Vec<(String, Literal)>
 }
 
 impl ActivationRecord {
     pub fn from(name: Literal, record_type : ARType, nesting_level: u32) -> Self {
         ActivationRecord{
-            name: name,
-            record_type: record_type,
-            nesting_level: nesting_level,
-            members: HashMap::new()
+            name,
+            record_type,
+            nesting_level,
+            members: HashMap::new(),
+            declared: HashMap::new(),
+            call_site: None,
+            arguments: Vec::new()
         }
     }
 
+    pub fn name(&self) -> &str {
+        self.name.to_str().unwrap_or("")
+    }
+
+    pub fn record_type(&self) -> &ARType {
+        &self.record_type
+    }
+
+    pub fn nesting_level(&self) -> u32 {
+        self.nesting_level
+    }
+
+    /// Variables of the record by lowercase name, in no particular order
+    pub fn members(&self) -> impl Iterator<Item = (&str, &Literal)> {
+        self.members.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Bytes held by the values of the record's variables and by the copies of its arguments
+    pub fn value_size(&self) -> usize {
+        let arguments = self.arguments.iter().map(|(_, value)| value);
+        self.members.values().chain(arguments).map(Literal::heap_size).sum()
+    }
+
+    pub fn set_call_site(&mut self, call_site: Position) {
+        self.call_site = Some(call_site);
+    }
+
+    /// Bind an actual parameter to its formal name, remembering the passed value for backtraces
+    pub fn set_argument(&mut self, param_name: &str, value: Literal) {
+        self.arguments.push((param_name.to_owned(), value.clone()));
+        self.set_item(param_name, value);
+    }
+
+    /// Actual parameters the record was called with, in order
+    pub fn arguments(&self) -> &[(String, Literal)] {
+        &self.arguments
+    }
+
+    /// Note the type of a variable or parameter of the record, before it holds a value
+    pub fn declare(&mut self, item_name: &str, item_type: Type) {
+        self.declared.insert(item_name.to_lowercase(), item_type);
+    }
+
+    /// Type a variable or parameter of the record was declared with, None for names it does not declare
+    pub fn declared_type(&self, item_name: &str) -> Option<&Type> {
+        self.declared.get(&item_name.to_lowercase())
+    }
+
     pub fn get_item(&self, item_name: &str) -> Option<&Literal> {
         self.members.get(&item_name.to_lowercase())
     }
@@ -45,11 +102,27 @@ pub struct ARCallStack {
     pub records: Vec<ActivationRecord>
 }
 
+impl Default for ARCallStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ARCallStack {
     pub fn new() -> Self {
         ARCallStack{ records: Vec::new()}
     }
 
+    /// Snapshot the records currently on the stack, innermost first
+    pub fn backtrace(&self) -> Backtrace {
+        let frames = self.records.iter().rev().map(|record| StackFrame {
+            name: record.name.to_str().unwrap_or("").to_owned(),
+            call_site: record.call_site,
+            arguments: record.arguments.clone(),
+        }).collect();
+        Backtrace { frames }
+    }
+
     pub fn push(&mut self, record: ActivationRecord) {
         self.records.push(record);
     }
@@ -65,6 +138,51 @@ impl ARCallStack {
     pub fn peek_mut(&mut self) -> Option<&mut ActivationRecord> {
         self.records.last_mut()
     }
+
+    /// Record `depth` frames below the top of the stack, counted the way a backtrace lists them
+    pub fn frame(&self, depth: usize) -> Option<&ActivationRecord> {
+        self.records.iter().rev().nth(depth)
+    }
+
+    pub fn frame_mut(&mut self, depth: usize) -> Option<&mut ActivationRecord> {
+        self.records.iter_mut().rev().nth(depth)
+    }
+}
+
+/// A single Pascal-level frame of a backtrace
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    pub name: String,
+    pub call_site: Option<Position>,
+    pub arguments: Vec<(String, Literal)>,
+}
+
+impl fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(call_site) = self.call_site {
+            let arguments: Vec<String> = self.arguments.iter()
+                .map(|(name, value)| format!("{} = {}", name, value))
+                .collect();
+            write!(f, "({}) called at {}", arguments.join(", "), call_site)?;
+        }
+        Ok(())
+    }
+}
+
+/// Call stack captured at the point where a runtime error was raised
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backtrace {
+    pub frames: Vec<StackFrame>,
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for frame in &self.frames {
+            writeln!(f, "  at {}", frame)?;
+        }
+        Ok(())
+    }
 }
 
 pub trait CallStack {
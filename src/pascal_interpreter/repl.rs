@@ -0,0 +1,208 @@
+//! Read-eval-print loop over a session that keeps its globals between inputs.
+//!
+//! Each input is a fragment: declarations, statements, or an expression whose value is shown. Lines
+//! are collected until the `begin`s and `end`s of the input balance, so blocks and procedures can be
+//! typed over several lines. Inputs starting with `:` are meta-commands, see [`HELP`].
+
+use crate::pascal_interpreter::{
+    ast::{
+        dot::token_text,
+        nodes::*,
+        outline::expr_outline,
+    },
+    error::RuntimeError,
+    interpreter::Interpreter,
+    lexer::{tokenize_with_options, LexerOptions},
+    native::NativeFunction,
+    parser::Parser,
+    semantic_analyzer::SemanticAnalyzer,
+    token::TokenType,
+};
+
+use std::fmt::Write;
+
+pub const HELP: &str = "\
+:vars          list the global variables
+:type <expr>   show the type of an expression
+:ast <expr>    show the syntax tree of an expression
+:history       list the previous inputs
+:reset         forget all declarations and values
+:help          show this help
+:quit          leave";
+
+/// What an input line led to
+#[derive(Debug)]
+pub enum Reply {
+    /// The input goes on in the next line
+    Continue,
+    /// Text to show, empty for statements and declarations
+    Output(String),
+    Failed(RuntimeError),
+    Quit,
+}
+
+pub struct Repl {
+    options: LexerOptions,
+    natives: Vec<NativeFunction>,
+    analyzer: SemanticAnalyzer,
+    interpreter: Interpreter,
+    // lines of an input that is not complete yet
+    pending: String,
+    history: Vec<String>,
+}
+
+impl Repl {
+    pub fn new(options: LexerOptions) -> Self {
+        let mut repl = Repl {
+            options,
+            natives: Vec::new(),
+            analyzer: SemanticAnalyzer::new(),
+            interpreter: Interpreter::new(),
+            pending: String::new(),
+            history: Vec::new(),
+        };
+        repl.reset();
+        repl
+    }
+
+    /// Make a native function callable from the inputs, also after a `:reset`. Starts the session over.
+    pub fn define_native(&mut self, native: NativeFunction) {
+        self.natives.push(native);
+        self.reset();
+    }
+
+    /// True while the lines read so far are an incomplete input
+    pub fn is_continuing(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Read one line, running the input once it is complete
+    pub fn feed(&mut self, line: &str) -> Reply {
+        if self.pending.is_empty() && line.trim_start().starts_with(':') {
+            let command = line.trim().to_owned();
+            let reply = self.command(&command);
+            self.history.push(command);
+            return reply;
+        }
+
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        if self.pending.trim().is_empty() {
+            self.pending.clear();
+            return Reply::Output(String::new());
+        }
+        if !is_complete(&self.pending, &self.options) {
+            return Reply::Continue;
+        }
+
+        let input = std::mem::take(&mut self.pending);
+        let input = input.trim_end();
+        self.history.push(input.to_owned());
+        match self.eval(input) {
+            Ok(output) => Reply::Output(output),
+            Err(err) => Reply::Failed(err)
+        }
+    }
+
+    fn reset(&mut self) {
+        self.analyzer = SemanticAnalyzer::new();
+        self.interpreter = Interpreter::new();
+        for native in &self.natives {
+            self.analyzer.define_native(native);
+            self.interpreter.define_native(native.clone());
+        }
+        self.analyzer.start_session();
+        self.interpreter.start_session("repl").expect("an empty session fits in any limits");
+    }
+
+    fn eval(&mut self, input: &str) -> Result<String, RuntimeError> {
+        let mut fragment = self.parse(input)?;
+        self.analyzer.analyze_fragment(&mut fragment)?;
+        let value = self.interpreter.run_fragment(&fragment)?;
+        Ok(match (&fragment, value) {
+            (Fragment::Expression(_), Some(value)) => value.to_string(),
+            (Fragment::Expression(_), None) => String::from("(unset)"),
+            _ => String::new()
+        })
+    }
+
+    // the last statement or declaration may be left without its `;`
+    fn parse(&self, input: &str) -> Result<Fragment, RuntimeError> {
+        let source = if input.ends_with(';') { input.to_owned() } else { format!("{};", input) };
+        Parser::from(&source, self.options.clone()).parse_fragment()
+    }
+
+    fn parse_expr(&mut self, input: &str) -> Result<Expr, RuntimeError> {
+        // anything but an expression is refused before the analyzer could declare what it holds
+        let mut fragment = match self.parse(input)? {
+            Fragment::Expression(expr) => Fragment::Expression(expr),
+            _ => return Err(RuntimeError::IllformedVarExpr)
+        };
+        self.analyzer.analyze_fragment(&mut fragment)?;
+        match fragment {
+            Fragment::Expression(expr) => Ok(expr),
+            Fragment::Statements(mut statements) if statements.len() == 1 => match statements.remove(0) {
+                Stmt::ProcedureCall(call) => Err(RuntimeError::NotAFunction(token_text(&call.name))),
+                _ => Err(RuntimeError::IllformedVarExpr)
+            },
+            _ => Err(RuntimeError::IllformedVarExpr)
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Reply {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(split) => (&command[..split], command[split..].trim()),
+            None => (command, "")
+        };
+        match name {
+            ":vars" => Reply::Output(self.vars()),
+            ":type" => match self.parse_expr(argument) {
+                Ok(expr) => Reply::Output(self.analyzer.expr_type(&expr).map_or_else(|| String::from("unknown"), |expr_type| expr_type.to_string())),
+                Err(err) => Reply::Failed(err)
+            },
+            ":ast" => match self.parse_expr(argument) {
+                Ok(expr) => Reply::Output(expr_outline(&expr).trim_end().to_owned()),
+                Err(err) => Reply::Failed(err)
+            },
+            ":history" => Reply::Output(self.history.iter().enumerate()
+                .map(|(i, input)| format!("{:>4}  {}", i + 1, input.replace('\n', "\n      ")))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            ":reset" => {
+                self.reset();
+                Reply::Output(String::new())
+            },
+            ":help" => Reply::Output(HELP.to_owned()),
+            ":quit" | ":q" => Reply::Quit,
+            _ => Reply::Output(format!("unknown command {}, see :help", name))
+        }
+    }
+
+    fn vars(&self) -> String {
+        let mut out = String::new();
+        for (name, var_type) in self.analyzer.global_variables() {
+            match self.interpreter.get_global(name) {
+                Some(value) => writeln!(out, "{} : {} = {}", name, var_type, value).unwrap(),
+                None => writeln!(out, "{} : {}", name, var_type).unwrap()
+            }
+        }
+        out.trim_end().to_owned()
+    }
+}
+
+// the input can be run once its `begin`s are closed and no procedure is waiting for its body
+fn is_complete(input: &str, options: &LexerOptions) -> bool {
+    let tokens = match tokenize_with_options(input, options.clone()) {
+        Ok(tokens) => tokens,
+        Err(RuntimeError::UnterminatedComment(_)) | Err(RuntimeError::UnterminatedConditional(_)) => return false,
+        // the parser reports other lexical errors
+        Err(_) => return true
+    };
+    let count = |token_type: TokenType| tokens.iter().filter(|token| token.token_type() == token_type).count();
+    let (begins, ends, procedures) = (count(TokenType::Begin), count(TokenType::End), count(TokenType::Procedure));
+    begins <= ends && (procedures == 0 || begins > 0)
+}
@@ -2,24 +2,92 @@ use crate::pascal_interpreter::{
     ast::nodes::*,
     error::RuntimeError,
     literal::Literal,
+    logging,
+    native::NativeFunction,
     symbols,
+    token::TokenType,
 };
 
+use std::collections::HashMap;
 use std::rc::Rc;
 
 pub struct SemanticAnalyzer {
     current_scope : Rc<Option<symbols::ScopedSymbolTable>>,
+    // symbols of native functions, defined in the global scope of every program
+    natives: Vec<(String, symbols::Symbol)>,
+    // types of the global variables declared by the last analyzed program, by lowercase name
+    globals: HashMap<String, symbols::Type>,
+}
+
+impl Default for SemanticAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SemanticAnalyzer {
     pub fn new() -> Self {
         SemanticAnalyzer { 
             current_scope: Rc::from(None),
+            natives: Vec::new(),
+            globals: HashMap::new(),
         }
     }
 
+    pub fn define_native(&mut self, native: &NativeFunction) {
+        self.natives.push((native.name().to_owned(), native.symbol()));
+    }
+
+    /// Declared type of a global variable of the last analyzed program
+    pub fn global_type(&self, name: &str) -> Option<&symbols::Type> {
+        self.globals.get(&name.to_lowercase())
+    }
+
+    /// Global variables of the last analyzed program or session, sorted by name
+    pub fn global_variables(&self) -> Vec<(&str, &symbols::Type)> {
+        let mut globals: Vec<(&str, &symbols::Type)> = self.globals.iter().map(|(name, var_type)| (name.as_str(), var_type)).collect();
+        globals.sort_by_key(|(name, _)| *name);
+        globals
+    }
+
+    /// Start a session of fragments analyzed one after the other against the same global scope, as in the REPL
+    pub fn start_session(&mut self) {
+        self.globals.clear();
+        self.enter_global_scope();
+    }
+
+    /// Analyze a fragment of the session. A bare call of a procedure is turned into a statement,
+    /// and a failing fragment leaves the global scope as it was.
+    pub fn analyze_fragment(&mut self, fragment: &mut Fragment) -> Result<(), RuntimeError> {
+        if let Fragment::Expression(Expr::Call(call)) = fragment {
+            let name = call.name.literal().to_str().unwrap_or_default();
+            if let Some(symbols::Symbol::Procedure(..)) | Some(symbols::Symbol::Native(_, None)) = self.lookup_symbol(name) {
+                *fragment = Fragment::Statements(vec![Stmt::ProcedureCall(call.clone())]);
+            }
+        }
+
+        let saved_symbols = self.current_scope.as_ref().as_ref().map(|scope| scope.symbols.clone()).unwrap_or_default();
+        let result = match fragment {
+            Fragment::Declarations(declarations) => declarations.iter_mut().try_for_each(|decl| decl.accept_visitor(self).map(|_| ())),
+            Fragment::Statements(statements) => statements.iter_mut().try_for_each(|stmt| stmt.accept_visitor(self).map(|_| ())),
+            Fragment::Expression(expr) => self.visit_expr(expr).map(|_| ())
+        };
+        if result.is_err() {
+            // a failure inside a procedure body leaves its scope current
+            while self.current_scope.as_ref().as_ref().is_some_and(|scope| scope.nesting_level() > 1) {
+                self.restore_previous_scope();
+            }
+            if let Some(scope) = Rc::get_mut(&mut self.current_scope).and_then(Option::as_mut) {
+                scope.symbols = saved_symbols;
+            }
+        }
+        self.record_globals();
+        result
+    }
+
     pub fn analyze(&mut self, program: &mut ProgramNode) -> Result<Option<Literal>, RuntimeError> {
         self.current_scope = Rc::from(None);
+        self.globals.clear();
         self.visit_program(program)
     }
 
@@ -39,12 +107,97 @@ impl SemanticAnalyzer {
     }
 
     fn set_current_scope(&mut self, scope: symbols::ScopedSymbolTable) {
+        log::trace!(target: logging::SEMA, "entering scope {:?}", scope);
         self.current_scope = Rc::from(Some(scope));
     }
 
+    /// Type of an expression, or None where it can't be told before running
+    pub fn expr_type(&self, expr: &Expr) -> Option<symbols::Type> {
+        match expr {
+            Expr::Num(node) => match node.token.literal() {
+                Literal::Int(_) => Some(symbols::Type::Integer),
+                Literal::Float(_) => Some(symbols::Type::Float),
+                _ => None
+            },
+            Expr::Var(node) => match self.lookup_symbol(node.name.literal().to_str()?) {
+                Some(symbols::Symbol::Var(var_type)) => Some(var_type.clone()),
+                _ => None
+            },
+            Expr::UnaryOp(node) => self.expr_type(&node.node),
+            Expr::BinaryOp(node) => match node.op.token_type() {
+                TokenType::Division => Some(symbols::Type::Float),
+                TokenType::IntegerDivision | TokenType::Mod | TokenType::Modulus => Some(symbols::Type::Integer),
+                _ => match (self.expr_type(&node.left_side)?, self.expr_type(&node.right_side)?) {
+                    (symbols::Type::Integer, symbols::Type::Integer) => Some(symbols::Type::Integer),
+                    (symbols::Type::String, symbols::Type::String) => Some(symbols::Type::String),
+                    (symbols::Type::String, _) | (_, symbols::Type::String) => None,
+                    _ => Some(symbols::Type::Float)
+                }
+            },
+            Expr::Call(node) => match &node.proc_symbol {
+                Some(symbols::Symbol::Native(_, return_type)) => return_type.clone(),
+                _ => None
+            }
+        }
+    }
+
+    // arguments are checked against the formal parameters, where an integer may be passed for a real
+    fn check_arguments(&self, call: &ProcedureCallNode, formal_params: &[symbols::Type]) -> Result<(), RuntimeError> {
+        let name = call.name.literal().to_str().unwrap_or_default().to_owned();
+        if formal_params.len() != call.actual_params.len() {
+            return Err(RuntimeError::ArgumentCountMismatch(name, formal_params.len(), call.actual_params.len()))
+        }
+        for (i, (formal, actual)) in formal_params.iter().zip(&call.actual_params).enumerate() {
+            match (formal, self.expr_type(actual)) {
+                (_, None) | (symbols::Type::Float, Some(symbols::Type::Integer)) => {},
+                (formal, Some(actual)) if *formal != actual => {
+                    return Err(RuntimeError::ArgumentTypeMismatch(name, i + 1, formal.clone(), actual))
+                },
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // a call inside an expression has to be to a function. A function without parameters may be
+    // called without parentheses, as in `n := ParamCount`.
+    fn visit_expr(&mut self, expr: &mut Expr) -> Result<Option<Literal>, RuntimeError> {
+        if let Expr::Var(var) = expr {
+            let var_name = var.name.literal().to_str().ok_or(RuntimeError::IllformedVarExpr)?;
+            if let Some(symbols::Symbol::Procedure(..)) | Some(symbols::Symbol::Native(..)) = self.lookup_symbol(var_name) {
+                let span = var.name.span();
+                *expr = Expr::Call(ProcedureCallNode::from(var.name.clone(), Vec::new()).with_span(span));
+            }
+        }
+        expr.accept_visitor(self)?;
+        if let Expr::Call(call) = expr {
+            if let Some(symbols::Symbol::Procedure(..)) | Some(symbols::Symbol::Native(_, None)) = call.proc_symbol {
+                return Err(RuntimeError::NotAFunction(call.name.literal().to_str().unwrap_or_default().to_owned()))
+            }
+        }
+        Ok(None)
+    }
+
+    // the scope of the program, with the natives in it
+    fn enter_global_scope(&mut self) {
+        self.set_current_scope(symbols::ScopedSymbolTable::from(String::from("global"), 1u32, Rc::from(None)));
+        for (name, symbol) in self.natives.clone() {
+            self.define_symbol(&name, symbol);
+        }
+    }
+
+    fn record_globals(&mut self) {
+        if let Some(global_scope) = self.current_scope.as_ref() {
+            self.globals = global_scope.symbols.iter().filter_map(|(name, symbol)| match symbol {
+                symbols::Symbol::Var(var_type) => Some((name.clone(), var_type.clone())),
+                _ => None
+            }).collect();
+        }
+    }
+
     // will panic if there's no enclosing scope to restore
     fn restore_previous_scope(&mut self) {
-        unimplemented!()
+        self.current_scope = self.current_scope.as_ref().as_ref().unwrap().enclosing_scope.clone();
     }
 }
 
@@ -54,12 +207,12 @@ impl NodeVisitor for SemanticAnalyzer {
     }
 
     fn visit_binary_op(&mut self, visitable: &mut BinaryOpNode) -> Result<Option<Literal>, RuntimeError> {
-        visitable.left_side.accept_visitor(self)?;
-        visitable.right_side.accept_visitor(self)
+        self.visit_expr(&mut visitable.left_side)?;
+        self.visit_expr(&mut visitable.right_side)
     }
 
-    fn visit_unary_op(&mut self, _: &mut UnaryOpNode) -> Result<Option<Literal>, RuntimeError> {
-        Ok(None)
+    fn visit_unary_op(&mut self, visitable: &mut UnaryOpNode) -> Result<Option<Literal>, RuntimeError> {
+        self.visit_expr(&mut visitable.node)
     }
 
     fn visit_compound(&mut self, visitable: &mut CompoundStatementNode) -> Result<Option<Literal>, RuntimeError> {
@@ -76,7 +229,7 @@ impl NodeVisitor for SemanticAnalyzer {
     }    
 
      fn visit_assignment(&mut self, visitable: &mut AssignmentNode) -> Result<Option<Literal>, RuntimeError> {
-         visitable.right.accept_visitor(self)?;
+         self.visit_expr(&mut visitable.right)?;
          self.visit_var(&mut visitable.left)
     }
 
@@ -85,14 +238,12 @@ impl NodeVisitor for SemanticAnalyzer {
     }
 
     fn visit_program(&mut self, visitable: &mut ProgramNode) -> Result<Option<Literal>, RuntimeError> {
-        let program_scope = Rc::from(
-            Some(symbols::ScopedSymbolTable::from(String::from("global"), 1u32, Rc::from(None)))
-        );
-        self.current_scope = program_scope;
+        self.enter_global_scope();
         self.visit_block(&mut visitable.block)?;
+        self.record_globals();
         
-        self.current_scope = self.current_scope.as_ref().as_ref().unwrap().enclosing_scope.clone();
-        println!("Ref count is {}", Rc::strong_count(&self.current_scope));
+        self.restore_previous_scope();
+        log::debug!(target: logging::SEMA, "program {} analyzed, globals {:?}", visitable.name.to_str().unwrap_or_default(), self.global_variables());
 
         Ok(None)
     }
@@ -121,6 +272,7 @@ impl NodeVisitor for SemanticAnalyzer {
         match &type_symbol {
              Some(symbols::Symbol::Builtin(internal_type)) => { 
                  let var_symbol = symbols::Symbol::Var(internal_type.clone());
+                 visitable.var_type = Some(internal_type.clone());
                  self.define_symbol(variable_name, var_symbol);
                  Ok(None)
              },
@@ -129,48 +281,49 @@ impl NodeVisitor for SemanticAnalyzer {
          }        
     }
 
-    fn visit_type(&mut self, _: &mut TypeNode) -> Result<Option<Literal>, RuntimeError> {
-        Ok(None)
-    }
-
     fn visit_procedure_decl(&mut self, visitable: &mut ProcedureDeclNode) -> Result<Option<Literal>, RuntimeError> {
        let mut params : Vec<(String, symbols::Type)> = Vec::new();
         for i in &visitable.params {
-            let param_name = i.var.name.literal().to_str().unwrap();
-            let param_type = i.param_type.get_type();
+            let param_name = i.var.name.literal().to_str().ok_or(RuntimeError::IllformedVarExpr)?;
+            let param_type = i.param_type.get_type()?;
             params.push((param_name.to_owned(), param_type));
             
         }
 
-        let proc_name = visitable.name.to_str().unwrap();
-        let procedure_symbol = symbols::Symbol::Procedure(params.clone(), visitable.block.clone());
+        let proc_name = visitable.name.to_str().ok_or(RuntimeError::IllformedVarExpr)?;
+        // defined before the body is analyzed, so the procedure can call itself
+        let qualified_name = self.current_scope.as_ref().as_ref().unwrap().qualify(proc_name);
+        let procedure_symbol = symbols::Symbol::Procedure(params.clone(), qualified_name);
         self.define_symbol(proc_name, procedure_symbol);
     
-        let procedure_scope = Rc::from(
-            Some(symbols::ScopedSymbolTable::from(proc_name.to_lowercase(), 2, self.current_scope.clone()))
-        );
-        self.current_scope = procedure_scope;
+        let nesting_level = self.current_scope.as_ref().as_ref().unwrap().nesting_level() + 1;
+        self.set_current_scope(symbols::ScopedSymbolTable::from(proc_name.to_lowercase(), nesting_level, self.current_scope.clone()));
        
         for (param_name, param_type) in &params {
             let var_symbol = symbols::Symbol::Var(param_type.clone());
-            self.define_symbol(&param_name, var_symbol);
+            self.define_symbol(param_name, var_symbol);
         }
 
         self.visit_block(&mut visitable.block)?;
-        self.current_scope = self.current_scope.as_ref().as_ref().unwrap().enclosing_scope.clone();
-        println!("Ref count is {}", Rc::strong_count(&self.current_scope));
-        Ok(None)
-    }
-
-    fn visit_param(&mut self, _: &mut ParamNode) -> Result<Option<Literal>, RuntimeError> {
+        log::trace!(target: logging::SEMA, "leaving {:?}", self.current_scope);
+        self.restore_previous_scope();
         Ok(None)
     }
 
     fn visit_procedure_call(&mut self, visitable: &mut ProcedureCallNode) -> Result<Option<Literal>, RuntimeError> {
         for param in &mut visitable.actual_params {
-            param.accept_visitor(self)?;
+            self.visit_expr(param)?;
+        }
+        let proc_name = visitable.name.literal().to_str().ok_or(RuntimeError::IllformedVarExpr)?;
+        let proc_symbol = self.lookup_symbol(proc_name).cloned();
+        match &proc_symbol {
+            Some(symbols::Symbol::Procedure(params, _)) => {
+                let param_types: Vec<symbols::Type> = params.iter().map(|(_, param_type)| param_type.clone()).collect();
+                self.check_arguments(visitable, &param_types)?;
+            },
+            Some(symbols::Symbol::Native(params, _)) => self.check_arguments(visitable, params)?,
+            _ => return Err(RuntimeError::UndefinedProcedure(proc_name.to_owned()))
         }
-        let proc_symbol = self.current_scope.as_ref().as_ref().unwrap().lookup_symbol(visitable.name.to_str().unwrap()).map(|s| s.clone());
 
         visitable.proc_symbol = proc_symbol;
         Ok(None)
@@ -1,25 +1,41 @@
-use crate::pascal_interpreter::ast::nodes::BlockNode;
-
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub enum Type
 {
     Integer,
-    Float
+    Float,
+    String
 }
 
-#[derive(Clone)]
+/// Writes the Pascal name of the type
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Integer => write!(f, "integer"),
+            Type::Float => write!(f, "real"),
+            Type::String => write!(f, "string"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Symbol {
     Builtin(Type),
     Var(Type),
-    Procedure(Vec<(String, Type)>,  BlockNode), // params
+    // params and the qualified name of the declaration, e.g. `global.alpha.beta`
+    Procedure(Vec<(String, Type)>, String),
+    // params and result of a procedure or function implemented in Rust
+    Native(Vec<Type>, Option<Type>),
 }
 
 //#[derive(Clone)]
 pub struct ScopedSymbolTable {
     name: String,
+    // names of the enclosing scopes and this one, joined by dots
+    path: String,
     nesting_level: u32,
     pub symbols : HashMap<String, Symbol>,
     pub enclosing_scope: Rc<Option<ScopedSymbolTable>>
@@ -27,14 +43,28 @@ pub struct ScopedSymbolTable {
 
 impl ScopedSymbolTable {
     pub fn from(name: String, level: u32, enclosing_scope: Rc<Option<ScopedSymbolTable>>) -> Self {
+        let path = match enclosing_scope.as_ref() {
+            Some(enclosing) => qualified_name(&enclosing.path, &name),
+            None => name.to_lowercase()
+        };
         ScopedSymbolTable{
-            name: name, 
+            name, 
+            path,
             nesting_level: level, 
             symbols: ScopedSymbolTable::init_builtin_symbols(),
-            enclosing_scope: enclosing_scope
+            enclosing_scope
         }
     }
 
+    pub fn nesting_level(&self) -> u32 {
+        self.nesting_level
+    }
+
+    /// Qualified name of a procedure declared in this scope
+    pub fn qualify(&self, name: &str) -> String {
+        qualified_name(&self.path, name)
+    }
+
     pub fn define_symbol(&mut self, name : &str, value : Symbol) {
         self.symbols.insert(name.to_lowercase(), value);
     }
@@ -50,6 +80,17 @@ impl ScopedSymbolTable {
         let mut result = HashMap::new();
         result.insert(String::from("integer"), Symbol::Builtin(Type::Integer));
         result.insert(String::from("real"), Symbol::Builtin(Type::Float));
+        result.insert(String::from("string"), Symbol::Builtin(Type::String));
         result
     }
 }
+
+pub fn qualified_name(scope_path: &str, name: &str) -> String {
+    format!("{}.{}", scope_path, name.to_lowercase())
+}
+
+impl fmt::Debug for ScopedSymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Scope(name = {}, level = {}, symbols = {:?})", self.name, self.nesting_level, self.symbols)
+    }
+}
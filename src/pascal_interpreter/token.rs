@@ -1,4 +1,9 @@
-use crate::pascal_interpreter::literal::Literal;
+use crate::pascal_interpreter::{
+    directive::Switches,
+    literal::Literal,
+};
+
+use std::fmt;
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum TokenType
@@ -14,6 +19,16 @@ pub enum TokenType
    Rparen,
    Identifier,
    Assignment,
+   Equal,
+   NotEqual,
+   Less,
+   LessEqual,
+   Greater,
+   GreaterEqual,
+   Range,
+   LparenStar,
+   StarRparen,
+   DoubleSlash,
    Semi,
    Begin,
    End,
@@ -25,34 +40,282 @@ pub enum TokenType
    Comma,
    IntegerType,
    FloatType,
+   StringType,
    Procedure,
-   EOF,
+   // reserved words without grammar support yet
+   And,
+   Array,
+   Asm,
+   Case,
+   Const,
+   Constructor,
+   Destructor,
+   Do,
+   Downto,
+   Else,
+   Exports,
+   File,
+   For,
+   Function,
+   Goto,
+   If,
+   Implementation,
+   In,
+   Inherited,
+   Inline,
+   Interface,
+   Label,
+   Library,
+   Mod,
+   Nil,
+   Not,
+   Object,
+   Of,
+   Or,
+   Packed,
+   Record,
+   Repeat,
+   Set,
+   Shl,
+   Shr,
+   Then,
+   To,
+   Type,
+   Unit,
+   Until,
+   Uses,
+   While,
+   With,
+   Xor,
+   Eof,
+}
+
+impl TokenType {
+    /// Whether the token is a reserved word, which can never be used as an identifier
+    pub fn is_reserved_word(self) -> bool {
+        matches!(self,
+            TokenType::Begin | TokenType::End | TokenType::Program | TokenType::Var |
+            TokenType::IntegerDivision | TokenType::IntegerType | TokenType::FloatType |
+            TokenType::Procedure | TokenType::And | TokenType::Array | TokenType::Asm |
+            TokenType::Case | TokenType::Const | TokenType::Constructor | TokenType::Destructor |
+            TokenType::Do | TokenType::Downto | TokenType::Else | TokenType::Exports |
+            TokenType::File | TokenType::For | TokenType::Function | TokenType::Goto |
+            TokenType::If | TokenType::Implementation | TokenType::In | TokenType::Inherited |
+            TokenType::Inline | TokenType::Interface | TokenType::Label | TokenType::Library |
+            TokenType::Mod | TokenType::Nil | TokenType::Not | TokenType::Object | TokenType::Of |
+            TokenType::Or | TokenType::Packed | TokenType::Record | TokenType::Repeat |
+            TokenType::Set | TokenType::Shl | TokenType::Shr | TokenType::StringType |
+            TokenType::Then | TokenType::To | TokenType::Type | TokenType::Unit |
+            TokenType::Until | TokenType::Uses | TokenType::While | TokenType::With |
+            TokenType::Xor)
+    }
+}
+
+/// Writes how the token is spelled in quotes, or the kind of token it is, as error messages name it
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let spelling = match self {
+            TokenType::IntegerConst => return write!(f, "integer constant"),
+            TokenType::FloatConst => return write!(f, "real constant"),
+            TokenType::Identifier => return write!(f, "identifier"),
+            TokenType::Eof => return write!(f, "end of input"),
+            TokenType::Plus => "+",
+            TokenType::Minus => "-",
+            TokenType::Multiply => "*",
+            TokenType::Division => "/",
+            TokenType::Modulus => "%",
+            TokenType::Lparen => "(",
+            TokenType::Rparen => ")",
+            TokenType::Assignment => ":=",
+            TokenType::Equal => "=",
+            TokenType::NotEqual => "<>",
+            TokenType::Less => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::Greater => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::Range => "..",
+            TokenType::LparenStar => "(*",
+            TokenType::StarRparen => "*)",
+            TokenType::DoubleSlash => "//",
+            TokenType::Semi => ";",
+            TokenType::Dot => ".",
+            TokenType::Colon => ":",
+            TokenType::Comma => ",",
+            TokenType::IntegerDivision => "div",
+            TokenType::IntegerType => "integer",
+            TokenType::FloatType => "real",
+            TokenType::StringType => "string",
+            // the other reserved words are named like their variants
+            reserved_word => return write!(f, "'{}'", format!("{:?}", reserved_word).to_lowercase()),
+        };
+        write!(f, "'{}'", spelling)
+    }
+}
+
+/// Line and column of a character in the source text, both starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: u32,
+    pub col: u32,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// Byte range `start..end` of the source text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+    Directive,
+    // source excluded by `{$IFDEF}` and friends
+    InactiveCode,
 }
 
+/// Source text between tokens that the parser does not see
 #[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    kind: TriviaKind,
+    text: String,
+    span: Span,
+}
+
+impl Trivia {
+    pub fn new(kind: TriviaKind, text: String, span: Span) -> Trivia {
+        Trivia { kind, text, span }
+    }
+
+    pub fn kind(&self) -> TriviaKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    // whitespace runs are split after each line break, so this only holds for the last piece of a line
+    fn ends_line(&self) -> bool {
+        self.kind == TriviaKind::Whitespace && self.text.ends_with('\n')
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Token {
-        token_type: TokenType,
-        literal: Literal,
-        line : u32,
-        col: u32,
-    }
-
-    impl Token {
-        pub fn new(token_type: TokenType, literal: Literal, line: u32, col: u32) -> Token {
-            Token {
-                token_type: token_type,
-                literal: literal,
-                line: line, 
-                col: col,
-            }
-        }
+    token_type: TokenType,
+    literal: Literal,
+    line : u32,
+    col: u32,
+    span: Span,
+    text: String,
+    leading_trivia: Vec<Trivia>,
+    trailing_trivia: Vec<Trivia>,
+    switches: Switches,
+}
 
-        pub fn token_type(&self) -> TokenType {
-            self.token_type
+// tokens are equal when they mean the same: position, spelling and trivia are ignored,
+// and identifiers and keywords compare case-insensitively as in Pascal
+impl PartialEq for Token {
+    fn eq(&self, other: &Token) -> bool {
+        self.token_type == other.token_type && match (&self.literal, &other.literal) {
+            (Literal::Text(lhs), Literal::Text(rhs)) => lhs.eq_ignore_ascii_case(rhs),
+            (lhs, rhs) => lhs == rhs
         }
+    }
+}
 
-        pub fn literal(&self) -> &Literal {
-           &self.literal
+impl Token {
+    pub fn new(token_type: TokenType, literal: Literal, line: u32, col: u32) -> Token {
+        Token {
+            token_type,
+            literal,
+            line,
+            col,
+            span: Span::default(),
+            text: String::new(),
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
+            switches: Switches::default(),
         }
     }
 
+    /// Attach the source text the token was read from and the trivia in front of it
+    pub fn with_source(mut self, span: Span, text: String, leading_trivia: Vec<Trivia>) -> Token {
+        self.span = span;
+        self.text = text;
+        self.leading_trivia = leading_trivia;
+        self
+    }
+
+    pub fn with_switches(mut self, switches: Switches) -> Token {
+        self.switches = switches;
+        self
+    }
+
+    pub fn token_type(&self) -> TokenType {
+        self.token_type
+    }
+
+    pub fn literal(&self) -> &Literal {
+        &self.literal
+    }
+
+    pub fn position(&self) -> Position {
+        Position { line: self.line, col: self.col }
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn col(&self) -> u32 {
+        self.col
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The token exactly as written in the source, e.g. `$FF` or `Begin`
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Switches in effect where the token appears in the source
+    pub fn switches(&self) -> Switches {
+        self.switches
+    }
+
+    /// Length in bytes of the token with all of its trivia
+    pub fn full_len(&self) -> usize {
+        let trivia_len: usize = self.leading_trivia.iter().chain(&self.trailing_trivia).map(|trivia| trivia.text.len()).sum();
+        trivia_len + self.text.len()
+    }
+
+    pub fn leading_trivia(&self) -> &[Trivia] {
+        &self.leading_trivia
+    }
+
+    pub fn trailing_trivia(&self) -> &[Trivia] {
+        &self.trailing_trivia
+    }
+
+    /// Move the leading trivia up to and including the first line break to the trailing trivia of `previous`
+    pub(crate) fn split_trivia_with(&mut self, previous: &mut Token) {
+        let count = self.leading_trivia.iter().position(Trivia::ends_line)
+            .map_or(self.leading_trivia.len(), |index| index + 1);
+        previous.trailing_trivia = self.leading_trivia.drain(..count).collect();
+    }
+}
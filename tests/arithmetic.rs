@@ -0,0 +1,84 @@
+use pascal_interpreter::{
+    error::RuntimeError,
+    literal::LiteralType,
+    token::TokenType,
+    Engine, RunOptions,
+};
+
+fn run(body: &str) -> Result<Engine, RuntimeError> {
+    let source = format!("program Main; var i : integer; r : real; s : string; begin {} end.", body);
+    let mut engine = Engine::new();
+    engine.set_global("s", "a");
+    engine.run_source(&source, &RunOptions::default()).into_result()?;
+    Ok(engine)
+}
+
+// the error of a failing run, without its backtrace
+fn fail(body: &str) -> RuntimeError {
+    match run(body) {
+        Ok(_) => panic!("{} ran", body),
+        Err(RuntimeError::WithBacktrace(cause, _)) => *cause,
+        Err(err) => err
+    }
+}
+
+#[test]
+fn div_gives_an_integer() {
+    let engine = run("i := 7 div 2").unwrap();
+    assert_eq!(engine.get_global::<i64>("i"), Some(3));
+    let engine = run("i := -7 div 2").unwrap();
+    assert_eq!(engine.get_global::<i64>("i"), Some(-3));
+}
+
+#[test]
+fn slash_gives_a_real() {
+    let engine = run("r := 7 / 2").unwrap();
+    assert_eq!(engine.get_global::<f64>("r"), Some(3.5));
+}
+
+#[test]
+fn div_by_zero_fails() {
+    assert_eq!(fail("i := 7 div 0"), RuntimeError::DivisionByZero);
+}
+
+#[test]
+fn unsupported_operands_name_the_operator() {
+    let err = fail("i := s div 2");
+    assert_eq!(err, RuntimeError::UnsupportedOperands(TokenType::IntegerDivision, LiteralType::Text, LiteralType::Int));
+    let err = fail("i := s mod 2");
+    assert_eq!(err, RuntimeError::UnsupportedOperands(TokenType::Mod, LiteralType::Text, LiteralType::Int));
+    let err = fail("r := s / 2");
+    assert_eq!(err, RuntimeError::UnsupportedOperands(TokenType::Division, LiteralType::Text, LiteralType::Int));
+}
+
+#[test]
+fn percent_after_an_operand_is_the_modulus() {
+    let engine = run("i := 17; i := i%10").unwrap();
+    assert_eq!(engine.get_global::<i64>("i"), Some(7));
+    let engine = run("i := 7 % %11").unwrap();
+    assert_eq!(engine.get_global::<i64>("i"), Some(1));
+}
+
+#[test]
+fn overflow_checks_follow_the_q_switch() {
+    assert_eq!(fail("i := $7FFFFFFFFFFFFFFF + 1"), RuntimeError::IntegerOverflow);
+    let engine = run("{$Q-} i := $7FFFFFFFFFFFFFFF + 1").unwrap();
+    assert_eq!(engine.get_global::<i64>("i"), Some(i64::MIN));
+    let engine = run("{$OVERFLOWCHECKS OFF} i := -$7FFFFFFFFFFFFFFF - 2").unwrap();
+    assert_eq!(engine.get_global::<i64>("i"), Some(i64::MAX));
+    assert_eq!(fail("{$Q-} i := $7FFFFFFFFFFFFFFF + 1; {$Q+} i := i - 1"), RuntimeError::IntegerOverflow);
+}
+
+#[test]
+fn switches_apply_where_the_operator_is() {
+    let engine = run("i := {$Q-} $7FFFFFFFFFFFFFFF * 2 {$Q+}").unwrap();
+    assert_eq!(engine.get_global::<i64>("i"), Some(-2));
+    assert_eq!(fail("i := $7FFFFFFFFFFFFFFF {$Q-} {$Q+} * 2"), RuntimeError::IntegerOverflow);
+}
+
+#[test]
+fn switches_without_effect_change_nothing() {
+    let engine = run("{$R+,I-,B+} i := 7 div 2; r := 1 / 4").unwrap();
+    assert_eq!(engine.get_global::<i64>("i"), Some(3));
+    assert_eq!(engine.get_global::<f64>("r"), Some(0.25));
+}
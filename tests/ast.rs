@@ -0,0 +1,136 @@
+use pascal_interpreter::{
+    ast::{dot, nodes::VarNode, visit::{walk_var_mut, VisitorMut}},
+    interpreter::Interpreter,
+    literal::Literal,
+    parser::Parser,
+    semantic_analyzer::SemanticAnalyzer,
+    token::Token,
+    LexerOptions,
+};
+
+use std::collections::HashMap;
+
+const NESTED: &str = "program Main;
+procedure Outer(a : integer);
+  procedure Inner(b : integer);
+  begin
+    Inner(b - 1)
+  end;
+begin
+  Inner(a);
+  Inner(a + 1)
+end;
+procedure Other;
+begin
+  Outer(1);
+  Missing()
+end;
+begin
+  Outer(2);
+  Other()
+end.";
+
+// renames every use and declaration of one variable
+struct Rename {
+    from: &'static str,
+    to: &'static str,
+}
+
+impl VisitorMut for Rename {
+    fn visit_var(&mut self, node: &mut VarNode) {
+        if node.name.literal().to_str() == Some(self.from) {
+            let name = &node.name;
+            node.name = Token::new(name.token_type(), Literal::from_str(self.to.to_owned()), name.line(), name.col());
+        }
+        walk_var_mut(self, node)
+    }
+}
+
+#[test]
+fn rewritten_ast_runs_with_the_new_names() {
+    let source = "program Main;
+var a, b : integer;
+begin
+  a := 2;
+  b := a * 3
+end.";
+    let mut program = Parser::from(source, LexerOptions::default()).parse().unwrap();
+    Rename { from: "a", to: "x" }.visit_program(&mut program);
+    SemanticAnalyzer::new().analyze(&mut program).unwrap();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.interpret(&program).unwrap();
+    let globals = interpreter.globals().unwrap();
+    assert_eq!(globals.get_item("x"), Some(&Literal::Int(2)));
+    assert_eq!(globals.get_item("b"), Some(&Literal::Int(6)));
+    assert_eq!(globals.get_item("a"), None);
+}
+
+// the edges of a DOT graph as pairs of node labels
+fn edges(dot: &str) -> Vec<(String, String)> {
+    let mut labels = HashMap::new();
+    let mut edges = Vec::new();
+    for line in dot.lines().map(str::trim) {
+        if let Some((id, label)) = line.strip_suffix("\"];").and_then(|line| line.split_once(" [label=\"")) {
+            labels.insert(id.to_owned(), label.to_owned());
+        } else if let Some((from, to)) = line.strip_suffix(';').and_then(|line| line.split_once(" -> ")) {
+            edges.push((labels[from].clone(), labels[to].clone()));
+        }
+    }
+    edges
+}
+
+#[test]
+fn ast_graph_nests_procedures_and_calls() {
+    let program = Parser::from(NESTED, LexerOptions::default()).parse().unwrap();
+    let dot = dot::ast_to_dot(&program);
+    assert!(dot.starts_with("digraph ast {\n"));
+    assert!(dot.ends_with("}\n"));
+    let edges = edges(&dot);
+    for (parent, child) in &[
+        ("Program Main", "Block"),
+        ("ProcedureDecl Outer", "Param"),
+        ("ProcedureDecl Outer", "Block"),
+        ("ProcedureDecl Inner", "Block"),
+        ("Call Inner", "BinaryOp -"),
+        ("Call Inner", "Var a"),
+        ("Call Outer", "Num 1"),
+        ("Compound", "Call Missing"),
+    ] {
+        assert!(edges.contains(&(parent.to_string(), child.to_string())), "{} -> {}", parent, child);
+    }
+    assert_eq!(edges.iter().filter(|(parent, _)| parent.starts_with("Call ")).count(), 5);
+    assert_eq!(edges.iter().filter(|(_, child)| child.starts_with("Call ")).count(), 7);
+}
+
+#[test]
+fn call_graph_draws_nested_procedures_inside_their_scope() {
+    let program = Parser::from(NESTED, LexerOptions::default()).parse().unwrap();
+    assert_eq!(dot::call_graph_to_dot(&program), r#"digraph calls {
+  node [shape=box, fontname="monospace"];
+  subgraph "cluster_global" {
+    label="Main";
+    "global" [label="program Main"];
+    subgraph "cluster_global.outer" {
+      label="Outer";
+      "global.outer" [label="Outer(a)"];
+      subgraph "cluster_global.outer.inner" {
+        label="Inner";
+        "global.outer.inner" [label="Inner(b)"];
+      }
+    }
+    subgraph "cluster_global.other" {
+      label="Other";
+      "global.other" [label="Other()"];
+    }
+  }
+  "?Missing" [label="Missing", style=dashed];
+  "global" -> "global.other";
+  "global" -> "global.outer";
+  "global.other" -> "?Missing";
+  "global.other" -> "global.outer";
+  "global.outer" -> "global.outer.inner" [label="2"];
+  "global.outer.inner" -> "global.outer.inner";
+}
+"#);
+}
@@ -0,0 +1,122 @@
+use pascal_interpreter::{
+    error::RuntimeError,
+    literal::{Literal, LiteralType},
+    symbols::Type,
+    Engine, Outcome, RunOptions,
+};
+
+fn run(engine: &mut Engine, source: &str) -> Outcome {
+    engine.run_source(source, &RunOptions::default())
+}
+
+fn semantic_error(source: &str) -> RuntimeError {
+    match run(&mut Engine::new(), source) {
+        Outcome::SemanticError(err) => err,
+        outcome => panic!("{:?}", outcome)
+    }
+}
+
+// the error of a failing run, without its backtrace
+fn runtime_error(engine: &mut Engine, source: &str) -> RuntimeError {
+    match run(engine, source) {
+        Outcome::RuntimeError(RuntimeError::WithBacktrace(cause, _)) => *cause,
+        outcome => panic!("{:?}", outcome)
+    }
+}
+
+#[test]
+fn calls_to_undeclared_names_are_rejected() {
+    let err = semantic_error("program Main; begin Missing(1) end.");
+    assert_eq!(err, RuntimeError::UndefinedProcedure("Missing".to_owned()));
+    let err = semantic_error("program Main; var x : integer; begin x := Missing(1) end.");
+    assert_eq!(err, RuntimeError::UndefinedProcedure("Missing".to_owned()));
+}
+
+#[test]
+fn calls_to_variables_are_rejected() {
+    let err = semantic_error("program Main; var x : integer; begin x(1) end.");
+    assert_eq!(err, RuntimeError::UndefinedProcedure("x".to_owned()));
+}
+
+#[test]
+fn a_space_before_the_arguments_is_still_a_call() {
+    let mut engine = Engine::new();
+    let outcome = run(&mut engine, "program Main; var x : integer; procedure P(a : integer); begin end; begin P (1); x := 2 end.");
+    assert!(outcome.is_completed(), "{:?}", outcome);
+    assert_eq!(engine.get_global::<i64>("x"), Some(2));
+}
+
+#[test]
+fn native_results_are_checked_against_the_return_type() {
+    let mut engine = Engine::new();
+    engine.register_fn("Name", &[], Type::Integer, |_| Ok(Literal::from_str("x".to_owned())));
+    let err = runtime_error(&mut engine, "program Main; var i : integer; begin i := Name() end.");
+    assert_eq!(err, RuntimeError::NativeReturnTypeMismatch("Name".to_owned(), Some(Type::Integer), Some(LiteralType::Text)));
+}
+
+#[test]
+fn integer_results_of_real_natives_are_converted() {
+    let mut engine = Engine::new();
+    engine.register_fn("Half", &[Type::Integer], Type::Float, |args| match args[0] {
+        Literal::Int(int) => Ok(Literal::from_int(int / 2)),
+        _ => unreachable!()
+    });
+    let outcome = run(&mut engine, "program Main; var r : real; begin r := Half(5) end.");
+    assert!(outcome.is_completed(), "{:?}", outcome);
+    assert_eq!(engine.get_global::<f64>("r"), Some(2.0));
+}
+
+#[test]
+fn functions_without_parameters_are_called_without_parentheses() {
+    let mut engine = Engine::new();
+    engine.register_fn("Answer", &[], Type::Integer, |_| Ok(Literal::from_int(42)));
+    let outcome = run(&mut engine, "program Main; var i : integer; begin i := Answer + 1 end.");
+    assert!(outcome.is_completed(), "{:?}", outcome);
+    assert_eq!(engine.get_global::<i64>("i"), Some(43));
+}
+
+#[test]
+fn bare_names_of_procedures_are_not_values() {
+    let err = semantic_error("program Main; var i : integer; procedure P; begin end; begin i := P end.");
+    assert_eq!(err, RuntimeError::NotAFunction("P".to_owned()));
+}
+
+#[test]
+fn bare_names_of_functions_with_parameters_need_arguments() {
+    let mut engine = Engine::new();
+    engine.register_fn("Twice", &[Type::Integer], Type::Integer, |args| Ok(args[0].clone()));
+    match run(&mut engine, "program Main; var i : integer; begin i := Twice end.") {
+        Outcome::SemanticError(err) => assert_eq!(err, RuntimeError::ArgumentCountMismatch("Twice".to_owned(), 1, 0)),
+        outcome => panic!("{:?}", outcome)
+    }
+}
+
+#[test]
+fn errors_carry_the_calls_they_happened_in() {
+    let source = "program Main;
+procedure Inner(b : integer);
+var c : integer;
+begin
+  c := b div 0
+end;
+procedure Outer(a : integer; r : real);
+begin
+  Inner(a * 2)
+end;
+begin
+  Outer(3, 1.5)
+end.";
+    let (cause, backtrace) = match run(&mut Engine::new(), source) {
+        Outcome::RuntimeError(RuntimeError::WithBacktrace(cause, backtrace)) => (cause, backtrace),
+        outcome => panic!("{:?}", outcome)
+    };
+    assert_eq!(*cause, RuntimeError::DivisionByZero);
+    let frames: Vec<_> = backtrace.frames.iter()
+        .map(|frame| (frame.name.as_str(), frame.call_site.map(|site| (site.line, site.col)), frame.arguments.clone()))
+        .collect();
+    assert_eq!(frames, [
+        ("Inner", Some((9, 3)), vec![("b".to_owned(), Literal::from_int(6))]),
+        ("Outer", Some((12, 3)), vec![("a".to_owned(), Literal::from_int(3)), ("r".to_owned(), Literal::from_float(1.5))]),
+        ("Main", None, vec![]),
+    ]);
+}
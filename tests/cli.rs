@@ -0,0 +1,62 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// runs the `pascal` binary with the input on its standard input
+fn pascal(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pascal"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn repl_reports_failures_once() {
+    let output = pascal(&["repl"], "var x : integer\nx := (1;\nprocedure P(a : integer); begin a := a div 0 end\nP(2)\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "\
+error: 1:8: expected ')', found ';'
+error: division by zero
+  at P(a = 2) called at 1:1
+  at repl
+");
+}
+
+#[test]
+fn failures_name_their_stage() {
+    let output = pascal(&["run", "-"], "program Main; begin x := end.");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "syntax error: 1:26: expected an expression, found 'end'\n");
+
+    let output = pascal(&["run", "-"], "program Main; begin x := 1 end.");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stderr(&output), "semantic error: undefined variable x\n");
+
+    let output = pascal(&["run", "-"], "program Main; var x : integer; begin x := 1 div 0 end.");
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(stderr(&output), "runtime error: division by zero\n  at Main\n");
+}
+
+#[test]
+fn nothing_is_logged_without_log() {
+    let source = "program Main; begin {$R+} end.";
+    let output = pascal(&["run", "-"], source);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "");
+
+    let output = pascal(&["run", "--log", "warn", "-"], source);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "[WARN lexer] 1:21: RangeChecks switch has no effect\n");
+
+    let output = pascal(&["run", "--log", "loud", "-"], source);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("unknown log level loud\n"));
+}
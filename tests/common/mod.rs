@@ -0,0 +1,9 @@
+// Programs shared by the tests: comments of all kinds, directives with excluded code and text
+// after `end.`, nested procedures and long expressions.
+
+pub const PROGRAMS: [(&str, &str); 4] = [
+    ("comments", include_str!("../programs/comments.pas")),
+    ("directives", include_str!("../programs/directives.pas")),
+    ("nested", include_str!("../programs/nested.pas")),
+    ("expressions", include_str!("../programs/expressions.pas")),
+];
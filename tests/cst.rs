@@ -0,0 +1,55 @@
+mod common;
+
+use pascal_interpreter::{
+    cst,
+    parser::Parser,
+    token::{TokenType, TriviaKind},
+    LexerOptions,
+};
+
+use common::PROGRAMS;
+
+#[test]
+fn syntax_tree_gives_back_the_source() {
+    for (name, source) in PROGRAMS.iter() {
+        let (_, syntax) = Parser::from(source, LexerOptions::default()).parse_with_syntax().unwrap();
+        assert_eq!(syntax.to_string(), *source, "{}", name);
+        assert_eq!(syntax.span().end, source.len(), "{}", name);
+    }
+}
+
+#[test]
+fn syntax_tree_lowers_to_the_parsed_ast() {
+    for (name, source) in PROGRAMS.iter() {
+        let (program, syntax) = Parser::from(source, LexerOptions::default()).parse_with_syntax().unwrap();
+        assert_eq!(cst::lower(&syntax), program, "{}", name);
+    }
+}
+
+#[test]
+fn text_after_the_program_is_kept() {
+    let source = "program Main; begin end. { done }\nnot Pascal (* at all";
+    let (_, syntax) = Parser::from(source, LexerOptions::default()).parse_with_syntax().unwrap();
+    assert_eq!(syntax.to_string(), source);
+}
+
+#[test]
+fn text_after_the_program_is_not_lexed() {
+    let (plain, _) = Parser::from("program Main; begin end.", LexerOptions::default()).parse_with_syntax().unwrap();
+    for (source, rest) in [
+        ("program Main; begin end. @@", " @@"),
+        ("program Main; begin end. (* open", " (* open"),
+        ("program Main; begin end.\n{ open\n'unterminated", "\n{ open\n'unterminated"),
+    ].iter().copied() {
+        let (program, syntax) = Parser::from(source, LexerOptions::default()).parse_with_syntax().unwrap();
+        assert_eq!(program, plain, "{}", source);
+        assert_eq!(syntax.to_string(), source);
+        let tokens = syntax.tokens();
+        let dot = tokens.iter().find(|token| token.token_type() == TokenType::Dot).unwrap();
+        let eof = tokens.iter().find(|token| token.token_type() == TokenType::Eof).unwrap();
+        let trivia: Vec<_> = dot.token().trailing_trivia().iter().chain(eof.token().leading_trivia()).collect();
+        assert_eq!(trivia.len(), 1, "{}", source);
+        assert_eq!(trivia[0].kind(), TriviaKind::InactiveCode, "{}", source);
+        assert_eq!(trivia[0].text(), rest, "{}", source);
+    }
+}
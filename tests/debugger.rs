@@ -0,0 +1,271 @@
+use pascal_interpreter::{
+    debugger::{console::Console, DebugHandler, Debugger, Pause, Resume, StopReason},
+    error::RuntimeError,
+    literal::{Literal, LiteralType},
+    symbols::Type,
+    Engine, Outcome, RunOptions,
+};
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+const PROGRAM: &str = "program Main;
+var x, y : integer;
+procedure Inc(a : integer);
+var b : integer;
+begin
+  b := a + 1;
+  b := b * 2
+end;
+begin
+  x := 1;
+  Inc(x);
+  y := x + 1;
+  x := y * 3
+end.";
+
+// resumes as told, then with the last resume, and remembers where it stopped
+struct Script {
+    resumes: VecDeque<Resume>,
+    stops: Vec<(u32, StopReason)>,
+}
+
+impl DebugHandler for Script {
+    fn paused(&mut self, pause: &mut Pause<'_>) -> Resume {
+        self.stops.push((pause.position().line, pause.reason().clone()));
+        match self.resumes.len() {
+            0 => Resume::Continue,
+            1 => self.resumes[0],
+            _ => self.resumes.pop_front().unwrap()
+        }
+    }
+}
+
+fn debugger(resumes: &[Resume]) -> Debugger<Script> {
+    Debugger::new(Script { resumes: resumes.iter().copied().collect(), stops: Vec::new() })
+}
+
+fn run<H: DebugHandler + 'static>(debugger: &mut Debugger<H>, start: Resume) -> (Engine, Outcome) {
+    let mut engine = Engine::new();
+    let outcome = debugger.run(&mut engine, PROGRAM, &RunOptions::default(), start);
+    (engine, outcome)
+}
+
+fn lines(debugger: &mut Debugger<Script>) -> Vec<u32> {
+    debugger.handler_mut().stops.iter().map(|(line, _)| *line).collect()
+}
+
+#[test]
+fn stepping_in_stops_at_every_statement() {
+    let mut debugger = debugger(&[Resume::StepIn]);
+    let (engine, outcome) = run(&mut debugger, Resume::StepIn);
+    assert!(outcome.is_completed(), "{:?}", outcome);
+    assert_eq!(lines(&mut debugger), [10, 11, 6, 7, 12, 13]);
+    assert_eq!(engine.get_global::<i64>("x"), Some(6));
+}
+
+#[test]
+fn stepping_over_skips_the_statements_of_calls() {
+    let mut debugger = debugger(&[Resume::StepOver]);
+    run(&mut debugger, Resume::StepIn);
+    assert_eq!(lines(&mut debugger), [10, 11, 12, 13]);
+}
+
+#[test]
+fn stepping_out_stops_in_the_caller() {
+    let mut debugger = debugger(&[Resume::StepIn, Resume::StepIn, Resume::StepOut, Resume::Continue]);
+    run(&mut debugger, Resume::StepIn);
+    assert_eq!(lines(&mut debugger), [10, 11, 6, 12]);
+}
+
+#[test]
+fn continuing_stops_at_breakpoints() {
+    let mut debugger = debugger(&[Resume::Continue]);
+    let id = debugger.breakpoints_mut().add(12, None).unwrap();
+    let (_, outcome) = run(&mut debugger, Resume::Continue);
+    assert!(outcome.is_completed(), "{:?}", outcome);
+    assert_eq!(debugger.handler_mut().stops, [(12, StopReason::Breakpoint(id))]);
+    assert_eq!(debugger.breakpoints_mut().iter().map(|breakpoint| breakpoint.hits()).collect::<Vec<_>>(), [1]);
+}
+
+#[test]
+fn breakpoints_stop_only_where_their_condition_holds() {
+    let mut debugger = debugger(&[Resume::Continue]);
+    debugger.breakpoints_mut().add(6, Some("a > 1")).unwrap();
+    let id = debugger.breakpoints_mut().add(7, Some("b = 2")).unwrap();
+    run(&mut debugger, Resume::Continue);
+    assert_eq!(debugger.handler_mut().stops, [(7, StopReason::Breakpoint(id))]);
+}
+
+#[test]
+fn failing_conditions_stop_the_run() {
+    let mut debugger = debugger(&[Resume::Continue]);
+    let id = debugger.breakpoints_mut().add(6, Some("b = 1")).unwrap();
+    run(&mut debugger, Resume::Continue);
+    let failed = StopReason::ConditionFailed(id, RuntimeError::UndefinedVariable("b".to_owned()));
+    assert_eq!(debugger.handler_mut().stops, [(6, failed)]);
+}
+
+#[test]
+fn conditions_have_to_be_comparisons() {
+    let mut debugger = debugger(&[]);
+    assert_eq!(debugger.breakpoints_mut().add(6, Some("a + 1")), Err(RuntimeError::NotACondition("a + 1".to_owned())));
+}
+
+#[test]
+fn pauses_show_the_call_stack() {
+    let mut debugger = Debugger::new(|pause: &mut Pause<'_>| {
+        assert_eq!(pause.depth(), 2);
+        assert_eq!(pause.variable(0, "b"), Some(&Literal::from_int(2)));
+        assert_eq!(pause.variable(1, "x"), Some(&Literal::from_int(1)));
+        assert_eq!(pause.variable(0, "x"), None);
+        assert_eq!(pause.evaluate(0, "b * 10"), Ok(Some(Literal::from_int(20))));
+        assert_eq!(pause.evaluate(0, "b > a"), Ok(Some(Literal::from_bool(true))));
+        assert_eq!(pause.evaluate(1, "y"), Ok(None));
+        let frames: Vec<String> = pause.backtrace().frames.iter().map(|frame| frame.name.clone()).collect();
+        assert_eq!(frames, ["Inc", "Main"]);
+        Resume::Continue
+    });
+    debugger.breakpoints_mut().add(7, None).unwrap();
+    let (_, outcome) = run(&mut debugger, Resume::Continue);
+    assert!(outcome.is_completed(), "{:?}", outcome);
+}
+
+#[test]
+fn variables_can_be_changed_at_a_stop() {
+    let mut debugger = Debugger::new(|pause: &mut Pause<'_>| {
+        pause.set_variable(0, "x", Literal::from_int(10)).unwrap();
+        let err = pause.set_variable(0, "x", Literal::from_str("ten".to_owned()));
+        assert_eq!(err, Err(RuntimeError::VariableTypeMismatch("x".to_owned(), Type::Integer, LiteralType::Text)));
+        Resume::Continue
+    });
+    debugger.breakpoints_mut().add(12, None).unwrap();
+    let (engine, _) = run(&mut debugger, Resume::Continue);
+    assert_eq!(engine.get_global::<i64>("y"), Some(11));
+    assert_eq!(engine.get_global::<i64>("x"), Some(33));
+}
+
+#[test]
+fn changed_values_must_fit_the_declared_type() {
+    let mut debugger = Debugger::new(|pause: &mut Pause<'_>| {
+        if pause.position().line == 7 {
+            assert_eq!(pause.variable(0, "b"), Some(&Literal::from_int(6)));
+            return Resume::Continue
+        }
+        // b is not set before line 6, but is still an integer
+        assert_eq!(pause.variable(0, "b"), None);
+        let err = pause.set_variable(0, "b", Literal::from_float(1.5));
+        assert_eq!(err, Err(RuntimeError::VariableTypeMismatch("b".to_owned(), Type::Integer, LiteralType::Float)));
+        let err = pause.set_variable(0, "A", Literal::from_str("one".to_owned()));
+        assert_eq!(err, Err(RuntimeError::VariableTypeMismatch("a".to_owned(), Type::Integer, LiteralType::Text)));
+        let err = pause.set_variable(1, "y", Literal::from_bool(true));
+        assert_eq!(err, Err(RuntimeError::VariableTypeMismatch("y".to_owned(), Type::Integer, LiteralType::Bool)));
+        pause.set_variable(0, "a", Literal::from_int(5)).unwrap();
+        Resume::Continue
+    });
+    debugger.breakpoints_mut().add(6, None).unwrap();
+    debugger.breakpoints_mut().add(7, None).unwrap();
+    let (_, outcome) = run(&mut debugger, Resume::Continue);
+    assert!(outcome.is_completed(), "{:?}", outcome);
+}
+
+#[test]
+fn only_declared_variables_can_be_changed() {
+    let mut debugger = Debugger::new(|pause: &mut Pause<'_>| {
+        // the globals belong to the frame of the program, not to the one of the call
+        let err = pause.set_variable(0, "x", Literal::from_int(1));
+        assert_eq!(err, Err(RuntimeError::UndefinedVariable("x".to_owned())));
+        let err = pause.set_variable(1, "Z", Literal::from_int(1));
+        assert_eq!(err, Err(RuntimeError::UndefinedVariable("z".to_owned())));
+        assert_eq!(pause.variable(0, "x"), None);
+        assert_eq!(pause.variable(1, "z"), None);
+        Resume::Continue
+    });
+    debugger.breakpoints_mut().add(6, None).unwrap();
+    let (_, outcome) = run(&mut debugger, Resume::Continue);
+    assert!(outcome.is_completed(), "{:?}", outcome);
+}
+
+#[test]
+fn watches_are_evaluated_at_every_stop() {
+    let values = Rc::new(RefCell::new(Vec::new()));
+    let seen = Rc::clone(&values);
+    let mut debugger = Debugger::new(move |pause: &mut Pause<'_>| {
+        let watched: Vec<String> = pause.watch_values().into_iter()
+            .map(|(text, value)| format!("{} = {:?}", text, value))
+            .collect();
+        seen.borrow_mut().push(watched.join(", "));
+        Resume::StepOver
+    });
+    debugger.watches_mut().add("x + 1").unwrap();
+    run(&mut debugger, Resume::StepIn);
+    let int = |value| format!("x + 1 = Ok(Some({:?}))", Literal::from_int(value));
+    let unset = String::from("x + 1 = Err(UndefinedVariable(\"x\"))");
+    assert_eq!(*values.borrow(), [unset, int(2), int(2), int(2)]);
+}
+
+#[test]
+fn quitting_stops_the_run() {
+    let mut debugger = debugger(&[Resume::Quit]);
+    let (engine, outcome) = run(&mut debugger, Resume::StepIn);
+    match outcome {
+        Outcome::RuntimeError(RuntimeError::WithBacktrace(cause, _)) => assert_eq!(*cause, RuntimeError::Stopped),
+        outcome => panic!("{:?}", outcome)
+    }
+    assert_eq!(lines(&mut debugger), [10]);
+    assert_eq!(engine.get_global::<i64>("x"), None);
+}
+
+// output of the console, kept where the test can read it after the run
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn the_console_reads_commands_until_the_run_resumes() {
+    let output = Shared::default();
+    let commands = "b 7 if b = 2\nwatch a\nc\np b * 10\nframe 1\nlocals\nset y := 5\nbt\nc\n";
+    let console = Console::new(PROGRAM, commands.as_bytes(), output.clone());
+    let mut debugger = Debugger::new(console);
+    let (engine, outcome) = run(&mut debugger, Resume::StepIn);
+    assert!(outcome.is_completed(), "{:?}", outcome);
+    assert_eq!(engine.get_global::<i64>("y"), Some(2));
+    let text = String::from_utf8(output.0.borrow().clone()).unwrap();
+    let expected = [
+        "Main at 10:3",
+        "  10    x := 1;",
+        "(debug) breakpoint 1 at line 7",
+        "(debug) watch 1: a",
+        "(debug) breakpoint 1, Inc at 7:3",
+        "   7    b := b * 2",
+        "  watch 1: a = 1",
+        "(debug) b * 10 = 20",
+        "(debug) frame 1: Main",
+        "(debug) x = 1",
+        "(debug) y = 5",
+    ];
+    assert!(text.starts_with(&expected.join("\n")), "{}", text);
+    assert!(text.contains("(debug)   at Inc(a = 1) called at 11:3\n"), "{}", text);
+}
+
+#[test]
+fn the_console_quits_when_its_input_ends() {
+    let console = Console::new(PROGRAM, "s\n".as_bytes(), Shared::default());
+    let mut debugger = Debugger::new(console);
+    let (_, outcome) = run(&mut debugger, Resume::StepIn);
+    match outcome {
+        Outcome::RuntimeError(RuntimeError::WithBacktrace(cause, _)) => assert_eq!(*cause, RuntimeError::Stopped),
+        outcome => panic!("{:?}", outcome)
+    }
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use pascal_interpreter::{
+    format::{self, BeginPlacement, FormatOptions, KeywordCase},
+    parser::Parser,
+    LexerOptions,
+};
+
+use common::PROGRAMS;
+
+fn styles() -> Vec<FormatOptions> {
+    let narrow = FormatOptions { max_line_length: 30, ..FormatOptions::default() };
+    let gnu = FormatOptions {
+        keyword_case: KeywordCase::Upper,
+        indent_width: 4,
+        begin_placement: BeginPlacement::Indented,
+        ..FormatOptions::default()
+    };
+    let preserve = FormatOptions { keyword_case: KeywordCase::Preserve, ..FormatOptions::default() };
+    vec![FormatOptions::default(), narrow, gnu, preserve]
+}
+
+fn format(source: &str, options: &FormatOptions) -> String {
+    let (_, syntax) = Parser::from(source, LexerOptions::default()).parse_with_syntax().unwrap();
+    format::format(&syntax, options)
+}
+
+#[test]
+fn formatting_twice_changes_nothing() {
+    for options in styles() {
+        for (name, source) in PROGRAMS.iter() {
+            let once = format(source, &options);
+            let twice = format(&once, &options);
+            assert_eq!(twice, once, "{} with {:?}", name, options);
+        }
+    }
+}
+
+#[test]
+fn formatting_keeps_the_ast() {
+    let parse = |source: &str| Parser::from(source, LexerOptions::default()).parse().unwrap();
+    for options in styles() {
+        for (name, source) in PROGRAMS.iter() {
+            let once = format(source, &options);
+            let twice = format(&once, &options);
+            assert_eq!(parse(&once), parse(source), "{} with {:?}", name, options);
+            assert_eq!(parse(&twice), parse(source), "{} with {:?}", name, options);
+        }
+    }
+}
+
+#[test]
+fn comments_and_inactive_code_are_kept() {
+    for (name, source) in PROGRAMS.iter() {
+        let formatted = format(source, &FormatOptions::default());
+        for line in source.lines().filter(|line| line.trim_start().starts_with('{')) {
+            assert!(formatted.contains(line.trim()), "{}: {} is lost", name, line.trim());
+        }
+    }
+    let (_, source) = PROGRAMS.iter().find(|(name, _)| *name == "directives").unwrap();
+    let formatted = format(source, &FormatOptions::default());
+    assert!(formatted.contains("this is not even Pascal (* at all *)"));
+    assert!(formatted.ends_with("anything goes here\n"));
+}
+
+#[test]
+fn programs_from_an_ast_are_formatted_like_their_source() {
+    let parse = |source: &str| Parser::from(source, LexerOptions::default()).parse().unwrap();
+    let options = FormatOptions::default();
+    let (_, source) = PROGRAMS.iter().find(|(name, _)| *name == "nested").unwrap();
+    let formatted = format::format_program(&parse(source), &options).unwrap();
+    assert_eq!(parse(&formatted), parse(source));
+    assert_eq!(format(&formatted, &options), formatted);
+}
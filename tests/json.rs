@@ -0,0 +1,75 @@
+#![cfg(feature = "json")]
+
+mod common;
+
+use pascal_interpreter::{
+    ast::json::{from_json, to_json, VERSION},
+    error::RuntimeError,
+    parser::Parser,
+    semantic_analyzer::SemanticAnalyzer,
+    LexerOptions,
+};
+
+use common::PROGRAMS;
+
+fn parse(source: &str) -> pascal_interpreter::ast::nodes::ProgramNode {
+    Parser::from(source, LexerOptions::default()).parse().unwrap()
+}
+
+fn is_invalid(json: &str) -> bool {
+    matches!(from_json(json), Err(RuntimeError::InvalidAstJson(_)))
+}
+
+#[test]
+fn programs_round_trip() {
+    for (name, source) in PROGRAMS.iter() {
+        let program = parse(source);
+        let json = to_json(&program).unwrap();
+        let loaded = from_json(&json).unwrap();
+        assert_eq!(to_json(&loaded).unwrap(), json, "{}", name);
+        // switches other than overflow checks are not part of the schema, and the directives
+        // program turns range checks on
+        if *name != "directives" {
+            assert_eq!(loaded, program, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn loaded_programs_analyze() {
+    for (name, source) in PROGRAMS.iter() {
+        let mut loaded = from_json(&to_json(&parse(source)).unwrap()).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        assert!(analyzer.analyze(&mut loaded).is_ok(), "{}", name);
+    }
+}
+
+#[test]
+fn analysis_results_are_not_written() {
+    let mut program = parse("program Main; procedure P(a : integer); begin end; begin P(1) end.");
+    SemanticAnalyzer::new().analyze(&mut program).unwrap();
+    let json = to_json(&program).unwrap();
+    assert!(!json.contains("proc_symbol") && !json.contains("span") && !json.contains("trivia"));
+    assert!(json.contains(&format!("\"version\": {}", VERSION)));
+}
+
+#[test]
+fn malformed_trees_are_rejected() {
+    let program = |block: &str| format!(r#"{{"version": 1, "program": {{"name": "Main", "block": {}}}}}"#, block);
+    let valid = program(r#"{"declarations": [{"kind": "var", "name": "x", "type": "integer"}],
+        "statements": [{"kind": "assignment", "target": "x", "value": {"kind": "number", "value": {"integer": 1}}}]}"#);
+    assert!(from_json(&valid).is_ok());
+
+    assert!(is_invalid(&valid.replace("\"version\": 1", "\"version\": 2")));
+    assert!(is_invalid(&valid.replace("\"integer\"}", "\"boolean\"}")));
+    assert!(is_invalid(&valid.replace("\"target\": \"x\"", "\"target\": \"begin\"")));
+    assert!(is_invalid(&valid.replace("\"target\": \"x\"", "\"target\": \"x y\"")));
+    assert!(is_invalid(&valid.replace("\"target\": \"x\"", "\"target\": \"\"")));
+    assert!(is_invalid(&valid.replace("\"kind\": \"number\"", "\"kind\": \"string\"")));
+    assert!(is_invalid(&valid.replace("\"name\": \"x\"", "\"name\": \"x\", \"proc_symbol\": null")));
+    assert!(is_invalid(&program(r#"{"declarations": [], "statements": [{"kind": "empty"}], "extra": 1}"#)));
+    let operator = program(r#"{"declarations": [], "statements": [{"kind": "call", "name": "P", "arguments": [
+        {"kind": "binary", "op": "and", "left": {"kind": "variable", "name": "a"}, "right": {"kind": "variable", "name": "b"}}]}]}"#);
+    assert!(is_invalid(&operator));
+    assert!(!is_invalid(&operator.replace("\"and\"", "\"div\"")));
+}
@@ -0,0 +1,211 @@
+use pascal_interpreter::{
+    directive::Switch,
+    error::RuntimeError,
+    literal::Literal,
+    token::{Position, TokenType, TriviaKind},
+    tokenize, tokenize_with_options, LexerOptions,
+};
+
+fn types(source: &str) -> Vec<TokenType> {
+    tokenize(source).unwrap().iter().map(|token| token.token_type()).collect()
+}
+
+#[test]
+fn keywords_match_in_any_case() {
+    assert_eq!(types("begin BEGIN Begin bEgIn"), vec![TokenType::Begin; 4].into_iter().chain(Some(TokenType::Eof)).collect::<Vec<_>>());
+    assert_eq!(types("and Xor with div Mod"), vec![
+        TokenType::And, TokenType::Xor, TokenType::With, TokenType::IntegerDivision, TokenType::Mod, TokenType::Eof
+    ]);
+}
+
+#[test]
+fn words_near_keywords_are_identifiers() {
+    for word in ["an", "andy", "beginning", "x", "zz", "withs", "Ändern"] {
+        assert_eq!(types(word), vec![TokenType::Identifier, TokenType::Eof], "{}", word);
+    }
+}
+
+#[test]
+fn operators_take_the_longest_match() {
+    use TokenType::*;
+    assert_eq!(types("x := 1"), vec![Identifier, Assignment, IntegerConst, Eof]);
+    assert_eq!(types("x : = 1"), vec![Identifier, Colon, Equal, IntegerConst, Eof]);
+    assert_eq!(types("<> <= >= < > = .."), vec![NotEqual, LessEqual, GreaterEqual, Less, Greater, Equal, Range, Eof]);
+    assert_eq!(types("1..9"), vec![IntegerConst, Range, IntegerConst, Eof]);
+    assert_eq!(types("a<=b>=c<>d"), vec![Identifier, LessEqual, Identifier, GreaterEqual, Identifier, NotEqual, Identifier, Eof]);
+    assert_eq!(types("x:=(y)"), vec![Identifier, Assignment, Lparen, Identifier, Rparen, Eof]);
+    assert_eq!(types("end."), vec![End, Dot, Eof]);
+}
+
+fn comments(source: &str, options: LexerOptions) -> Vec<String> {
+    tokenize_with_options(source, options).unwrap().iter()
+        .flat_map(|token| token.leading_trivia().iter().chain(token.trailing_trivia()))
+        .filter(|trivia| trivia.kind() == TriviaKind::Comment)
+        .map(|trivia| trivia.text().to_owned())
+        .collect()
+}
+
+#[test]
+fn all_comment_forms_are_trivia() {
+    use TokenType::*;
+    let source = "x { braces } y (* parens *) z // to the end of the line\nw";
+    assert_eq!(types(source), vec![Identifier, Identifier, Identifier, Identifier, Eof]);
+    assert_eq!(comments(source, LexerOptions::default()), vec!["{ braces }", "(* parens *)", "// to the end of the line"]);
+    assert_eq!(types("x (* { *) y { (* } z"), vec![Identifier, Identifier, Identifier, Eof]);
+    assert_eq!(types("x := 1 // comment"), vec![Identifier, Assignment, IntegerConst, Eof]);
+}
+
+#[test]
+fn comments_nest_only_when_asked() {
+    let source = "x { outer { inner } still outer } y (* a (* b *) c *) z";
+    let nested = LexerOptions { nested_comments: true, ..LexerOptions::default() };
+    assert_eq!(comments(source, nested), vec!["{ outer { inner } still outer }", "(* a (* b *) c *)"]);
+    assert!(tokenize(source).is_err());
+    assert_eq!(comments("x { a { b } y", LexerOptions::default()), vec!["{ a { b }"]);
+}
+
+#[test]
+fn unterminated_comments_report_where_they_open() {
+    let at = |line, col| Err(RuntimeError::UnterminatedComment(Position { line, col }));
+    assert_eq!(tokenize("x := 1;\n  { never closed"), at(2, 3));
+    assert_eq!(tokenize("x (* never\nclosed *"), at(1, 3));
+    let nested = LexerOptions { nested_comments: true, ..LexerOptions::default() };
+    assert_eq!(tokenize_with_options("{ a { b }\n}\n  { c { d }", nested), at(3, 3));
+}
+
+#[test]
+fn unimplemented_switches_are_accepted_but_only_overflow_checks_apply() {
+    let tokens = tokenize("{$R+,I-,B+,Q-} x").unwrap();
+    let switches = tokens[0].switches();
+    assert!(switches.is_enabled(Switch::RangeChecks) && !switches.is_enabled(Switch::IoChecks));
+    assert!(!switches.is_enabled(Switch::OverflowChecks));
+    let implemented: Vec<Switch> = [Switch::RangeChecks, Switch::OverflowChecks, Switch::IoChecks, Switch::CompleteBooleanEval]
+        .iter()
+        .copied()
+        .filter(|switch| switch.is_implemented())
+        .collect();
+    assert_eq!(implemented, vec![Switch::OverflowChecks]);
+}
+
+fn identifiers(source: &str, options: LexerOptions) -> Vec<String> {
+    tokenize_with_options(source, options).unwrap().iter()
+        .filter(|token| token.token_type() == TokenType::Identifier)
+        .map(|token| token.text().to_owned())
+        .collect()
+}
+
+#[test]
+fn conditionals_pick_the_compiled_code() {
+    let source = "{$IFDEF DEBUG} a {$ELSE} b {$ENDIF} {$IFNDEF DEBUG} c {$ENDIF}";
+    assert_eq!(identifiers(source, LexerOptions::default()), vec!["b", "c"]);
+    let debug = LexerOptions { defines: ["DEBUG".to_owned()].iter().cloned().collect(), ..LexerOptions::default() };
+    assert_eq!(identifiers(source, debug), vec!["a"]);
+    let source = "{$DEFINE X} {$IFDEF x} a {$ENDIF} {$UNDEF X} {$IFDEF X} b {$ENDIF}";
+    assert_eq!(identifiers(source, LexerOptions::default()), vec!["a"]);
+    let source = "{$IFDEF NEVER} {$DEFINE X} {$IFDEF X} a {$ENDIF} {$ENDIF} {$IFDEF X} b {$ELSE} c {$ENDIF}";
+    assert_eq!(identifiers(source, LexerOptions::default()), vec!["c"]);
+}
+
+#[test]
+fn unbalanced_conditionals_are_errors() {
+    assert_eq!(tokenize("x\n {$ENDIF}"), Err(RuntimeError::UnbalancedConditional(Position { line: 2, col: 2 })));
+    assert_eq!(tokenize("{$IFDEF A} x {$ELSE} y {$ELSE} z {$ENDIF}"), Err(RuntimeError::UnbalancedConditional(Position { line: 1, col: 24 })));
+    assert_eq!(tokenize("x {$IFDEF A} y"), Err(RuntimeError::UnterminatedConditional(Position { line: 1, col: 3 })));
+}
+
+fn literal(source: &str) -> Literal {
+    let tokens = tokenize(source).unwrap();
+    assert_eq!(tokens.len(), 2, "{} is not one token", source);
+    tokens[0].literal().clone()
+}
+
+#[test]
+fn integers_are_read_in_every_radix() {
+    assert_eq!(literal("255"), Literal::from_int(255));
+    assert_eq!(literal("$FF"), Literal::from_int(255));
+    assert_eq!(literal("$ff"), Literal::from_int(255));
+    assert_eq!(literal("%1111_1111"), Literal::from_int(255));
+    assert_eq!(literal("&377"), Literal::from_int(255));
+    assert_eq!(literal("1_000_000"), Literal::from_int(1_000_000));
+    assert_eq!(literal("$7FFF_FFFF_FFFF_FFFF"), Literal::from_int(i64::MAX));
+}
+
+#[test]
+fn reals_are_read_with_fractions_and_exponents() {
+    assert_eq!(literal("1.5"), Literal::from_float(1.5));
+    assert_eq!(literal("1.5E-3"), Literal::from_float(0.0015));
+    assert_eq!(literal("2e3"), Literal::from_float(2000.0));
+    assert_eq!(literal("2E+3"), Literal::from_float(2000.0));
+    assert_eq!(literal("1_000.000_5"), Literal::from_float(1000.0005));
+    assert_eq!(tokenize("1.5e3").unwrap()[0].text(), "1.5e3");
+    assert_eq!(types("1..5"), vec![TokenType::IntegerConst, TokenType::Range, TokenType::IntegerConst, TokenType::Eof]);
+}
+
+#[test]
+fn out_of_range_numbers_are_errors() {
+    let at = |text: &str, line, col| Err(RuntimeError::NumberOutOfRange(text.to_owned(), Position { line, col }));
+    assert_eq!(tokenize("x := 9223372036854775808"), at("9223372036854775808", 1, 6));
+    assert_eq!(tokenize("x :=\n  $1_0000_0000_0000_0000"), at("$1_0000_0000_0000_0000", 2, 3));
+    let binary = format!("%{}", "1".repeat(65));
+    assert_eq!(tokenize(&binary), at(&binary, 1, 1));
+    assert_eq!(tokenize("1e400"), at("1e400", 1, 1));
+}
+
+#[test]
+fn malformed_numbers_are_errors_or_separate_tokens() {
+    let unexpected = |ch, line, col| Err(RuntimeError::UnexpectedChar(ch, Position { line, col }));
+    assert_eq!(tokenize("x := $"), unexpected('$', 1, 6));
+    assert_eq!(tokenize("$G"), unexpected('$', 1, 1));
+    assert_eq!(tokenize("1__0"), unexpected('_', 1, 2));
+    assert_eq!(tokenize("1_"), unexpected('_', 1, 2));
+    assert_eq!(tokenize("x :=\n 'a'"), unexpected('\'', 2, 2));
+    // an `e` without digits after it is not an exponent
+    assert_eq!(types("1e"), vec![TokenType::IntegerConst, TokenType::Identifier, TokenType::Eof]);
+    assert_eq!(types("1e+"), vec![TokenType::IntegerConst, TokenType::Identifier, TokenType::Plus, TokenType::Eof]);
+}
+
+#[test]
+fn percent_is_a_binary_prefix_only_where_an_operand_is_expected() {
+    use TokenType::*;
+    assert_eq!(types("x%10"), vec![Identifier, Modulus, IntegerConst, Eof]);
+    assert_eq!(types("(x)%11"), vec![Lparen, Identifier, Rparen, Modulus, IntegerConst, Eof]);
+    assert_eq!(types("10%11"), vec![IntegerConst, Modulus, IntegerConst, Eof]);
+    assert_eq!(types("x := %101"), vec![Identifier, Assignment, IntegerConst, Eof]);
+    assert_eq!(types("x % %101")[2], IntegerConst);
+    let tokens = tokenize("x := (%101)").unwrap();
+    assert_eq!(tokens[3].literal().to_int(), Some(5));
+}
+
+// the source as given back by the tokens, their trivia included
+fn rebuild(tokens: &[pascal_interpreter::token::Token]) -> String {
+    let mut text = String::new();
+    for token in tokens {
+        token.leading_trivia().iter().for_each(|trivia| text.push_str(trivia.text()));
+        text.push_str(token.text());
+        token.trailing_trivia().iter().for_each(|trivia| text.push_str(trivia.text()));
+    }
+    text
+}
+
+#[test]
+fn excluded_code_is_one_piece_of_trivia() {
+    let source = "x {$IFDEF NEVER}\n  y := 1; { comment }\n  z (* more *)\n{$ELSE} w {$ENDIF} v";
+    let tokens = tokenize(source).unwrap();
+    assert_eq!(rebuild(&tokens), source);
+    let inactive: Vec<&str> = tokens.iter()
+        .flat_map(|token| token.leading_trivia().iter().chain(token.trailing_trivia()))
+        .filter(|trivia| trivia.kind() == TriviaKind::InactiveCode)
+        .map(|trivia| trivia.text())
+        .collect();
+    assert_eq!(inactive, vec!["\n  y := 1; { comment }\n  z (* more *)\n"]);
+    let identifiers: Vec<&str> = tokens.iter().map(|token| token.text()).collect();
+    assert_eq!(identifiers, vec!["x", "w", "v", ""]);
+}
+
+#[test]
+fn excluded_code_at_the_end_is_kept() {
+    let source = "x {$IFDEF NEVER} y z";
+    assert!(tokenize(source).is_err());
+    let source = "x {$IFDEF NEVER} y z {$ENDIF}";
+    assert_eq!(rebuild(&tokenize(source).unwrap()), source);
+}
@@ -0,0 +1,129 @@
+use pascal_interpreter::{
+    error::RuntimeError,
+    limits::Limit,
+    Engine, Outcome, RunOptions,
+};
+
+use std::time::Duration;
+
+const LOOP: &str = "program Main; var i : integer;
+procedure Spin(n : integer);
+begin
+  Spin(n + 1)
+end;
+begin
+  i := 0;
+  Spin(i)
+end.";
+
+const SHORT: &str = "program Main; var i : integer; begin i := 1; i := i + 1 end.";
+
+fn limit(outcome: Outcome) -> Limit {
+    match outcome {
+        Outcome::RuntimeError(RuntimeError::ResourceExhausted(limit, _)) => limit,
+        outcome => panic!("{:?}", outcome)
+    }
+}
+
+#[test]
+fn runs_stop_after_their_step_budget() {
+    let mut engine = Engine::new();
+    let options = RunOptions { max_steps: Some(100), ..RunOptions::default() };
+    assert_eq!(limit(engine.run_source(LOOP, &options)), Limit::Steps(100));
+    assert_eq!(engine.usage().steps, 101);
+}
+
+#[test]
+fn budgets_are_counted_per_run() {
+    let mut engine = Engine::new();
+    let options = RunOptions { max_steps: Some(20), ..RunOptions::default() };
+    for _ in 0..3 {
+        let outcome = engine.run_source(SHORT, &options);
+        assert!(outcome.is_completed(), "{:?}", outcome);
+    }
+    assert!(engine.usage().steps <= 20);
+}
+
+#[test]
+fn runs_stop_at_their_deadline() {
+    let mut engine = Engine::new();
+    let options = RunOptions { timeout: Some(Duration::from_millis(0)), ..RunOptions::default() };
+    assert_eq!(limit(engine.run_source(LOOP, &options)), Limit::Deadline);
+}
+
+#[test]
+fn cancelling_stops_the_next_run_only() {
+    let mut engine = Engine::new();
+    engine.cancel_handle().cancel();
+    assert_eq!(limit(engine.run_source(SHORT, &RunOptions::default())), Limit::Cancelled);
+    let outcome = engine.run_source(SHORT, &RunOptions::default());
+    assert!(outcome.is_completed(), "{:?}", outcome);
+    assert_eq!(engine.get_global::<i64>("i"), Some(2));
+}
+
+#[test]
+fn cancelling_stops_the_run_in_progress() {
+    let mut engine = Engine::new();
+    let cancel = engine.cancel_handle();
+    engine.register_procedure("Stop", &[], move |_| {
+        cancel.cancel();
+        Ok(())
+    });
+    let source = "program Main; var i : integer; begin i := 1; Stop(); i := 2 end.";
+    assert_eq!(limit(engine.run_source(source, &RunOptions::default())), Limit::Cancelled);
+    assert_eq!(engine.get_global::<i64>("i"), Some(1));
+}
+
+fn with_text(bytes: usize) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_global("s", "x".repeat(bytes).as_str());
+    engine
+}
+
+#[test]
+fn runs_stop_at_their_record_cap() {
+    let mut engine = Engine::new();
+    let options = RunOptions { max_records: Some(10), ..RunOptions::default() };
+    assert_eq!(limit(engine.run_source(LOOP, &options)), Limit::ActivationRecords(10));
+    assert_eq!(engine.usage().peak_records, 10);
+}
+
+#[test]
+fn runs_stop_at_their_value_cap() {
+    let source = "program Main; var s : string; begin s := s + s; s := s + s; s := s + s end.";
+    let mut engine = with_text(100);
+    let options = RunOptions { max_value_bytes: Some(500), ..RunOptions::default() };
+    assert_eq!(limit(engine.run_source(source, &options)), Limit::ValueBytes(500));
+    // the second concatenation does not fit next to the 200 bytes `s` holds by then
+    assert_eq!(engine.get_global::<String>("s").map(|s| s.len()), Some(200));
+}
+
+#[test]
+fn arguments_count_against_the_value_cap() {
+    let source = "program Main; var s : string;
+procedure Keep(a : string);
+begin
+end;
+begin
+  Keep(s)
+end.";
+    let mut engine = with_text(100);
+    let outcome = engine.run_source(source, &RunOptions::default());
+    assert!(outcome.is_completed(), "{:?}", outcome);
+    // the global, the parameter and the copy of the argument kept for backtraces
+    assert_eq!(engine.usage().peak_value_bytes, 300);
+    let options = RunOptions { max_value_bytes: Some(250), ..RunOptions::default() };
+    assert_eq!(limit(engine.run_source(source, &options)), Limit::ValueBytes(250));
+}
+
+#[test]
+fn memory_is_counted_per_run() {
+    let source = "program Main; var s : string; begin s := s + s end.";
+    let mut engine = with_text(100);
+    let options = RunOptions { max_value_bytes: Some(300), ..RunOptions::default() };
+    for _ in 0..3 {
+        let outcome = engine.run_source(source, &options);
+        assert!(outcome.is_completed(), "{:?}", outcome);
+        assert_eq!(engine.usage().peak_value_bytes, 200);
+    }
+}
@@ -0,0 +1,32 @@
+use log::LevelFilter;
+
+use pascal_interpreter::logging::{self, Filter};
+
+#[test]
+fn a_bare_level_applies_to_every_target() {
+    let filter = Filter::parse("debug").unwrap();
+    for target in [logging::LEXER, logging::PARSER, logging::SEMA, logging::EXEC].iter() {
+        assert_eq!(filter.level_for(target), LevelFilter::Debug, "{}", target);
+    }
+    assert_eq!(Filter::parse("").unwrap().level_for(logging::EXEC), LevelFilter::Off);
+}
+
+#[test]
+fn targets_take_their_own_level() {
+    let filter = Filter::parse("warn, exec=trace,sema = info").unwrap();
+    assert_eq!(filter.level_for(logging::EXEC), LevelFilter::Trace);
+    assert_eq!(filter.level_for(logging::SEMA), LevelFilter::Info);
+    assert_eq!(filter.level_for(logging::LEXER), LevelFilter::Warn);
+
+    // without a bare level the other targets are off, and a later pair wins
+    let filter = Filter::parse("parser=debug,parser=error").unwrap();
+    assert_eq!(filter.level_for(logging::PARSER), LevelFilter::Error);
+    assert_eq!(filter.level_for(logging::LEXER), LevelFilter::Off);
+}
+
+#[test]
+fn invalid_specs_are_refused() {
+    assert_eq!(Filter::parse("loud"), Err(String::from("unknown log level loud")));
+    assert_eq!(Filter::parse("exec=loud"), Err(String::from("unknown log level loud")));
+    assert_eq!(Filter::parse("warn,vm=trace"), Err(String::from("unknown log target vm, expected one of lexer, parser, sema, exec")));
+}
@@ -0,0 +1,157 @@
+use pascal_interpreter::{
+    ast::nodes::{Expr, ProcedureCallNode, Stmt},
+    error::RuntimeError,
+    literal::Literal,
+    observer::{Observer, Tracer},
+    record::{ARCallStack, ActivationRecord},
+    symbols::Type,
+    token::Span,
+    Engine, RunOptions,
+};
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+const SOURCE: &str = "program Main;
+var x : integer;
+procedure Double(n : integer);
+var y : integer;
+begin
+  y := n * 2
+end;
+begin
+  x := Answer() - 1;
+  Double(x)
+end.";
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.register_fn("Answer", &[], Type::Integer, |_| Ok(Literal::from_int(42)));
+    engine
+}
+
+// the events of a run, each as a line with the source text it is about
+struct Recorder {
+    events: Rc<RefCell<Vec<String>>>,
+}
+
+impl Recorder {
+    fn push(&self, event: String) {
+        self.events.borrow_mut().push(event);
+    }
+}
+
+impl Observer for Recorder {
+    fn statement_entered(&mut self, _: &Stmt, span: Span, _: &mut ARCallStack) -> Result<(), RuntimeError> {
+        self.push(format!("statement {}", &SOURCE[span.start..span.end]));
+        Ok(())
+    }
+
+    fn expression_evaluated(&mut self, _: &Expr, span: Span, value: Option<&Literal>) {
+        self.push(format!("expression {} = {:?}", &SOURCE[span.start..span.end], value));
+    }
+
+    fn variable_assigned(&mut self, name: &str, _: Span, value: &Literal) {
+        self.push(format!("assigned {} = {}", name, value));
+    }
+
+    fn procedure_entered(&mut self, _: &ProcedureCallNode, record: &ActivationRecord) {
+        self.push(format!("entered {} {:?}", record.name(), record.arguments()));
+    }
+
+    fn procedure_exited(&mut self, _: &ProcedureCallNode, record: &ActivationRecord, result: Option<&Literal>) {
+        self.push(format!("exited {} {:?}", record.name(), result));
+    }
+}
+
+#[test]
+fn observers_see_the_events_in_order() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = engine();
+    engine.add_observer(Box::new(Recorder { events: events.clone() }));
+    engine.run_source(SOURCE, &RunOptions::default()).into_result().unwrap();
+    assert_eq!(*events.borrow(), [
+        "statement x := Answer() - 1",
+        "entered Answer []",
+        "exited Answer Some(Int(42))",
+        "expression Answer() = Some(Int(42))",
+        "expression 1 = Some(Int(1))",
+        "expression Answer() - 1 = Some(Int(41))",
+        "assigned x = 41",
+        "statement Double(x)",
+        "expression x = Some(Int(41))",
+        "entered Double [(\"n\", Int(41))]",
+        "statement y := n * 2",
+        "expression n = Some(Int(41))",
+        "expression 2 = Some(Int(2))",
+        "expression n * 2 = Some(Int(82))",
+        "assigned y = 82",
+        "exited Double None",
+    ]);
+}
+
+// a `Vec<u8>` the test keeps a handle to while the engine owns the tracer
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+fn trace(expressions: bool) -> String {
+    let output = Output::default();
+    let mut engine = engine();
+    engine.add_observer(Box::new(Tracer::new(SOURCE, output.clone()).with_expressions(expressions)));
+    engine.run_source(SOURCE, &RunOptions::default()).into_result().unwrap();
+    output.text()
+}
+
+#[test]
+fn tracer_prints_statements_and_calls() {
+    assert_eq!(trace(false), "\
+TRACE 9:3     x := Answer() - 1
+TRACE 9:8       -> Answer()
+TRACE           <- Answer = 42
+TRACE           x = 41
+TRACE 10:3    Double(x)
+TRACE 10:3      -> Double(n = 41)
+TRACE 6:3       y := n * 2
+TRACE             y = 82
+TRACE           <- Double
+");
+}
+
+#[test]
+fn tracer_prints_expressions_when_asked() {
+    assert_eq!(trace(true), "\
+TRACE 9:3     x := Answer() - 1
+TRACE 9:8       -> Answer()
+TRACE           <- Answer = 42
+TRACE 9:8       Answer() = 42
+TRACE 9:19      1 = 1
+TRACE 9:8       Answer() - 1 = 41
+TRACE           x = 41
+TRACE 10:3    Double(x)
+TRACE 10:10     x = 41
+TRACE 10:3      -> Double(n = 41)
+TRACE 6:3       y := n * 2
+TRACE 6:8         n = 41
+TRACE 6:12        2 = 2
+TRACE 6:8         n * 2 = 82
+TRACE             y = 82
+TRACE           <- Double
+");
+}
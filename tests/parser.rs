@@ -0,0 +1,41 @@
+use pascal_interpreter::{
+    error::RuntimeError,
+    format::{self, FormatOptions},
+    parser::Parser,
+    token::TokenType,
+    LexerOptions,
+};
+
+fn parse(source: &str) -> Result<(), RuntimeError> {
+    Parser::from(source, LexerOptions::default()).parse().map(|_| ())
+}
+
+fn reserved_word(err: RuntimeError) -> TokenType {
+    match err {
+        RuntimeError::ReservedWordAsIdentifier(token) => token.token_type(),
+        err => panic!("{:?}", err)
+    }
+}
+
+#[test]
+fn reserved_words_are_rejected_as_identifiers() {
+    let err = parse("program Main; var end : integer; begin end.").unwrap_err();
+    assert_eq!(reserved_word(err), TokenType::End);
+    let err = parse("program Main; var x : integer; begin type := 1 end.").unwrap_err();
+    assert_eq!(reserved_word(err), TokenType::Type);
+}
+
+#[test]
+fn calls_and_assignments_are_told_apart_by_the_next_token() {
+    parse("program Main; var x : integer; procedure P(a : integer); begin end; begin P(1); x := 2 end.").unwrap();
+}
+
+#[test]
+fn layout_does_not_change_the_ast() {
+    let program = |source: &str| Parser::from(source, LexerOptions::default()).parse().unwrap();
+    let compact = program("program Main; procedure Q(a, b : integer); begin end; begin Q(1, 2) end.");
+    let spread = program("program Main;\nprocedure Q(a, b : integer);\nbegin\nend;\n\nbegin\n  Q(1,\n    2)\nend.");
+    assert_eq!(compact, spread);
+    let formatted = format::format_program(&compact, &FormatOptions::default()).unwrap();
+    assert_eq!(program(&formatted), compact);
+}
@@ -0,0 +1,26 @@
+{ header comment }
+program   Comments ; // the program
+
+
+var a,b,c : integer; { three }
+    longname, anotherlongname, yetanotherlongname, andonemore : real;
+
+procedure Foo(x, y : integer); (* takes two *)
+begin
+  { nothing to do }
+end;
+
+begin
+  { first }
+  c := 0;
+  a := 1 + { inline } 2 *
+       3;   // trailing
+
+
+  b := a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a+a;
+  c := -(a - (b - c)) div 2 mod 3;
+  begin end;
+  Foo(a, // first arg
+      b);
+  (* old style *) c := a
+end.  trailing junk
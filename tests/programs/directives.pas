@@ -0,0 +1,17 @@
+program Directives;
+{$DEFINE FAST}
+var x, y : integer;
+    r : real;
+begin
+{$IFDEF FAST}
+  x := 1;
+{$ELSE}
+  x := 2; { never compiled }
+  this is not even Pascal (* at all *)
+{$ENDIF}
+{$IFNDEF FAST} y := 0; {$ENDIF}
+  {$Q-} y := $7FFFFFFFFFFFFFFF + x; {$Q+}
+  (*$R+*) r := 1.5E3 / 2
+end.
+{$IFDEF AFTER} text after the program {$ENDIF}
+anything goes here
@@ -0,0 +1,11 @@
+program Expressions;
+var first, second, third, fourth : integer;
+    ratio, scaled : real;
+begin
+   first := $FF + %1010 + &17 + 1_000;
+   second := first%10 + first mod 7 - first div 3 * (first - 2) div (first + 2) mod 5;
+   third := -(-(+first)) + second * second - (first + second) * (first - second) + first * second * 3;
+   fourth := ((((first + 1) * 2) - 3) div 4) + ((second - (third div 2)) * (first mod 3 + 1)) - 1;
+   ratio := 1.5E-3 + 2.25 * first / (second + 0.5) - 3e2 / (1 + third * third + fourth * fourth);
+   scaled := ratio * ratio * ratio + ratio * ratio + ratio + first + second + third + fourth + 123456789
+end.
@@ -0,0 +1,37 @@
+program Nested;
+var total : integer;
+
+procedure Outer(n : integer);
+var local : integer;
+
+   procedure Inner(m : integer);
+   var deepest : real;
+
+      procedure Innermost(k : integer);
+      var square : integer;
+      begin
+         square := k * k
+      end;
+
+   begin
+      deepest := m / 2;
+      Innermost(m * 2);
+      Innermost(m)
+   end;
+
+begin
+   local := n + 1;
+   Inner(local);
+   Inner(n)
+end;
+
+procedure Second();
+begin
+   Outer(10)
+end;
+
+begin
+   total := 0;
+   Outer(1);
+   Second()
+end.
@@ -0,0 +1,143 @@
+use pascal_interpreter::{
+    error::RuntimeError,
+    literal::Literal,
+    native::NativeFunction,
+    repl::{Repl, Reply},
+    symbols::Type,
+    LexerOptions,
+};
+
+fn repl() -> Repl {
+    Repl::new(LexerOptions::default())
+}
+
+fn output(repl: &mut Repl, line: &str) -> String {
+    match repl.feed(line) {
+        Reply::Output(output) => output,
+        reply => panic!("{}: {:?}", line, reply)
+    }
+}
+
+// the error of a failing input, without its backtrace
+fn failure(repl: &mut Repl, line: &str) -> RuntimeError {
+    match repl.feed(line) {
+        Reply::Failed(RuntimeError::WithBacktrace(cause, _)) => *cause,
+        Reply::Failed(err) => err,
+        reply => panic!("{}: {:?}", line, reply)
+    }
+}
+
+#[test]
+fn globals_are_kept_between_inputs() {
+    let mut repl = repl();
+    assert_eq!(output(&mut repl, "var x, y : integer;"), "");
+    assert_eq!(output(&mut repl, "x := 3"), "");
+    assert_eq!(output(&mut repl, "y := x * 2;"), "");
+    assert_eq!(output(&mut repl, "x + y"), "9");
+    assert_eq!(output(&mut repl, ":vars"), "x : integer = 3\ny : integer = 6");
+}
+
+#[test]
+fn unset_variables_show_as_unset() {
+    let mut repl = repl();
+    output(&mut repl, "var s : string");
+    assert_eq!(output(&mut repl, "s"), "(unset)");
+    assert_eq!(output(&mut repl, ":vars"), "s : string");
+}
+
+#[test]
+fn blocks_are_read_until_they_are_closed() {
+    let mut repl = repl();
+    output(&mut repl, "var x : integer");
+    assert!(matches!(repl.feed("begin"), Reply::Continue));
+    assert!(repl.is_continuing());
+    assert!(matches!(repl.feed("  x := 1;"), Reply::Continue));
+    assert!(matches!(repl.feed("  x := x + 1"), Reply::Continue));
+    assert_eq!(output(&mut repl, "end"), "");
+    assert!(!repl.is_continuing());
+    assert_eq!(repl.history().last().map(String::as_str), Some("begin\n  x := 1;\n  x := x + 1\nend"));
+    assert_eq!(output(&mut repl, "x"), "2");
+}
+
+#[test]
+fn procedures_are_read_until_their_body_is_closed() {
+    let mut repl = repl();
+    assert!(matches!(repl.feed("procedure Twice(a : integer);"), Reply::Continue));
+    assert!(matches!(repl.feed("var b : integer;"), Reply::Continue));
+    assert!(matches!(repl.feed("begin"), Reply::Continue));
+    assert!(matches!(repl.feed("  b := a * 2"), Reply::Continue));
+    assert_eq!(output(&mut repl, "end;"), "");
+    assert_eq!(output(&mut repl, "Twice(4)"), "");
+    assert_eq!(failure(&mut repl, "Twice(1.5)"), RuntimeError::ArgumentTypeMismatch("Twice".to_owned(), 1, Type::Integer, Type::Float));
+}
+
+#[test]
+fn open_comments_are_continued() {
+    let mut repl = repl();
+    assert!(matches!(repl.feed("1 + { a comment"), Reply::Continue));
+    assert_eq!(output(&mut repl, "that ends here } 2"), "3");
+}
+
+#[test]
+fn failures_leave_the_session_usable() {
+    let mut repl = repl();
+    output(&mut repl, "var x : integer");
+    output(&mut repl, "x := 3");
+    assert_eq!(failure(&mut repl, "x := x div 0"), RuntimeError::DivisionByZero);
+    assert_eq!(failure(&mut repl, "y := 1"), RuntimeError::UndefinedVariable("y".to_owned()));
+    assert!(matches!(failure(&mut repl, "x := ;"), RuntimeError::ExpectedExpression(..)));
+    assert_eq!(output(&mut repl, "x"), "3");
+}
+
+#[test]
+fn commands_describe_expressions() {
+    let mut repl = repl();
+    output(&mut repl, "var x : integer");
+    assert_eq!(output(&mut repl, ":type x / 2"), "real");
+    assert_eq!(output(&mut repl, ":type x div 2"), "integer");
+    assert!(output(&mut repl, ":ast x + 1").contains('+'));
+    assert!(output(&mut repl, ":help").contains(":vars"));
+    assert_eq!(output(&mut repl, ":frobnicate"), "unknown command :frobnicate, see :help");
+    assert!(matches!(repl.feed(":quit"), Reply::Quit));
+}
+
+#[test]
+fn commands_take_only_expressions() {
+    let mut repl = repl();
+    repl.define_native(NativeFunction::procedure("Beep", &[], |_| Ok(())));
+    assert_eq!(failure(&mut repl, ":type var z : integer"), RuntimeError::IllformedVarExpr);
+    assert_eq!(failure(&mut repl, ":ast procedure P; begin end"), RuntimeError::IllformedVarExpr);
+    assert_eq!(failure(&mut repl, ":type Beep()"), RuntimeError::NotAFunction("Beep".to_owned()));
+    assert_eq!(output(&mut repl, ":vars"), "");
+    assert_eq!(failure(&mut repl, "z"), RuntimeError::UndefinedVariable("z".to_owned()));
+}
+
+#[test]
+fn history_lists_inputs_and_commands() {
+    let mut repl = repl();
+    output(&mut repl, "var x : integer");
+    output(&mut repl, ":vars");
+    assert_eq!(output(&mut repl, ":history"), "   1  var x : integer\n   2  :vars");
+}
+
+#[test]
+fn reset_forgets_declarations_but_keeps_natives() {
+    let mut repl = repl();
+    repl.define_native(NativeFunction::function("Answer", &[], Type::Integer, |_| Ok(Literal::from_int(42))));
+    output(&mut repl, "var x : integer");
+    output(&mut repl, "x := Answer()");
+    assert_eq!(output(&mut repl, "x"), "42");
+    assert_eq!(output(&mut repl, ":reset"), "");
+    assert_eq!(failure(&mut repl, "x"), RuntimeError::UndefinedVariable("x".to_owned()));
+    assert_eq!(output(&mut repl, "Answer() + 1"), "43");
+}
+
+#[test]
+fn failures_read_as_messages() {
+    let mut repl = repl();
+    output(&mut repl, "var x : integer");
+    assert_eq!(failure(&mut repl, "x := ;").to_string(), "1:6: expected an expression, found ';'");
+    assert_eq!(failure(&mut repl, "x := (1;").to_string(), "1:8: expected ')', found ';'");
+    assert_eq!(failure(&mut repl, "x := x + 1").to_string(), "an operand or argument has no value");
+    assert_eq!(failure(&mut repl, "x := 1 div 0").to_string(), "division by zero");
+}